@@ -0,0 +1,271 @@
+use crate::manhattan::{
+    banded_fitting_alignment, banded_global_alignment, fitting_alignment, global_alignment,
+    linear_space_alignment, local_alignment, overlap_alignment, AlignmentMode, Band, GapModel,
+    ScoringScheme, SubstitutionMatrix,
+};
+use crate::utils::{print_hms, Fasta};
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct AlignArgs {
+    #[arg(long, required = true, value_name = "first_file")]
+    first_file: String,
+
+    /// Records aligned against those of the first file; without it the
+    /// records of the first file are aligned with each other.
+    #[arg(long, required = false, value_name = "second_file")]
+    second_file: Option<String>,
+
+    /// global, local, fitting, overlap, or affine for global alignment with
+    /// affine gaps.
+    #[arg(long, required = false, value_name = "MODE", default_value = "global")]
+    mode: String,
+
+    /// Align every record of the first file with every record of the second,
+    /// instead of pairing them in order.
+    #[arg(long, required = false)]
+    all_pairs: bool,
+
+    /// Built-in matrix name (BLOSUM45, BLOSUM62, BLOSUM80, PAM250) or NCBI
+    /// matrix file, replacing the match and mismatch scores.
+    #[arg(long, required = false, value_name = "MATRIX")]
+    matrix: Option<String>,
+
+    #[arg(long, required = false, value_parser = value_parser!(i32), default_value = "1")]
+    match_reward: i32,
+
+    #[arg(long, required = false, value_parser = value_parser!(i32), default_value = "1")]
+    mismatch_penalty: i32,
+
+    #[arg(long, required = false, value_parser = value_parser!(i32), default_value = "2")]
+    indel_penalty: i32,
+
+    /// Penalty of the first position of a gap; switches to affine gaps.
+    #[arg(long, required = false, value_parser = value_parser!(i32), requires = "gap_extend")]
+    gap_open: Option<i32>,
+
+    /// Penalty of each further position of a gap.
+    #[arg(long, required = false, value_parser = value_parser!(i32), requires = "gap_open")]
+    gap_extend: Option<i32>,
+
+    /// Global alignment in linear space, for long sequences.
+    #[arg(long, required = false)]
+    linear_space: bool,
+
+    /// Diagonal band width for global or fitting alignment of similar
+    /// sequences, or adaptive to widen it until it holds the best alignment.
+    #[arg(long, required = false, value_name = "BAND")]
+    band: Option<String>,
+
+    /// Alignment columns per block.
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "60")]
+    width: usize,
+
+    /// Write the alignments here instead of to standard output.
+    #[arg(long, required = false, value_name = "OUTPUT")]
+    output: Option<String>,
+}
+
+impl AlignArgs {
+    pub fn get_first_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.first_file.to_owned())
+    }
+
+    pub fn get_second_file(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.second_file.to_owned())
+    }
+
+    pub fn get_mode(&self) -> Result<AlignmentMode, Box<dyn Error>> {
+        let affine = self.mode.eq_ignore_ascii_case("affine");
+        if affine && self.gap_open.is_none() {
+            return Err("Affine mode needs --gap-open and --gap-extend".into());
+        }
+        let mode = if affine {
+            AlignmentMode::Global
+        } else {
+            self.mode.parse::<AlignmentMode>()?
+        };
+        if self.linear_space && (mode != AlignmentMode::Global || self.gap_open.is_some()) {
+            return Err("Linear space is only supported for global linear-gap alignment".into());
+        }
+        Ok(mode)
+    }
+
+    pub fn get_band(&self) -> Result<Option<Band>, Box<dyn Error>> {
+        let Some(band) = &self.band else {
+            return Ok(None);
+        };
+        let mode = self.get_mode()?;
+        if !matches!(mode, AlignmentMode::Global | AlignmentMode::Fitting)
+            || self.gap_open.is_some()
+            || self.linear_space
+        {
+            return Err(
+                "A band is only supported for global or fitting linear-gap alignment".into(),
+            );
+        }
+        Ok(Some(band.parse::<Band>()?))
+    }
+
+    pub fn get_scoring(&self) -> Result<ScoringScheme<i32>, Box<dyn Error>> {
+        let gaps = match (self.gap_open, self.gap_extend) {
+            (Some(opening), Some(extension)) => GapModel::Affine { opening, extension },
+            _ => GapModel::Linear(self.indel_penalty),
+        };
+        let Some(matrix) = &self.matrix else {
+            return Ok(ScoringScheme::match_mismatch(
+                self.match_reward,
+                self.mismatch_penalty,
+                gaps,
+            ));
+        };
+        let matrix = if Path::new(matrix).is_file() {
+            SubstitutionMatrix::read_file(matrix)?
+        } else {
+            SubstitutionMatrix::builtin(matrix)?
+        };
+        Ok(ScoringScheme::with_matrix(matrix, gaps))
+    }
+
+    pub fn get_width(&self) -> Result<usize, Box<dyn Error>> {
+        if self.width == 0 {
+            return Err("Block width must be positive".into());
+        }
+        Ok(self.width)
+    }
+
+    pub fn get_output(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.output.to_owned())
+    }
+}
+
+/// Prints each aligned pair: titles, summary, CIGAR and the alignment in
+/// blocks of `--width` columns, marking identities with `|` and other positive
+/// scores with `+`.
+pub fn run_align(args: AlignArgs) -> Result<(), Box<dyn Error>> {
+    let read = |file: &str| -> Result<Vec<Fasta>, Box<dyn Error>> {
+        Ok(Fasta::read_file(file)?.iter().map(|f| f.upper()).collect())
+    };
+    let first = read(&args.get_first_file()?)?;
+    let second = match args.get_second_file()? {
+        Some(file) => Some(read(&file)?),
+        None => None,
+    };
+    let pairs = record_pairs(&first, second.as_deref(), args.all_pairs)?;
+    let mode = args.get_mode()?;
+    let scoring = args.get_scoring()?;
+    let width = args.get_width()?;
+    let band = args.get_band()?;
+
+    let start = Instant::now();
+    let mut out: Box<dyn Write> = match args.get_output()? {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    for (s, t) in pairs {
+        let result = match (mode, band) {
+            (AlignmentMode::Global, Some(band)) => {
+                banded_global_alignment(&s.text, &t.text, &scoring, band)?
+            }
+            (AlignmentMode::Fitting, Some(band)) => {
+                banded_fitting_alignment(&s.text, &t.text, &scoring, band)?
+            }
+            (AlignmentMode::Global, _) if args.linear_space => {
+                linear_space_alignment(&s.text, &t.text, &scoring)?
+            }
+            (AlignmentMode::Global, _) => global_alignment(&s.text, &t.text, &scoring)?,
+            (AlignmentMode::Local, _) => local_alignment(&s.text, &t.text, &scoring)?,
+            (AlignmentMode::Fitting, _) => fitting_alignment(&s.text, &t.text, &scoring)?,
+            (AlignmentMode::Overlap, _) => overlap_alignment(&s.text, &t.text, &scoring)?,
+        };
+        writeln!(out, ">{} vs {}", s.title, t.title)?;
+        writeln!(out, "{}", result.summary())?;
+        writeln!(out, "CIGAR: {}\n", result.cigar()?)?;
+        let marks = |a, b| match scoring.score(a, b) {
+            _ if a == b => '|',
+            score if score > 0 => '+',
+            _ => ' ',
+        };
+        write!(out, "{}", result.blocks(width, marks))?;
+    }
+    out.flush()?;
+    if args.output.is_some() {
+        print_hms(&start);
+    }
+    Ok(())
+}
+
+/// Records to align: the two files paired in order or all against all, or
+/// every pair of records of a single file.
+fn record_pairs<'a>(
+    first: &'a [Fasta],
+    second: Option<&'a [Fasta]>,
+    all_pairs: bool,
+) -> Result<Vec<(&'a Fasta, &'a Fasta)>, Box<dyn Error>> {
+    let Some(second) = second else {
+        if !all_pairs && first.len() != 2 {
+            return Err("Give a second file, two records, or --all-pairs".into());
+        }
+        return Ok((0..first.len())
+            .flat_map(|i| (i + 1..first.len()).map(move |j| (&first[i], &first[j])))
+            .collect());
+    };
+    if all_pairs {
+        return Ok(first
+            .iter()
+            .flat_map(|s| second.iter().map(move |t| (s, t)))
+            .collect());
+    }
+    if first.len() != second.len() {
+        return Err(format!(
+            "The files hold {} and {} records; pair them with --all-pairs",
+            first.len(),
+            second.len()
+        )
+        .into());
+    }
+    Ok(first.iter().zip(second).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::align::record_pairs;
+    use crate::utils::Fasta;
+    use std::error::Error;
+
+    #[test]
+    fn test_record_pairs1() -> Result<(), Box<dyn Error>> {
+        let records = ["a", "b", "c"].map(|t| Fasta::new(t, "ACGT"));
+        let titles = |pairs: Vec<(&Fasta, &Fasta)>| {
+            pairs
+                .iter()
+                .map(|(s, t)| format!("{}{}", s.title, t.title))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles(record_pairs(&records, None, true)?),
+            vec!["ab", "ac", "bc"]
+        );
+        assert_eq!(
+            titles(record_pairs(&records[..2], None, false)?),
+            vec!["ab"]
+        );
+        assert!(record_pairs(&records, None, false).is_err());
+        assert_eq!(
+            titles(record_pairs(&records[..2], Some(&records[1..]), false)?),
+            vec!["ab", "bc"]
+        );
+        assert_eq!(
+            titles(record_pairs(&records[..1], Some(&records[1..]), true)?),
+            vec!["ab", "ac"]
+        );
+        assert!(record_pairs(&records, Some(&records[1..]), false).is_err());
+        Ok(())
+    }
+}
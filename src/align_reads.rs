@@ -0,0 +1,127 @@
+use crate::bwt::FMIndex;
+use crate::mapping::{AlignerOptions, Extension, ReadAligner, SamRecord, SamWriter};
+use crate::utils::{print_hms, Fasta, SequenceReader, IUPAC_ALPHABET};
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct AlignReadsArgs {
+    #[arg(long, required = true, value_name = "genome_file")]
+    genome_file: String,
+
+    #[arg(long, required = true, value_name = "reads_file")]
+    reads_file: String,
+
+    /// Index built by `index` from the same genome file; built in memory when
+    /// omitted.
+    #[arg(long, required = false, value_name = "INDEX")]
+    index: Option<String>,
+
+    /// Write the placements to this SAM file instead of printing them.
+    #[arg(long, required = false, value_name = "SAM")]
+    sam: Option<String>,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "20")]
+    seed_length: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "10")]
+    seed_step: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "5")]
+    max_candidates: usize,
+
+    #[arg(long, required = false, value_name = "MODE", default_value = "fitting")]
+    extension: String,
+
+    #[arg(long, required = false, value_parser = value_parser!(i32), default_value = "1")]
+    match_reward: i32,
+
+    #[arg(long, required = false, value_parser = value_parser!(i32), default_value = "1")]
+    mismatch_penalty: i32,
+
+    #[arg(long, required = false, value_parser = value_parser!(i32), default_value = "2")]
+    indel_penalty: i32,
+}
+
+impl AlignReadsArgs {
+    pub fn get_genome_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.genome_file.to_owned())
+    }
+
+    pub fn get_reads_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.reads_file.to_owned())
+    }
+
+    pub fn get_options(&self) -> Result<AlignerOptions, Box<dyn Error>> {
+        Ok(AlignerOptions {
+            seed_len: self.seed_length,
+            seed_step: self.seed_step,
+            max_candidates: self.max_candidates,
+            extension: self.extension.parse::<Extension>()?,
+            match_reward: self.match_reward,
+            mismatch_penalty: self.mismatch_penalty,
+            indel_penalty: self.indel_penalty,
+            ..AlignerOptions::default()
+        })
+    }
+}
+
+/// Prints one line per read: title, record, 0-based offset, strand, score and
+/// CIGAR, with `*` fields for reads that could not be placed. With `--sam` the
+/// reads are written as SAM records instead.
+pub fn run_align_reads(args: AlignReadsArgs) -> Result<(), Box<dyn Error>> {
+    let reference = Fasta::read_file(args.get_genome_file()?)?
+        .iter()
+        .map(|f| f.upper())
+        .collect::<Vec<_>>();
+    // Reads are streamed, so that FASTQ qualities reach the SAM records
+    let reads = SequenceReader::open(args.get_reads_file()?)?;
+
+    let start = Instant::now();
+    let index = match &args.index {
+        Some(file) => FMIndex::read(file)?,
+        None => FMIndex::from_records(&reference, &IUPAC_ALPHABET, 32)?,
+    };
+    let aligner = ReadAligner::new(&index, &reference, args.get_options()?)?;
+    let mut sam = match &args.sam {
+        Some(file) => Some(SamWriter::create(file, index.records())?),
+        None => None,
+    };
+    for read in reads {
+        let read = read?.upper();
+        let alignment = aligner.align(&read.text)?;
+        if let Some(writer) = sam.as_mut() {
+            let record = match &alignment {
+                Some(hit) => SamRecord::from_alignment(
+                    &read.title,
+                    &read.text,
+                    read.quality.as_deref(),
+                    hit,
+                    index.records(),
+                )?,
+                None => SamRecord::unmapped(&read.title, &read.text, read.quality.as_deref()),
+            };
+            writer.write(&record)?;
+            continue;
+        }
+        match alignment {
+            Some(hit) => println!(
+                "{}\t{}\t{}\t{}\t{}\t{}",
+                read.title,
+                index.records().name(hit.record),
+                hit.offset,
+                hit.strand,
+                hit.score,
+                hit.cigar
+            ),
+            None => println!("{}\t*\t*\t*\t*\t*", read.title),
+        }
+    }
+    if let Some(writer) = sam {
+        writer.finish()?;
+        print_hms(&start);
+    }
+    Ok(())
+}
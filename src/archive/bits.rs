@@ -0,0 +1,78 @@
+use crate::archive::err::ArchiveError;
+
+/// Packs bits most significant first.
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    pub(crate) fn write(&mut self, code: u32, len: u8) {
+        for i in (0..len).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if code >> i & 1 == 1 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    pub(crate) fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    bits: usize,
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], bits: usize) -> Self {
+        BitReader {
+            bytes,
+            bits: bits.min(bytes.len() * 8),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn read(&mut self) -> Result<u32, ArchiveError> {
+        if self.position >= self.bits {
+            return Err(ArchiveError::Truncated);
+        }
+        let bit = self.bytes[self.position / 8] >> (7 - self.position % 8) & 1;
+        self.position += 1;
+        Ok(bit as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::bits::{BitReader, BitWriter};
+    use std::error::Error;
+
+    #[test]
+    fn test_bits1() -> Result<(), Box<dyn Error>> {
+        let mut writer = BitWriter::default();
+        writer.write(0b101, 3);
+        writer.write(0b1_1111_0000, 9);
+        assert_eq!(writer.bits(), 12);
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes, vec![0b1011_1111, 0b0000_0000]);
+        let mut reader = BitReader::new(&bytes, 12);
+        let bits = (0..12)
+            .map(|_| reader.read())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(bits, vec![1, 0, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0]);
+        assert!(reader.read().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use crate::archive::bits::{BitReader, BitWriter};
+use crate::archive::bytes::{put_u32, put_u64, ByteReader};
+use crate::archive::crc32::crc32;
+use crate::archive::err::ArchiveError;
+use crate::archive::huffman::{code_lengths, HuffmanDecoder, HuffmanEncoder};
+use crate::archive::mtf::{inverse_move_to_front, move_to_front};
+use crate::archive::rle::{decode_zero_runs, encode_zero_runs, END_OF_BLOCK, SYMBOLS};
+use crate::bwt::{burrows_wheeler_transform_sa_is, inverse_burrows_wheeler_transform};
+use crate::utils::Alphabet;
+use std::error::Error;
+
+const SENTINEL: u8 = b'$';
+
+/// Alphabet of the distinct bytes of `bytes` behind the BWT sentinel.
+fn block_alphabet(bytes: &[u8]) -> Result<Alphabet, Box<dyn Error>> {
+    let mut seen = [false; 256];
+    for &b in bytes {
+        seen[b as usize] = true;
+    }
+    let symbols = std::iter::once(SENTINEL)
+        .chain((SENTINEL + 1..=u8::MAX).filter(|&b| seen[b as usize]))
+        .collect::<Vec<_>>();
+    Alphabet::from_symbols(&symbols)
+}
+
+/// Whether a block can hold `byte`: ASCII above the BWT sentinel.
+pub(crate) fn is_block_byte(byte: u8) -> bool {
+    byte > SENTINEL && byte.is_ascii()
+}
+
+/// Compresses one block of sequence bytes: SA-IS BWT, move-to-front, zero-run
+/// coding and a canonical Huffman code. The block is written as its length,
+/// the CRC-32 of its bytes, one code length per symbol, the payload length in
+/// bits and the payload.
+pub(crate) fn compress_block(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Some(&b) = bytes.iter().find(|&&b| !is_block_byte(b)) {
+        return Err(format!("Sequence byte {:#04x} cannot be archived", b).into());
+    }
+    let text = std::str::from_utf8(bytes)?;
+    let (bwt, _) = burrows_wheeler_transform_sa_is(text, &block_alphabet(bytes)?)?;
+    let symbols = encode_zero_runs(&move_to_front(bwt.as_bytes()));
+
+    let mut frequencies = vec![0; SYMBOLS];
+    for &s in &symbols {
+        frequencies[s as usize] += 1;
+    }
+    let lengths = code_lengths(&frequencies);
+    let encoder = HuffmanEncoder::new(&lengths);
+    let mut writer = BitWriter::default();
+    for &s in &symbols {
+        encoder.encode(s as usize, &mut writer);
+    }
+
+    let mut out = Vec::new();
+    put_u64(&mut out, bytes.len());
+    put_u32(&mut out, crc32(bytes));
+    out.extend_from_slice(&lengths);
+    put_u64(&mut out, writer.bits());
+    out.extend_from_slice(&writer.into_bytes());
+    Ok(out)
+}
+
+/// Reads and decompresses the next block of `reader`.
+pub(crate) fn decompress_block(reader: &mut ByteReader) -> Result<Vec<u8>, Box<dyn Error>> {
+    let len = reader.u64()?;
+    let checksum = reader.u32()?;
+    let lengths = reader.take(SYMBOLS)?;
+    let bits = reader.u64()?;
+    let payload = reader.take(bits.div_ceil(8))?;
+
+    let decoder = HuffmanDecoder::new(lengths)?;
+    let mut bit_reader = BitReader::new(payload, bits);
+    let mut symbols = Vec::new();
+    loop {
+        let symbol = decoder.decode(&mut bit_reader)? as u16;
+        symbols.push(symbol);
+        if symbol == END_OF_BLOCK {
+            break;
+        }
+    }
+    let bwt = inverse_move_to_front(&decode_zero_runs(&symbols)?);
+    if bwt.len() != len + 1 || bwt.iter().filter(|&&b| b == SENTINEL).count() != 1 {
+        return Err(ArchiveError::Corrupt("block BWT".to_string()).into());
+    }
+    let bwt = String::from_utf8(bwt).map_err(|_| ArchiveError::Corrupt("block BWT".to_string()))?;
+    let mut text = inverse_burrows_wheeler_transform(&bwt, &block_alphabet(bwt.as_bytes())?)?;
+    text.pop();
+    if crc32(text.as_bytes()) != checksum {
+        return Err(ArchiveError::Checksum("sequence block".to_string()).into());
+    }
+    Ok(text.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::block::{compress_block, decompress_block};
+    use crate::archive::bytes::ByteReader;
+    use std::error::Error;
+
+    #[test]
+    fn test_block1() -> Result<(), Box<dyn Error>> {
+        let texts: [&[u8]; 4] = [
+            b"GATTACAGATTACAGATTACANNNNNNNNNNacgtacgtRYKM",
+            b"A",
+            b"TTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTTT",
+            b"MKVLAAGIVGLLLAQ*",
+        ];
+        for text in texts {
+            let block = compress_block(text)?;
+            let mut reader = ByteReader::new(&block);
+            assert_eq!(decompress_block(&mut reader)?, text.to_vec());
+            assert!(reader.is_empty());
+        }
+        assert!(compress_block(b"ACGT ACGT").is_err());
+        Ok(())
+    }
+}
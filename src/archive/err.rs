@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("Not a sturgeon archive")]
+    InvalidMagic,
+
+    #[error("Unsupported archive version {0}, expected {1}")]
+    UnsupportedVersion(u32, u32),
+
+    #[error("Archive is truncated")]
+    Truncated,
+
+    #[error("Checksum mismatch in {0}")]
+    Checksum(String),
+
+    #[error("Archive is corrupt: {0}")]
+    Corrupt(String),
+}
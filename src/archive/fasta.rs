@@ -0,0 +1,327 @@
+use crate::archive::block::{compress_block, decompress_block, is_block_byte};
+use crate::archive::bytes::{put_bytes, put_u32, put_u64, ByteReader};
+use crate::archive::crc32::crc32;
+use crate::archive::err::ArchiveError;
+use rayon::prelude::*;
+use std::error::Error;
+
+const MAGIC: &[u8; 8] = b"STURGBWZ";
+const VERSION: u32 = 2;
+
+/// Everything of a FASTA file except its sequence bytes: titles, line widths,
+/// line endings and the sequence bytes that blocks cannot hold, so that
+/// decompression reproduces the file exactly.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Layout {
+    crlf: bool,
+    trailing_newline: bool,
+    records: Vec<RecordLayout>,
+    /// Runs of sequence bytes kept out of the blocks, such as spaces, tabs and
+    /// the carriage returns of mixed line endings, by offset in the sequence.
+    escapes: Vec<(usize, Vec<u8>)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct RecordLayout {
+    title: Vec<u8>,
+    /// Sequence line lengths as (length, repeat count) runs.
+    lines: Vec<(usize, usize)>,
+}
+
+impl RecordLayout {
+    fn push_line(&mut self, len: usize) {
+        match self.lines.last_mut() {
+            Some((last, count)) if *last == len => *count += 1,
+            _ => self.lines.push((len, 1)),
+        }
+    }
+
+    fn sequence_len(&self) -> usize {
+        self.lines.iter().map(|&(len, count)| len * count).sum()
+    }
+}
+
+impl Layout {
+    /// Splits `content` into its layout and the concatenated sequences.
+    fn parse(content: &[u8]) -> Result<(Self, Vec<u8>), Box<dyn Error>> {
+        let mut layout = Layout {
+            trailing_newline: content.ends_with(b"\n"),
+            ..Layout::default()
+        };
+        let content = content.strip_suffix(b"\n").unwrap_or(content);
+        let mut lines = if content.is_empty() {
+            Vec::new()
+        } else {
+            content.split(|&b| b == b'\n').collect::<Vec<_>>()
+        };
+        // The last line has no line break of its own without a final newline
+        let terminated = if layout.trailing_newline {
+            lines.len()
+        } else {
+            lines.len().saturating_sub(1)
+        };
+        layout.crlf = terminated > 0 && lines[..terminated].iter().all(|l| l.ends_with(b"\r"));
+        if layout.crlf {
+            lines[..terminated]
+                .iter_mut()
+                .for_each(|l| *l = &l[..l.len() - 1]);
+        }
+
+        let mut sequence = Vec::with_capacity(content.len());
+        for line in lines {
+            if let Some(title) = line.strip_prefix(b">") {
+                layout.records.push(RecordLayout {
+                    title: title.to_vec(),
+                    lines: Vec::new(),
+                });
+            } else {
+                let record = layout
+                    .records
+                    .last_mut()
+                    .ok_or("FASTA content does not start with a '>' title line")?;
+                record.push_line(line.len());
+                sequence.extend_from_slice(line);
+            }
+        }
+        layout.escapes = extract_escapes(&mut sequence);
+        Ok((layout, sequence))
+    }
+
+    /// Interleaves titles and the lines of `sequence` back into FASTA content.
+    fn render(&self, sequence: &[u8]) -> Result<Vec<u8>, ArchiveError> {
+        let sequence = &insert_escapes(sequence, &self.escapes)?;
+        let total = self.records.iter().map(|r| r.sequence_len()).sum::<usize>();
+        if total != sequence.len() {
+            return Err(ArchiveError::Corrupt("sequence length".to_string()));
+        }
+        let newline: &[u8] = if self.crlf { b"\r\n" } else { b"\n" };
+        let mut out = Vec::with_capacity(sequence.len() + sequence.len() / 50);
+        let mut offset = 0;
+        for record in &self.records {
+            out.push(b'>');
+            out.extend_from_slice(&record.title);
+            out.extend_from_slice(newline);
+            for &(len, count) in &record.lines {
+                for _ in 0..count {
+                    out.extend_from_slice(&sequence[offset..offset + len]);
+                    out.extend_from_slice(newline);
+                    offset += len;
+                }
+            }
+        }
+        if !self.trailing_newline {
+            out.truncate(out.len().saturating_sub(newline.len()));
+        }
+        Ok(out)
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.push(self.crlf as u8);
+        out.push(self.trailing_newline as u8);
+        put_u64(out, self.records.len());
+        for record in &self.records {
+            put_bytes(out, &record.title);
+            put_u64(out, record.lines.len());
+            for &(len, count) in &record.lines {
+                put_u64(out, len);
+                put_u64(out, count);
+            }
+        }
+        put_u64(out, self.escapes.len());
+        for (offset, bytes) in &self.escapes {
+            put_u64(out, *offset);
+            put_bytes(out, bytes);
+        }
+        let checksum = crc32(&out[start..]);
+        put_u32(out, checksum);
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self, ArchiveError> {
+        let start = reader.position();
+        let mut layout = Layout {
+            crlf: reader.u8()? == 1,
+            trailing_newline: reader.u8()? == 1,
+            records: Vec::new(),
+            escapes: Vec::new(),
+        };
+        for _ in 0..reader.u64()? {
+            let mut record = RecordLayout {
+                title: reader.bytes()?.to_vec(),
+                lines: Vec::new(),
+            };
+            for _ in 0..reader.u64()? {
+                record.lines.push((reader.u64()?, reader.u64()?));
+            }
+            layout.records.push(record);
+        }
+        for _ in 0..reader.u64()? {
+            layout
+                .escapes
+                .push((reader.u64()?, reader.bytes()?.to_vec()));
+        }
+        let expected = crc32(reader.read_since(start));
+        if reader.u32()? != expected {
+            return Err(ArchiveError::Checksum("record layout".to_string()));
+        }
+        Ok(layout)
+    }
+}
+
+/// Removes the bytes that blocks cannot hold from `sequence`, returning them
+/// as runs by their offset in the original sequence.
+fn extract_escapes(sequence: &mut Vec<u8>) -> Vec<(usize, Vec<u8>)> {
+    let mut escapes: Vec<(usize, Vec<u8>)> = Vec::new();
+    let mut kept = 0;
+    for i in 0..sequence.len() {
+        let byte = sequence[i];
+        if is_block_byte(byte) {
+            sequence[kept] = byte;
+            kept += 1;
+            continue;
+        }
+        match escapes.last_mut() {
+            Some((offset, bytes)) if *offset + bytes.len() == i => bytes.push(byte),
+            _ => escapes.push((i, vec![byte])),
+        }
+    }
+    sequence.truncate(kept);
+    escapes
+}
+
+/// Puts the runs of [`extract_escapes`] back into `sequence`.
+fn insert_escapes(sequence: &[u8], escapes: &[(usize, Vec<u8>)]) -> Result<Vec<u8>, ArchiveError> {
+    let escaped = escapes.iter().map(|(_, bytes)| bytes.len()).sum::<usize>();
+    let mut out = Vec::with_capacity(sequence.len() + escaped);
+    let mut taken = 0;
+    for (offset, bytes) in escapes {
+        // Sequence bytes between the previous run and this one
+        let gap = offset
+            .checked_sub(out.len())
+            .filter(|&gap| taken + gap <= sequence.len())
+            .ok_or_else(|| ArchiveError::Corrupt("escaped bytes".to_string()))?;
+        out.extend_from_slice(&sequence[taken..taken + gap]);
+        out.extend_from_slice(bytes);
+        taken += gap;
+    }
+    out.extend_from_slice(&sequence[taken..]);
+    Ok(out)
+}
+
+/// Compresses FASTA `content` into an archive: a header, the record layout
+/// and the concatenated sequences cut into blocks of `block_size` bytes,
+/// which are compressed in parallel.
+pub fn compress_fasta(content: &[u8], block_size: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    if block_size == 0 {
+        return Err("Block size must be positive".into());
+    }
+    let (layout, sequence) = Layout::parse(content)?;
+    let blocks = sequence
+        .par_chunks(block_size)
+        .map(|chunk| compress_block(chunk).map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = MAGIC.to_vec();
+    put_u32(&mut out, VERSION);
+    layout.write(&mut out);
+    put_u64(&mut out, blocks.len());
+    for block in blocks {
+        out.extend_from_slice(&block);
+    }
+    Ok(out)
+}
+
+pub fn decompress_fasta(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut reader = ByteReader::new(data);
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(ArchiveError::InvalidMagic.into());
+    }
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(ArchiveError::UnsupportedVersion(version, VERSION).into());
+    }
+    let layout = Layout::read(&mut reader)?;
+    let mut sequence = Vec::new();
+    for _ in 0..reader.u64()? {
+        sequence.extend_from_slice(&decompress_block(&mut reader)?);
+    }
+    if !reader.is_empty() {
+        return Err(ArchiveError::Corrupt("trailing bytes".to_string()).into());
+    }
+    Ok(layout.render(&sequence)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::err::ArchiveError;
+    use crate::archive::fasta::{compress_fasta, decompress_fasta};
+    use std::error::Error;
+
+    #[test]
+    fn test_archive1() -> Result<(), Box<dyn Error>> {
+        let contents: [&[u8]; 6] = [
+            b">chr1 first record\nACGTACGTAC\nGTACGTACGT\nACG\n>chr2\nttgcaNNNNN\nTTGCA\n",
+            b">empty\n>irregular widths\nACG\nACGTACGT\n\nAC\n>last\nGATTACA",
+            b">crlf\r\nACGTAC\r\nGTA\r\n>x\r\n",
+            b">only title",
+            b"",
+            b">p1 protein\nMKVLAAGIVGLLLAQ*\n",
+        ];
+        for content in contents {
+            for block_size in [1, 4, 1 << 20] {
+                let archive = compress_fasta(content, block_size)?;
+                assert_eq!(decompress_fasta(&archive)?, content.to_vec());
+            }
+        }
+        assert!(compress_fasta(b"ACGT\n>chr1\nACGT\n", 16).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive3() -> Result<(), Box<dyn Error>> {
+        let contents: [&[u8]; 5] = [
+            b">crlf without final newline\r\nACGTAC\r\nGTA",
+            b">mixed\r\nACGTAC\nGTA\r\n>y\nTT\n",
+            b">spaces\nAC GT\tAC\n  GG$#\n",
+            b">final cr\r\nACGT\r",
+            b">bytes\nAC\x00\x01GT\xff\n\r\n",
+        ];
+        for content in contents {
+            for block_size in [1, 3, 1 << 20] {
+                let archive = compress_fasta(content, block_size)?;
+                assert_eq!(decompress_fasta(&archive)?, content.to_vec());
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive2() -> Result<(), Box<dyn Error>> {
+        let content = b">chr1\nGATTACAGATTACAGATTACA\n";
+        let archive = compress_fasta(content, 1 << 20)?;
+
+        // Header, then the layout with the title at byte 30, the escape count
+        // and its checksum, the block count and the block length, then the
+        // block checksum
+        let checksum_error = |position: usize| {
+            let mut corrupt = archive.clone();
+            corrupt[position] ^= 0x01;
+            matches!(
+                decompress_fasta(&corrupt).unwrap_err().downcast_ref(),
+                Some(ArchiveError::Checksum(_))
+            )
+        };
+        assert_eq!(&archive[30..34], b"chr1");
+        assert!(checksum_error(30));
+        assert!(checksum_error(86));
+
+        assert!(matches!(
+            decompress_fasta(b"GZIP")
+                .unwrap_err()
+                .downcast_ref::<ArchiveError>(),
+            Some(ArchiveError::InvalidMagic)
+        ));
+        assert!(decompress_fasta(&archive[..archive.len() - 2]).is_err());
+        Ok(())
+    }
+}
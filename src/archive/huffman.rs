@@ -0,0 +1,186 @@
+use crate::archive::bits::{BitReader, BitWriter};
+use crate::archive::err::ArchiveError;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+pub(crate) const MAX_CODE_LEN: u8 = 20;
+
+/// Huffman code lengths for `frequencies`, 0 for unused symbols. When the
+/// tree gets deeper than `MAX_CODE_LEN` the frequencies are flattened and the
+/// tree rebuilt, which costs little on real data.
+pub(crate) fn code_lengths(frequencies: &[usize]) -> Vec<u8> {
+    let mut frequencies = frequencies.to_vec();
+    loop {
+        let lengths = unlimited_code_lengths(&frequencies);
+        if lengths.iter().all(|&l| l <= MAX_CODE_LEN) {
+            return lengths;
+        }
+        for f in frequencies.iter_mut().filter(|f| **f > 0) {
+            *f = (*f / 2).max(1);
+        }
+    }
+}
+
+fn unlimited_code_lengths(frequencies: &[usize]) -> Vec<u8> {
+    let mut lengths = vec![0; frequencies.len()];
+    let used = (0..frequencies.len())
+        .filter(|&s| frequencies[s] > 0)
+        .collect::<Vec<_>>();
+    if used.len() == 1 {
+        lengths[used[0]] = 1;
+        return lengths;
+    }
+
+    // Nodes are the symbols followed by the internal nodes; ties break on the
+    // node index so that the code is deterministic
+    let mut parents = vec![usize::MAX; frequencies.len()];
+    let mut heap = used
+        .iter()
+        .map(|&s| Reverse((frequencies[s], s)))
+        .collect::<BinaryHeap<_>>();
+    while heap.len() > 1 {
+        let Reverse((w1, a)) = heap.pop().unwrap();
+        let Reverse((w2, b)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        parents[a] = node;
+        parents[b] = node;
+        heap.push(Reverse((w1 + w2, node)));
+    }
+    // Internal nodes are created after their children, so walking them in
+    // reverse fills in depths from the root down
+    let mut depths = vec![0u8; parents.len()];
+    for node in (0..parents.len()).rev() {
+        if parents[node] != usize::MAX {
+            depths[node] = depths[parents[node]] + 1;
+        }
+    }
+    for &s in &used {
+        lengths[s] = depths[s];
+    }
+    lengths
+}
+
+/// Canonical codes: symbols sorted by (length, symbol) get consecutive codes.
+fn canonical_order(lengths: &[u8]) -> Vec<usize> {
+    let mut order = (0..lengths.len())
+        .filter(|&s| lengths[s] > 0)
+        .collect::<Vec<_>>();
+    order.sort_by_key(|&s| (lengths[s], s));
+    order
+}
+
+pub(crate) struct HuffmanEncoder {
+    codes: Vec<(u32, u8)>,
+}
+
+impl HuffmanEncoder {
+    pub(crate) fn new(lengths: &[u8]) -> Self {
+        let mut codes = vec![(0, 0); lengths.len()];
+        let (mut code, mut previous) = (0u32, 0u8);
+        for s in canonical_order(lengths) {
+            code <<= lengths[s] - previous;
+            previous = lengths[s];
+            codes[s] = (code, lengths[s]);
+            code += 1;
+        }
+        HuffmanEncoder { codes }
+    }
+
+    pub(crate) fn encode(&self, symbol: usize, writer: &mut BitWriter) {
+        let (code, len) = self.codes[symbol];
+        writer.write(code, len);
+    }
+}
+
+pub(crate) struct HuffmanDecoder {
+    order: Vec<usize>,
+    /// Per code length: first code, index of its symbol in `order`, count.
+    levels: Vec<(u32, usize, usize)>,
+}
+
+impl HuffmanDecoder {
+    pub(crate) fn new(lengths: &[u8]) -> Result<Self, ArchiveError> {
+        if lengths.iter().any(|&l| l > MAX_CODE_LEN) {
+            return Err(ArchiveError::Corrupt("Huffman code too long".to_string()));
+        }
+        let order = canonical_order(lengths);
+        let mut levels = vec![(0, 0, 0); MAX_CODE_LEN as usize + 1];
+        let (mut code, mut index) = (0u32, 0);
+        for (len, level) in levels.iter_mut().enumerate().skip(1) {
+            code <<= 1;
+            let count = lengths.iter().filter(|&&l| l as usize == len).count();
+            *level = (code, index, count);
+            code += count as u32;
+            index += count;
+            if code > 1 << len {
+                return Err(ArchiveError::Corrupt(
+                    "Huffman code lengths are oversubscribed".to_string(),
+                ));
+            }
+        }
+        Ok(HuffmanDecoder { order, levels })
+    }
+
+    pub(crate) fn decode(&self, reader: &mut BitReader) -> Result<usize, ArchiveError> {
+        let mut code = 0;
+        for &(first, index, count) in &self.levels[1..] {
+            code = code << 1 | reader.read()?;
+            let offset = code.wrapping_sub(first) as usize;
+            if offset < count {
+                return Ok(self.order[index + offset]);
+            }
+        }
+        Err(ArchiveError::Corrupt("invalid Huffman code".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::bits::{BitReader, BitWriter};
+    use crate::archive::huffman::{code_lengths, HuffmanDecoder, HuffmanEncoder, MAX_CODE_LEN};
+    use std::error::Error;
+
+    #[test]
+    fn test_huffman1() -> Result<(), Box<dyn Error>> {
+        let frequencies = [45, 13, 12, 16, 9, 5, 0];
+        let lengths = code_lengths(&frequencies);
+        assert_eq!(lengths, vec![1, 3, 3, 3, 4, 4, 0]);
+
+        let symbols = [0, 1, 2, 3, 4, 5, 0, 0, 5];
+        let encoder = HuffmanEncoder::new(&lengths);
+        let mut writer = BitWriter::default();
+        for &s in &symbols {
+            encoder.encode(s, &mut writer);
+        }
+        let bits = writer.bits();
+        let bytes = writer.into_bytes();
+        let decoder = HuffmanDecoder::new(&lengths)?;
+        let mut reader = BitReader::new(&bytes, bits);
+        let decoded = symbols
+            .iter()
+            .map(|_| decoder.decode(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(decoded, symbols.to_vec());
+        Ok(())
+    }
+
+    #[test]
+    fn test_huffman2() {
+        // Fibonacci frequencies give the deepest possible tree
+        let mut frequencies = vec![1, 1];
+        while frequencies.len() < 40 {
+            let n = frequencies.len();
+            frequencies.push(frequencies[n - 1] + frequencies[n - 2]);
+        }
+        let lengths = code_lengths(&frequencies);
+        assert!(lengths.iter().all(|&l| (1..=MAX_CODE_LEN).contains(&l)));
+        // Kraft's inequality holds with equality for a full tree
+        let kraft = lengths
+            .iter()
+            .map(|&l| 1.0 / (1u64 << l) as f64)
+            .sum::<f64>();
+        assert!((kraft - 1.0).abs() < 1e-9);
+        assert_eq!(code_lengths(&[0, 7, 0]), vec![0, 1, 0]);
+    }
+}
@@ -0,0 +1,11 @@
+mod bits;
+mod block;
+mod bytes;
+pub(crate) mod crc32;
+mod err;
+mod fasta;
+mod huffman;
+mod mtf;
+mod rle;
+
+pub use fasta::{compress_fasta, decompress_fasta};
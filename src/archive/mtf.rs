@@ -0,0 +1,40 @@
+/// Move-to-front transform: each byte becomes its index in a recency list,
+/// turning the runs of a BWT into runs of zeros.
+pub(crate) fn move_to_front(bytes: &[u8]) -> Vec<u8> {
+    let mut order = (0..=255).collect::<Vec<u8>>();
+    bytes
+        .iter()
+        .map(|&b| {
+            let index = order.iter().position(|&o| o == b).unwrap();
+            order.copy_within(0..index, 1);
+            order[0] = b;
+            index as u8
+        })
+        .collect()
+}
+
+pub(crate) fn inverse_move_to_front(indices: &[u8]) -> Vec<u8> {
+    let mut order = (0..=255).collect::<Vec<u8>>();
+    indices
+        .iter()
+        .map(|&i| {
+            let b = order[i as usize];
+            order.copy_within(0..i as usize, 1);
+            order[0] = b;
+            b
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::mtf::{inverse_move_to_front, move_to_front};
+
+    #[test]
+    fn test_move_to_front1() {
+        let bytes = b"AAAC$CCGGGGTTAAAAT";
+        let indices = move_to_front(bytes);
+        assert_eq!(&indices[..8], &[65, 0, 0, 67, 38, 1, 0, 71]);
+        assert_eq!(inverse_move_to_front(&indices), bytes.to_vec());
+    }
+}
@@ -0,0 +1,80 @@
+use crate::archive::err::ArchiveError;
+
+/// Symbols of the zero-run coding: runs of zeros are written in bijective
+/// base 2 with `RUN_A` (digit 1) and `RUN_B` (digit 2), as in bzip2, and every
+/// other byte `b` becomes `b + 1`.
+pub(crate) const RUN_A: u16 = 0;
+pub(crate) const RUN_B: u16 = 1;
+pub(crate) const END_OF_BLOCK: u16 = 257;
+pub(crate) const SYMBOLS: usize = END_OF_BLOCK as usize + 1;
+
+pub(crate) fn encode_zero_runs(bytes: &[u8]) -> Vec<u16> {
+    let mut symbols = Vec::with_capacity(bytes.len() / 2 + 1);
+    let mut run = 0;
+    for &b in bytes.iter().chain(std::iter::once(&1)) {
+        if b == 0 {
+            run += 1;
+            continue;
+        }
+        while run > 0 {
+            if run % 2 == 1 {
+                symbols.push(RUN_A);
+                run = (run - 1) / 2;
+            } else {
+                symbols.push(RUN_B);
+                run = (run - 2) / 2;
+            }
+        }
+        symbols.push(b as u16 + 1);
+    }
+    // The sentinel byte 1 pushed above becomes the end of block
+    *symbols.last_mut().unwrap() = END_OF_BLOCK;
+    symbols
+}
+
+pub(crate) fn decode_zero_runs(symbols: &[u16]) -> Result<Vec<u8>, ArchiveError> {
+    let mut bytes = Vec::with_capacity(symbols.len() * 2);
+    let (mut run, mut weight) = (0, 1);
+    for &symbol in symbols {
+        match symbol {
+            RUN_A | RUN_B => {
+                run += weight * (symbol as usize + 1);
+                weight *= 2;
+                continue;
+            }
+            _ => {
+                bytes.resize(bytes.len() + run, 0);
+                (run, weight) = (0, 1);
+            }
+        }
+        if symbol == END_OF_BLOCK {
+            return Ok(bytes);
+        }
+        bytes.push((symbol - 1) as u8);
+    }
+    Err(ArchiveError::Corrupt("missing end of block".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::rle::{decode_zero_runs, encode_zero_runs, END_OF_BLOCK, RUN_A, RUN_B};
+    use std::error::Error;
+
+    #[test]
+    fn test_zero_runs1() -> Result<(), Box<dyn Error>> {
+        let bytes = [3, 0, 0, 0, 0, 0, 7, 0, 255, 0, 0];
+        let symbols = encode_zero_runs(&bytes);
+        // 5 = 1 + 2 * 2, 1 = 1 and 2 = 2
+        assert_eq!(
+            symbols,
+            vec![4, RUN_A, RUN_B, 8, RUN_A, 256, RUN_B, END_OF_BLOCK]
+        );
+        assert_eq!(decode_zero_runs(&symbols)?, bytes.to_vec());
+        for n in 0..40 {
+            let zeros = vec![0; n];
+            assert_eq!(decode_zero_runs(&encode_zero_runs(&zeros))?, zeros);
+        }
+        assert!(decode_zero_runs(&[4, RUN_A]).is_err());
+        Ok(())
+    }
+}
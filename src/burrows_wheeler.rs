@@ -1,9 +1,15 @@
-use crate::bwt::{fasta_burrows_wheeler_transform, fasta_burrows_wheeler_transform_sa_is};
-use crate::utils::{print_hms, Fasta, DNA_BW_N, DNA_BYTES};
+use crate::bwt::{
+    external_block_size, fasta_burrows_wheeler_transform, in_memory_footprint,
+    records_burrows_wheeler_transform_external, records_burrows_wheeler_transform_sa_is,
+};
+use crate::utils::{print_hms, Alphabet, Fasta, DNA_BYTES};
 use clap::Parser;
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
 use std::time::Instant;
 
 #[derive(Parser)]
@@ -11,18 +17,67 @@ use std::time::Instant;
 pub struct BWTArgs {
     #[arg(long, required = true, value_name = "INPUT")]
     input: String,
+
+    #[arg(long, required = false, value_name = "ALPHABET", default_value = "iupac")]
+    alphabet: String,
+
+    /// Peak memory to stay within, such as 8G; larger inputs are built on disk
+    #[arg(long, required = false, value_name = "BYTES")]
+    memory_budget: Option<String>,
+
+    /// Directory for the intermediate files of the disk-backed construction
+    #[arg(long, required = false, value_name = "DIR")]
+    temp_dir: Option<String>,
+
+    #[arg(long, required = false, value_name = "OUTPUT")]
+    output: Option<String>,
 }
 
 impl BWTArgs {
     pub fn get_input(&self) -> Result<String, Box<dyn Error>> {
         Ok(self.input.to_owned())
     }
+
+    pub fn get_alphabet(&self) -> Result<Alphabet, Box<dyn Error>> {
+        Alphabet::named(&self.alphabet)
+    }
+
+    /// The memory budget in bytes, with an optional K, M or G suffix.
+    pub fn get_memory_budget(&self) -> Result<Option<usize>, Box<dyn Error>> {
+        let Some(budget) = &self.memory_budget else {
+            return Ok(None);
+        };
+        let budget = budget.trim().to_ascii_uppercase();
+        let (digits, unit) = match budget.strip_suffix('B').unwrap_or(&budget) {
+            b if b.ends_with('K') => (&b[..b.len() - 1], 1 << 10),
+            b if b.ends_with('M') => (&b[..b.len() - 1], 1 << 20),
+            b if b.ends_with('G') => (&b[..b.len() - 1], 1 << 30),
+            b => (b, 1),
+        };
+        let bytes = digits
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid memory budget: {}", budget))?;
+        Ok(Some((bytes * unit as f64) as usize))
+    }
+
+    pub fn get_temp_dir(&self) -> Result<PathBuf, Box<dyn Error>> {
+        Ok(self
+            .temp_dir
+            .as_ref()
+            .map_or_else(std::env::temp_dir, PathBuf::from))
+    }
+
+    pub fn get_output(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.output.to_owned())
+    }
 }
 
 pub fn run_bwt(args: BWTArgs) -> Result<(), Box<dyn Error>> {
     let input_file = args.get_input()?;
+    let alphabet = args.get_alphabet()?;
     let fasta = Fasta::read_file(&input_file)?;
-    let chromosomes = fasta.iter().filter(|&f|f.title.contains("reference primary assembly") &&
+    // Consumes the parsed file, so that only the selected records stay in memory
+    let chromosomes = fasta.into_iter().filter(|f|f.title.contains("reference primary assembly") &&
         !f.title.contains("unlocalized genomic contig,") &&
          f.title.contains("Homo sapiens chromosome "))
         .map(|f| f.upper()).collect::<Vec<_>>();
@@ -36,10 +91,33 @@ pub fn run_bwt(args: BWTArgs) -> Result<(), Box<dyn Error>> {
     print_hms(&start);*/
 
     let start = Instant::now();
-    let (bwt_sa, _) = fasta_burrows_wheeler_transform_sa_is(&chromosomes[20], &DNA_BW_N)?;
+    let len = chromosomes.iter().map(|c| c.text.len() + 1).sum::<usize>();
+    let mut output: Box<dyn Write> = match args.get_output()? {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::sink()),
+    };
+    let budget = args.get_memory_budget()?;
+    let records = if budget.is_some_and(|b| in_memory_footprint(len) > b) {
+        let block_size = external_block_size(len, budget.unwrap())?;
+        println!("Building on disk in blocks of {} symbols", block_size);
+        records_burrows_wheeler_transform_external(
+            &chromosomes,
+            &alphabet,
+            block_size,
+            args.get_temp_dir()?,
+            &mut output,
+        )?
+    } else {
+        let (bwt_sa, _, records) =
+            records_burrows_wheeler_transform_sa_is(&chromosomes, &alphabet)?;
+        output.write_all(bwt_sa.as_bytes())?;
+        records
+    };
+    output.flush()?;
     print_hms(&start);
 
-    println!("{}", bwt_sa.len());
+    println!("{} records", records.len());
+    println!("{}", len);
     //let output_file = format!("{}_bwt.txt", input_file.split('.').next().unwrap());
     //fs::write(output_file, bwt).expect("Unable to write file");
     Ok(())
@@ -0,0 +1,202 @@
+use crate::bwt::counts::{char_counts, get_first_col_starts};
+use crate::bwt::fm::{calculate_fm_index, OccurrenceTable, Occurrences};
+use crate::bwt::matching::SuffixArrayRef;
+use crate::bwt::records::RECORD_SEPARATOR;
+use crate::utils::SymbolMap;
+use std::error::Error;
+
+const DOLLAR_SIGN: u8 = b'$';
+
+/// BWT row range `(top, bottom)` with the mismatch count of its prefix.
+type MismatchRange = (usize, usize, usize);
+
+/// Text position of an approximate match with its mismatch count.
+pub type ApproximateMatch = (usize, usize);
+
+/// Backtracking backward search. Every BWT range returned holds suffixes whose
+/// prefix differs from `pattern` in exactly the reported number of positions,
+/// and the ranges of distinct prefixes never overlap.
+pub(crate) fn bw_approximate_matching(
+    first_col_starts: &[usize],
+    occurrences: &impl Occurrences,
+    counts: &[usize],
+    char_map: &impl SymbolMap<u8>,
+    pattern: &str,
+    max_mismatches: usize,
+) -> Result<Vec<MismatchRange>, Box<dyn Error>> {
+    let pattern_symbols = pattern
+        .as_bytes()
+        .iter()
+        .map(|b| {
+            char_map
+                .rank(b)
+                .ok_or(format!("Symbol {} not found in char_map", b))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Substitutions never use the sentinel or record separator, so matches
+    // cannot run off the text or across records
+    let excluded = [DOLLAR_SIGN, RECORD_SEPARATOR]
+        .iter()
+        .filter_map(|b| char_map.rank(b))
+        .collect::<Vec<_>>();
+    let symbols = (0..counts.len())
+        .filter(|s| !excluded.contains(s) && counts[*s] > 0)
+        .collect::<Vec<_>>();
+
+    let mut ranges = Vec::new();
+    let mut stack = vec![(pattern_symbols.len(), 0, occurrences.len() - 1, 0)];
+    while let Some((remaining, top, bottom, mismatches)) = stack.pop() {
+        if remaining == 0 {
+            ranges.push((top, bottom, mismatches));
+            continue;
+        }
+        let expected = pattern_symbols[remaining - 1];
+        for &symbol in symbols.iter() {
+            let mismatches = mismatches + usize::from(symbol != expected);
+            if mismatches > max_mismatches {
+                continue;
+            }
+            let before = if top == 0 {
+                0
+            } else {
+                occurrences.occurrence(symbol, top - 1)
+            };
+            let through = occurrences.occurrence(symbol, bottom);
+            if through > before {
+                stack.push((
+                    remaining - 1,
+                    first_col_starts[symbol] + before,
+                    first_col_starts[symbol] + through - 1,
+                    mismatches,
+                ));
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+/// Start positions of `pattern` within Hamming distance `max_mismatches`, each
+/// paired with its mismatch count and sorted by position.
+pub fn bw_approximate_match_position<'a>(
+    first_col_starts: &[usize],
+    suffixes: impl Into<SuffixArrayRef<'a>>,
+    occurrences: &impl Occurrences,
+    counts: &[usize],
+    char_map: &impl SymbolMap<u8>,
+    pattern: &str,
+    max_mismatches: usize,
+) -> Result<Vec<ApproximateMatch>, Box<dyn Error>> {
+    let suffixes = suffixes.into();
+    let ranges = bw_approximate_matching(
+        first_col_starts,
+        occurrences,
+        counts,
+        char_map,
+        pattern,
+        max_mismatches,
+    )?;
+    let mut matches = ranges
+        .into_iter()
+        .flat_map(|(top, bottom, mismatches)| (top..=bottom).map(move |row| (row, mismatches)))
+        .map(|(row, mismatches)| {
+            let position = match suffixes {
+                SuffixArrayRef::Full(suffixes) => suffixes[row],
+                SuffixArrayRef::Sampled(suffixes) => {
+                    suffixes.locate(row, |r| occurrences.last_to_first(first_col_starts, r))
+                }
+            };
+            (position, mismatches)
+        })
+        .collect::<Vec<_>>();
+    matches.sort();
+    Ok(matches)
+}
+
+pub fn bw_approximate_match_positions<'a>(
+    bwt: &str,
+    suffixes: impl Into<SuffixArrayRef<'a>>,
+    patterns: &[&str],
+    char_map: &impl SymbolMap<u8>,
+    fw_step: usize,
+    max_mismatches: usize,
+) -> Result<Vec<Vec<ApproximateMatch>>, Box<dyn Error>> {
+    let bwt_bytes = bwt.as_bytes();
+    let suffixes = suffixes.into();
+
+    let counts = char_counts(bwt_bytes, char_map)?;
+    let first_col_starts = get_first_col_starts(&counts)?;
+    let fm_index = calculate_fm_index(bwt_bytes, char_map, fw_step)?;
+    let occurrences = OccurrenceTable::new(bwt_bytes, &fm_index, fw_step, char_map);
+
+    patterns
+        .iter()
+        .map(|&pattern| {
+            bw_approximate_match_position(
+                &first_col_starts,
+                suffixes,
+                &occurrences,
+                &counts,
+                char_map,
+                pattern,
+                max_mismatches,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::approximate::bw_approximate_match_positions;
+    use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
+    use crate::bwt::sampled::SampledSuffixArray;
+    use crate::motif::r#match::approximate_pattern_matching;
+    use crate::utils::hamming::hamming_distance;
+    use crate::utils::DNA_BW;
+    use std::error::Error;
+
+    #[test]
+    fn test_bw_approximate_match_positions1() -> Result<(), Box<dyn Error>> {
+        let text = "ACATGCTACTTT";
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(text, &DNA_BW)?;
+        let matches =
+            bw_approximate_match_positions(&bwt, &sa, &["ATT", "GGC", "GAA"], &DNA_BW, 1, 1)?;
+        assert_eq!(
+            matches,
+            vec![vec![(2, 1), (7, 1), (8, 1), (9, 1)], vec![(3, 1)], vec![]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bw_approximate_match_positions2() -> Result<(), Box<dyn Error>> {
+        let text = "CGCCCGAATCCAGAACGCATTCCCATATTTCGGGACCACTGGCCTCCACGGTACGGACGTCAATCAAAT";
+        let patterns = ["ATTCTGGA", "AAA", "GAGCGCTGG", "CCA"];
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(text, &DNA_BW)?;
+        let sampled = SampledSuffixArray::from_suffix_array(&sa, 4)?;
+        for max_mismatches in 0..=3 {
+            let matches = bw_approximate_match_positions(
+                &bwt,
+                &sampled,
+                &patterns,
+                &DNA_BW,
+                8,
+                max_mismatches,
+            )?;
+            for (pattern, pattern_matches) in patterns.iter().zip(matches.iter()) {
+                let positions = pattern_matches.iter().map(|&(p, _)| p).collect::<Vec<_>>();
+                assert_eq!(
+                    positions,
+                    approximate_pattern_matching(text, pattern, max_mismatches)?
+                );
+                for &(p, mismatches) in pattern_matches {
+                    assert_eq!(
+                        mismatches,
+                        hamming_distance(&text[p..p + pattern.len()], pattern)?
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
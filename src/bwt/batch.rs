@@ -0,0 +1,41 @@
+use rayon::prelude::*;
+use std::error::Error;
+
+/// Runs `query` on every pattern in parallel. Results come back in pattern
+/// order and, when several queries fail, the error of the first one is
+/// returned, so the outcome never depends on thread scheduling.
+pub(crate) fn par_queries<T: Send>(
+    patterns: &[&str],
+    query: impl Fn(&str) -> Result<T, Box<dyn Error>> + Sync,
+) -> Result<Vec<T>, Box<dyn Error>> {
+    let results = patterns
+        .par_iter()
+        .map(|&pattern| query(pattern).map_err(|e| e.to_string()))
+        .collect::<Vec<_>>();
+    Ok(results.into_iter().collect::<Result<Vec<_>, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::batch::par_queries;
+    use std::error::Error;
+
+    #[test]
+    fn test_par_queries1() -> Result<(), Box<dyn Error>> {
+        let patterns = (0..10_000).map(|i| i.to_string()).collect::<Vec<_>>();
+        let patterns = patterns.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+        let lengths = par_queries(&patterns, |p| Ok(p.len()))?;
+        assert_eq!(
+            lengths,
+            patterns.iter().map(|p| p.len()).collect::<Vec<_>>()
+        );
+
+        let error = par_queries(&patterns, |p| match p.parse::<usize>()? {
+            n if n % 1000 == 999 => Err(format!("failed on {}", n).into()),
+            n => Ok(n),
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "failed on 999");
+        Ok(())
+    }
+}
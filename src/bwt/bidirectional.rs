@@ -0,0 +1,409 @@
+use crate::bwt::bwt::burrows_wheeler_transform_suffixes;
+use crate::bwt::counts::{char_counts, get_first_col_starts};
+use crate::bwt::fm::Occurrences;
+use crate::bwt::rank::PackedBwt;
+use crate::bwt::records::{
+    join_records, validate_records, with_record_separator, RecordTable, RECORD_SEPARATOR,
+};
+use crate::bwt::sampled::SampledSuffixArray;
+use crate::utils::{Alphabet, Fasta};
+use std::error::Error;
+use std::ops::Range;
+
+/// Rows of a pattern in the forward index and of its reverse in the reverse
+/// index. Both ranges hold the same number of rows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct BiInterval {
+    forward: usize,
+    reverse: usize,
+    size: usize,
+}
+
+/// Super-maximal exact match: `query[query]` occurs `count` times in the
+/// text, at `positions`, and no longer exact match of the query contains it.
+/// `positions` is empty when `count` is above the cap the SMEM was found with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Smem {
+    pub query: Range<usize>,
+    pub count: usize,
+    pub positions: Vec<usize>,
+}
+
+/// FM-indexes of the text and of its reverse, kept in step so that a match
+/// can be extended by one character on either side in constant time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BidirectionalIndex {
+    alphabet: Alphabet,
+    forward: PackedBwt,
+    reverse: PackedBwt,
+    first_col_starts: Vec<usize>,
+    suffixes: SampledSuffixArray,
+    records: RecordTable,
+}
+
+impl BidirectionalIndex {
+    pub fn from_text(
+        text: &str,
+        alphabet: &Alphabet,
+        sa_sample_rate: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_records(&[Fasta::new("", text)], alphabet, sa_sample_rate)
+    }
+
+    pub fn from_records(
+        records: &[Fasta],
+        alphabet: &Alphabet,
+        sa_sample_rate: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        validate_records(records, alphabet)?;
+        let (text, records) = join_records(records)?;
+        let alphabet = with_record_separator(alphabet)?;
+        let (bwt, full_suffixes) = burrows_wheeler_transform_suffixes(&text, &alphabet)?;
+        let suffixes = SampledSuffixArray::from_suffixes(full_suffixes.iter(), sa_sample_rate)?;
+        drop(full_suffixes);
+        let reverse_text = text.chars().rev().collect::<String>();
+        let (reverse_bwt, _) = burrows_wheeler_transform_suffixes(&reverse_text, &alphabet)?;
+
+        // The text and its reverse have the same symbol counts
+        let counts = char_counts(bwt.as_bytes(), &alphabet)?;
+        Ok(BidirectionalIndex {
+            forward: PackedBwt::new(bwt.as_bytes(), &alphabet)?,
+            reverse: PackedBwt::new(reverse_bwt.as_bytes(), &alphabet)?,
+            first_col_starts: get_first_col_starts(&counts)?,
+            suffixes,
+            alphabet,
+            records,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    pub fn records(&self) -> &RecordTable {
+        &self.records
+    }
+
+    /// SMEMs of `query` at least `min_length` long, ordered by query start.
+    /// Symbols outside the alphabet never match, so SMEMs stop at them. Only
+    /// SMEMs occurring at most `max_positions` times are located.
+    pub fn smems(
+        &self,
+        query: &str,
+        min_length: usize,
+        max_positions: usize,
+    ) -> Result<Vec<Smem>, Box<dyn Error>> {
+        if query.as_bytes().contains(&RECORD_SEPARATOR) {
+            return Err("Query contains the record separator".into());
+        }
+        let symbols = query
+            .bytes()
+            .map(|b| self.alphabet.rank(b).filter(|&s| s > 0))
+            .collect::<Vec<_>>();
+
+        let mut found = Vec::new();
+        let mut x = 0;
+        while x < symbols.len() {
+            x = match symbols[x] {
+                Some(_) => self.smems_covering(&symbols, x, &mut found),
+                None => x + 1,
+            };
+        }
+        found.sort_by_key(|(range, _)| (range.start, range.end));
+        Ok(found
+            .into_iter()
+            .filter(|(range, _)| range.len() >= min_length.max(1))
+            .map(|(query, interval)| Smem {
+                query,
+                count: interval.size,
+                positions: if interval.size > max_positions {
+                    Vec::new()
+                } else {
+                    self.locate(&interval)
+                },
+            })
+            .collect())
+    }
+
+    /// Adds the SMEMs that contain `query[x]` to `found` and returns the end
+    /// of the longest of them, where the search for the next ones starts
+    /// (Li, 2012).
+    fn smems_covering(
+        &self,
+        symbols: &[Option<usize>],
+        x: usize,
+        found: &mut Vec<(Range<usize>, BiInterval)>,
+    ) -> usize {
+        // Extend right from x, keeping the interval each time it shrinks
+        let mut current = self.extend_backward(&self.full_interval(), symbols[x].unwrap());
+        if current.size == 0 {
+            return x + 1;
+        }
+        let mut ends = Vec::new();
+        let mut end = x + 1;
+        loop {
+            match symbols.get(end).copied().flatten() {
+                Some(symbol) => {
+                    let next = self.extend_forward(&current, symbol);
+                    if next.size != current.size {
+                        ends.push((end, current));
+                    }
+                    if next.size == 0 {
+                        break;
+                    }
+                    current = next;
+                    end += 1;
+                }
+                None => {
+                    ends.push((end, current));
+                    break;
+                }
+            }
+        }
+        ends.reverse();
+        let longest_end = ends[0].0;
+
+        // Extend all of them left together, longest first; a match that can
+        // go no further is an SMEM unless a longer one already contains it
+        let mut covering: Vec<(Range<usize>, BiInterval)> = Vec::new();
+        let mut start = x;
+        loop {
+            let symbol = start.checked_sub(1).and_then(|i| symbols[i]);
+            let mut extended: Vec<(usize, BiInterval)> = Vec::new();
+            for &(end, interval) in &ends {
+                let next = symbol
+                    .map(|s| self.extend_backward(&interval, s))
+                    .filter(|next| next.size > 0);
+                match next {
+                    Some(next) => {
+                        if extended.last().is_none_or(|(_, e)| e.size != next.size) {
+                            extended.push((end, next));
+                        }
+                    }
+                    None => {
+                        if extended.is_empty()
+                            && covering.last().is_none_or(|(r, _)| start < r.start)
+                        {
+                            covering.push((start..end, interval));
+                        }
+                    }
+                }
+            }
+            if extended.is_empty() {
+                break;
+            }
+            ends = extended;
+            start -= 1;
+        }
+        found.extend(covering);
+        longest_end
+    }
+
+    fn full_interval(&self) -> BiInterval {
+        BiInterval {
+            forward: 0,
+            reverse: 0,
+            size: self.len(),
+        }
+    }
+
+    /// Interval of `symbol` followed by the pattern of `interval`.
+    fn extend_backward(&self, interval: &BiInterval, symbol: usize) -> BiInterval {
+        let (forward, reverse, size) = self.extend(
+            &self.forward,
+            interval.forward,
+            interval.reverse,
+            interval.size,
+            symbol,
+        );
+        BiInterval {
+            forward,
+            reverse,
+            size,
+        }
+    }
+
+    /// Interval of the pattern of `interval` followed by `symbol`.
+    fn extend_forward(&self, interval: &BiInterval, symbol: usize) -> BiInterval {
+        let (reverse, forward, size) = self.extend(
+            &self.reverse,
+            interval.reverse,
+            interval.forward,
+            interval.size,
+            symbol,
+        );
+        BiInterval {
+            forward,
+            reverse,
+            size,
+        }
+    }
+
+    /// Backward search step in `bwt` for the range starting at `top`. Rows of
+    /// the other index are ordered by the symbol prepended here, so the new
+    /// range there starts after the rows of every smaller symbol.
+    fn extend(
+        &self,
+        bwt: &PackedBwt,
+        top: usize,
+        other_top: usize,
+        size: usize,
+        symbol: usize,
+    ) -> (usize, usize, usize) {
+        let in_range = |s: usize| {
+            let before = if top == 0 {
+                0
+            } else {
+                bwt.occurrence(s, top - 1)
+            };
+            (before, bwt.occurrence(s, top + size - 1) - before)
+        };
+        let smaller = (0..symbol).map(|s| in_range(s).1).sum::<usize>();
+        let (before, count) = in_range(symbol);
+        (
+            self.first_col_starts[symbol] + before,
+            other_top + smaller,
+            count,
+        )
+    }
+
+    fn locate(&self, interval: &BiInterval) -> Vec<usize> {
+        let mut positions = (interval.forward..interval.forward + interval.size)
+            .map(|row| {
+                self.suffixes.locate(row, |r| {
+                    self.forward.last_to_first(&self.first_col_starts, r)
+                })
+            })
+            .collect::<Vec<_>>();
+        positions.sort();
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::bidirectional::{BidirectionalIndex, Smem};
+    use crate::utils::{Fasta, DNA_BW};
+    use std::error::Error;
+
+    /// SMEMs by brute force: maximal exact matches not contained in another.
+    fn naive_smems(text: &str, query: &str, min_length: usize) -> Vec<(usize, usize, usize)> {
+        let count = |p: &str| {
+            (0..text.len())
+                .filter(|&i| text[i..].starts_with(p))
+                .count()
+        };
+        let mut mems = Vec::new();
+        for start in 0..query.len() {
+            for end in start + 1..=query.len() {
+                let occurrences = count(&query[start..end]);
+                let left = start == 0 || count(&query[start - 1..end]) == 0;
+                let right = end == query.len() || count(&query[start..end + 1]) == 0;
+                if occurrences > 0 && left && right {
+                    mems.push((start, end, occurrences));
+                }
+            }
+        }
+        let contained = |&(s, e, _): &(usize, usize, usize)| {
+            mems.iter()
+                .any(|&(s2, e2, _)| s2 <= s && e <= e2 && e2 - s2 > e - s)
+        };
+        mems.iter()
+            .filter(|m| !contained(m) && m.1 - m.0 >= min_length)
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn test_smems1() -> Result<(), Box<dyn Error>> {
+        let text = "GATTACAGGCTTACCGATTACGGTTAGCATTACAGGCA";
+        let index = BidirectionalIndex::from_text(text, &DNA_BW, 3)?;
+        let smems = index.smems("CTTACCGATTTACAGGCATT", 1, usize::MAX)?;
+        assert_eq!(
+            smems[0],
+            Smem {
+                query: 0..10,
+                count: 1,
+                positions: vec![9],
+            }
+        );
+        let found = smems
+            .iter()
+            .map(|s| (s.query.start, s.query.end, s.count))
+            .collect::<Vec<_>>();
+        assert_eq!(found, naive_smems(text, "CTTACCGATTTACAGGCATT", 1));
+        for smem in &smems {
+            for &p in &smem.positions {
+                assert_eq!(
+                    &text[p..p + smem.query.len()],
+                    &"CTTACCGATTTACAGGCATT"[smem.query.clone()]
+                );
+            }
+        }
+        // Above the cap only the count is kept
+        let capped = index.smems("CTTACCGATTTACAGGCATT", 1, 1)?;
+        assert_eq!(capped.len(), smems.len());
+        for (smem, capped) in smems.iter().zip(&capped) {
+            assert_eq!(capped.count, smem.count);
+            let expected = if smem.count > 1 {
+                &[][..]
+            } else {
+                &smem.positions[..]
+            };
+            assert_eq!(capped.positions, expected);
+        }
+        // Symbols missing from the text end matches like foreign ones
+        let index = BidirectionalIndex::from_text("ACCA", &DNA_BW, 1)?;
+        let smems = index.smems("GTACGN", 1, usize::MAX)?;
+        assert_eq!(
+            smems,
+            vec![Smem {
+                query: 2..4,
+                count: 1,
+                positions: vec![0],
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_smems2() -> Result<(), Box<dyn Error>> {
+        let records = [
+            Fasta::new("chr1", "ACGTTGCATGCATTTACGGACTAGCTAGGATCC"),
+            Fasta::new("chr2", "TTGCATGCAAACGTACGGACTAGGGATCCATG"),
+        ];
+        let index = BidirectionalIndex::from_records(&records, &DNA_BW, 2)?;
+        let text = "ACGTTGCATGCATTTACGGACTAGCTAGGATCC|TTGCATGCAAACGTACGGACTAGGGATCCATG";
+        let queries = [
+            "GCATGCATTTACGGAC",
+            "TTTTTTTT",
+            "ACGGACTAGGGATCCNNNTGCATGCA",
+            "GATCCTTGCA",
+        ];
+        for query in queries {
+            let found = index
+                .smems(query, 4, usize::MAX)?
+                .iter()
+                .map(|s| (s.query.start, s.query.end, s.count))
+                .collect::<Vec<_>>();
+            let expected = query
+                .split('N')
+                .scan(0, |offset, part| {
+                    let start = *offset;
+                    *offset += part.len() + 1;
+                    Some((start, part))
+                })
+                .filter(|(_, part)| !part.is_empty())
+                .flat_map(|(start, part)| {
+                    naive_smems(text, part, 4)
+                        .into_iter()
+                        .map(move |(s, e, c)| (s + start, e + start, c))
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "{}", query);
+        }
+        // A match never spans the record separator
+        assert!(index.smems("GATCCTTGCA", 6, usize::MAX)?.is_empty());
+        Ok(())
+    }
+}
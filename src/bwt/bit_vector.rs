@@ -0,0 +1,102 @@
+use std::error::Error;
+
+const WORD_BITS: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BitVector {
+    len: usize,
+    words: Vec<u64>,
+    ranks: Vec<usize>,
+}
+
+impl BitVector {
+    pub(crate) fn from_bits(bits: impl IntoIterator<Item = bool>) -> Self {
+        let mut words = Vec::new();
+        let mut len = 0;
+        for bit in bits {
+            if len % WORD_BITS == 0 {
+                words.push(0);
+            }
+            if bit {
+                words[len / WORD_BITS] |= 1 << (len % WORD_BITS);
+            }
+            len += 1;
+        }
+        Self::with_ranks(len, words)
+    }
+
+    /// Bit vector of `len` bits stored in `words`, as written by [`Self::words`].
+    pub(crate) fn from_words(len: usize, words: Vec<u64>) -> Result<Self, Box<dyn Error>> {
+        if words.len() != len.div_ceil(WORD_BITS) {
+            return Err(format!("{} words cannot hold {} bits", words.len(), len).into());
+        }
+        Ok(Self::with_ranks(len, words))
+    }
+
+    fn with_ranks(len: usize, words: Vec<u64>) -> Self {
+        // Cumulative popcount before each word
+        let mut total = 0;
+        let ranks = words
+            .iter()
+            .map(|w| {
+                let rank = total;
+                total += w.count_ones() as usize;
+                rank
+            })
+            .collect::<Vec<_>>();
+        BitVector { len, words, ranks }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub(crate) fn get(&self, i: usize) -> bool {
+        self.words[i / WORD_BITS] >> (i % WORD_BITS) & 1 == 1
+    }
+
+    /// Number of set bits in positions `[0, i)`.
+    pub(crate) fn rank(&self, i: usize) -> usize {
+        let (word, bit) = (i / WORD_BITS, i % WORD_BITS);
+        if bit == 0 {
+            if word == self.words.len() {
+                self.ranks
+                    .last()
+                    .map_or(0, |r| r + self.words[word - 1].count_ones() as usize)
+            } else {
+                self.ranks[word]
+            }
+        } else {
+            let mask = (1u64 << bit) - 1;
+            self.ranks[word] + (self.words[word] & mask).count_ones() as usize
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::bit_vector::BitVector;
+
+    #[test]
+    fn test_bit_vector_rank1() {
+        let bits = (0..200).map(|i| i % 3 == 0).collect::<Vec<_>>();
+        let bit_vector = BitVector::from_bits(bits.iter().copied());
+        for i in 0..=200 {
+            assert_eq!(bit_vector.rank(i), bits[..i].iter().filter(|&&b| b).count());
+        }
+        for (i, &b) in bits.iter().enumerate() {
+            assert_eq!(bit_vector.get(i), b);
+        }
+    }
+
+    #[test]
+    fn test_bit_vector_rank2() {
+        let bit_vector = BitVector::from_bits(vec![true; 128]);
+        assert_eq!(bit_vector.rank(64), 64);
+        assert_eq!(bit_vector.rank(128), 128);
+    }
+}
@@ -1,15 +1,14 @@
-use std::collections::HashMap;
+use crate::utils::SymbolMap;
 use std::error::Error;
-use std::hash::Hash;
 
-pub(crate) fn char_buckets<T: Eq + Hash>(
+pub(crate) fn char_buckets<T>(
     text_bytes: &[T],
-    char_map: &HashMap<T, usize>,
+    char_map: &impl SymbolMap<T>,
 ) -> Result<Vec<usize>, Box<dyn Error>> {
-    let mut char_counts = vec![0; char_map.len()];
+    let mut char_counts = vec![0; char_map.size()];
     for b in text_bytes {
-        if char_map.contains_key(b) {
-            char_counts[*char_map.get(b).unwrap()] += 1;
+        if let Some(rank) = char_map.rank(b) {
+            char_counts[rank] += 1;
         }
     }
     Ok(char_counts)
@@ -1,8 +1,10 @@
 use crate::bwt::counts::char_counts;
-use crate::bwt::suffix_array::suffix_array_bytes;
+use crate::bwt::fm::Occurrences;
+use crate::bwt::rank::PackedBwt;
+use crate::bwt::records::{join_records, validate_records, with_record_separator, RecordTable};
+use crate::bwt::suffix_array::{suffix_array_bytes, SuffixArray};
 use crate::bwt::suffix_array_induced_sorting;
-use crate::utils::Fasta;
-use std::collections::HashMap;
+use crate::utils::{Alphabet, Fasta, InvalidSymbolError, SymbolMap};
 use std::error::Error;
 use std::hash::Hash;
 
@@ -33,9 +35,22 @@ pub fn fasta_burrows_wheeler_transform(fasta: &Fasta) -> Result<String, Box<dyn
 
 pub fn burrows_wheeler_transform_sa_is(
     text: &str,
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
 ) -> Result<(String, Vec<usize>), Box<dyn Error>> {
+    let (bwt, suffixes) = burrows_wheeler_transform_suffixes(text, char_map)?;
+    Ok((bwt, suffixes.into_usizes()))
+}
+
+/// BWT and the suffix array it was read from, kept in the width SA-IS sorted
+/// it with.
+pub(crate) fn burrows_wheeler_transform_suffixes(
+    text: &str,
+    char_map: &impl SymbolMap<u8>,
+) -> Result<(String, SuffixArray), Box<dyn Error>> {
     let text_bytes = text.as_bytes();
+    if let Some(position) = char_map.first_invalid(text_bytes) {
+        return Err(InvalidSymbolError::new(text_bytes[position], position).into());
+    }
     let suffixes = suffix_array_induced_sorting(text_bytes, char_map)?;
     let n = text.len();
 
@@ -47,20 +62,30 @@ pub fn burrows_wheeler_transform_sa_is(
         }
     };
 
-    let bwt = suffixes
-        .iter()
-        .map(|&s| get_bwt_character(s))
-        .collect::<Vec<_>>();
+    let bwt = suffixes.iter().map(get_bwt_character).collect::<Vec<_>>();
     Ok((String::from_utf8(bwt)?, suffixes))
 }
 
 pub fn fasta_burrows_wheeler_transform_sa_is(
     fasta: &Fasta,
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
 ) -> Result<(String, Vec<usize>), Box<dyn Error>> {
     burrows_wheeler_transform_sa_is(&fasta.text, char_map)
 }
 
+/// BWT over all `records` joined by the record separator, which is added to
+/// `alphabet` so that no suffix compares across a record boundary.
+pub fn records_burrows_wheeler_transform_sa_is(
+    records: &[Fasta],
+    alphabet: &Alphabet,
+) -> Result<(String, SuffixArray, RecordTable), Box<dyn Error>> {
+    validate_records(records, alphabet)?;
+    let (text, table) = join_records(records)?;
+    let alphabet = with_record_separator(alphabet)?;
+    let (bwt, suffixes) = burrows_wheeler_transform_suffixes(&text, &alphabet)?;
+    Ok((bwt, suffixes, table))
+}
+
 fn calculate_start_positions(counts: &[usize]) -> Result<Vec<usize>, Box<dyn Error>> {
     let mut cumulative_sum = 0;
     Ok(counts
@@ -73,26 +98,9 @@ fn calculate_start_positions(counts: &[usize]) -> Result<Vec<usize>, Box<dyn Err
         .collect())
 }
 
-// Helper function for calculating LF-mapping occurrences
-fn calculate_char_occurrences(
-    bwt_bytes: &[u8],
-    char_map: &HashMap<u8, usize>,
-) -> Result<Vec<usize>, Box<dyn Error>> {
-    let mut accumulated_occurrences = vec![0; char_map.len()];
-    Ok(bwt_bytes
-        .iter()
-        .map(|c| {
-            let pos = *char_map.get(c).unwrap();
-            let occ = accumulated_occurrences[pos];
-            accumulated_occurrences[pos] += 1;
-            occ
-        })
-        .collect::<Vec<_>>())
-}
-
 pub fn inverse_burrows_wheeler_transform(
     bwt: &str,
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
 ) -> Result<String, Box<dyn Error>> {
     let n = bwt.len();
     let bwt_bytes = bwt.as_bytes();
@@ -106,9 +114,8 @@ pub fn inverse_burrows_wheeler_transform(
     // Calculate starting positions for each character in first column
     let start_pos = calculate_start_positions(&counts)?;
 
-    // Create smaller count array for LF-mapping
-
-    let char_occ = calculate_char_occurrences(bwt_bytes, char_map)?;
+    // LF-mapping ranks come from the packed BWT, not a per-row array
+    let occurrences = PackedBwt::new(bwt_bytes, char_map)?;
 
     // Find the dollar sign position
     let mut idx = bwt_bytes
@@ -121,8 +128,7 @@ pub fn inverse_burrows_wheeler_transform(
 
     for _ in 0..n - 1 {
         // Move to the next character in the original string
-        let c = *char_map.get(&bwt_bytes[idx]).unwrap();
-        idx = start_pos[c] + char_occ[idx];
+        idx = occurrences.last_to_first(&start_pos, idx);
 
         // Don't add the '$' to the result
         result.push(bwt_bytes[idx]);
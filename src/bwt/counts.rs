@@ -1,13 +1,13 @@
-use std::collections::HashMap;
+use crate::utils::SymbolMap;
 use std::error::Error;
 
 pub(crate) fn char_counts(
     bwt_bytes: &[u8],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
 ) -> Result<Vec<usize>, Box<dyn Error>> {
-    let mut char_counts = vec![0; char_map.len()];
+    let mut char_counts = vec![0; char_map.size()];
     for b in bwt_bytes {
-        char_counts[*char_map.get(b).unwrap()] += 1;
+        char_counts[char_map.rank(b).unwrap()] += 1;
     }
     Ok(char_counts)
 }
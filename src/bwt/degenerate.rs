@@ -0,0 +1,242 @@
+use crate::bwt::fm::Occurrences;
+use crate::bwt::matching::SuffixArrayRef;
+use crate::utils::SymbolMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// How a reference N compares with the pattern during degenerate search.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NPolicy {
+    /// Reference Ns match no pattern symbol, not even N.
+    #[default]
+    NeverMatch,
+    /// Reference Ns match every pattern symbol.
+    MatchAnything,
+}
+
+impl FromStr for NPolicy {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "never" => Ok(NPolicy::NeverMatch),
+            "any" => Ok(NPolicy::MatchAnything),
+            _ => Err(format!("Unknown N policy {}, expected never or any", s).into()),
+        }
+    }
+}
+
+impl Display for NPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NPolicy::NeverMatch => write!(f, "never"),
+            NPolicy::MatchAnything => write!(f, "any"),
+        }
+    }
+}
+
+/// Bases compatible with an IUPAC nucleotide code.
+pub(crate) fn iupac_bases(code: u8) -> Option<&'static [u8]> {
+    Some(match code {
+        b'A' => b"A",
+        b'C' => b"C",
+        b'G' => b"G",
+        b'T' => b"T",
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        b'N' => b"ACGT",
+        _ => return None,
+    })
+}
+
+/// Whether `pattern` holds any IUPAC code other than A, C, G and T.
+pub fn is_degenerate(pattern: &str) -> bool {
+    pattern
+        .bytes()
+        .any(|b| iupac_bases(b).is_some_and(|bases| bases.len() > 1))
+}
+
+/// Backward search that expands every IUPAC code of `pattern` into its bases,
+/// branching over them. The BWT row ranges returned never overlap, one per
+/// distinct reference string matched.
+pub(crate) fn bw_degenerate_matching(
+    first_col_starts: &[usize],
+    occurrences: &impl Occurrences,
+    counts: &[usize],
+    char_map: &impl SymbolMap<u8>,
+    pattern: &str,
+    n_policy: NPolicy,
+) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+    let wildcard = match n_policy {
+        NPolicy::NeverMatch => None,
+        NPolicy::MatchAnything => char_map.rank(&b'N'),
+    };
+    let pattern_symbols = pattern
+        .bytes()
+        .map(|b| {
+            let bases = iupac_bases(b).ok_or(format!(
+                "Symbol {} is not an IUPAC nucleotide code",
+                b as char
+            ))?;
+            Ok(bases
+                .iter()
+                .filter_map(|base| char_map.rank(base))
+                .chain(wildcard)
+                .filter(|&s| counts[s] > 0)
+                .collect::<Vec<_>>())
+        })
+        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+
+    let mut ranges = Vec::new();
+    let mut stack = vec![(pattern_symbols.len(), 0, occurrences.len() - 1)];
+    while let Some((remaining, top, bottom)) = stack.pop() {
+        if remaining == 0 {
+            ranges.push((top, bottom));
+            continue;
+        }
+        for &symbol in &pattern_symbols[remaining - 1] {
+            let before = if top == 0 {
+                0
+            } else {
+                occurrences.occurrence(symbol, top - 1)
+            };
+            let through = occurrences.occurrence(symbol, bottom);
+            if through > before {
+                stack.push((
+                    remaining - 1,
+                    first_col_starts[symbol] + before,
+                    first_col_starts[symbol] + through - 1,
+                ));
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+/// Sorted start positions of the reference strings `pattern` matches once its
+/// IUPAC codes are expanded.
+pub fn bw_degenerate_match_position<'a>(
+    first_col_starts: &[usize],
+    suffixes: impl Into<SuffixArrayRef<'a>>,
+    occurrences: &impl Occurrences,
+    counts: &[usize],
+    char_map: &impl SymbolMap<u8>,
+    pattern: &str,
+    n_policy: NPolicy,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let suffixes = suffixes.into();
+    let ranges = bw_degenerate_matching(
+        first_col_starts,
+        occurrences,
+        counts,
+        char_map,
+        pattern,
+        n_policy,
+    )?;
+    let mut positions = ranges
+        .into_iter()
+        .flat_map(|(top, bottom)| top..=bottom)
+        .map(|row| match suffixes {
+            SuffixArrayRef::Full(suffixes) => suffixes[row],
+            SuffixArrayRef::Sampled(suffixes) => {
+                suffixes.locate(row, |r| occurrences.last_to_first(first_col_starts, r))
+            }
+        })
+        .collect::<Vec<_>>();
+    positions.sort();
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
+    use crate::bwt::counts::{char_counts, get_first_col_starts};
+    use crate::bwt::degenerate::{
+        bw_degenerate_match_position, is_degenerate, iupac_bases, NPolicy,
+    };
+    use crate::bwt::rank::PackedBwt;
+    use crate::bwt::sampled::SampledSuffixArray;
+    use crate::utils::DNA_BW_N;
+    use std::error::Error;
+
+    fn degenerate_positions(
+        text: &str,
+        pattern: &str,
+        n_policy: NPolicy,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(text, &DNA_BW_N)?;
+        let counts = char_counts(bwt.as_bytes(), &DNA_BW_N)?;
+        let first_col_starts = get_first_col_starts(&counts)?;
+        let occurrences = PackedBwt::new(bwt.as_bytes(), &DNA_BW_N)?;
+        let sampled = SampledSuffixArray::from_suffix_array(&sa, 3)?;
+        bw_degenerate_match_position(
+            &first_col_starts,
+            &sampled,
+            &occurrences,
+            &counts,
+            &DNA_BW_N,
+            pattern,
+            n_policy,
+        )
+    }
+
+    #[test]
+    fn test_degenerate_matching1() -> Result<(), Box<dyn Error>> {
+        let text = "GATTACAGGATCACNGATNNCAGATGACA";
+        assert_eq!(
+            degenerate_positions(text, "GATYA", NPolicy::NeverMatch)?,
+            vec![0, 8]
+        );
+        assert_eq!(
+            degenerate_positions(text, "GATNA", NPolicy::NeverMatch)?,
+            vec![0, 8, 22]
+        );
+        assert_eq!(
+            degenerate_positions(text, "GATNA", NPolicy::MatchAnything)?,
+            vec![0, 8, 15, 22]
+        );
+        assert_eq!(
+            degenerate_positions(text, "CACG", NPolicy::NeverMatch)?,
+            Vec::<usize>::new()
+        );
+        assert_eq!(
+            degenerate_positions(text, "CACG", NPolicy::MatchAnything)?,
+            vec![11]
+        );
+        assert!(degenerate_positions(text, "GA$", NPolicy::NeverMatch).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_degenerate_matching2() -> Result<(), Box<dyn Error>> {
+        // Brute force: every window whose bases fit the pattern codes
+        let text = "ACGTNACGGTTRCAGNNTACGCATGCATNACGA";
+        let patterns = ["ACG", "RCNK", "NNN", "BDHV", "SWN"];
+        for n_policy in [NPolicy::NeverMatch, NPolicy::MatchAnything] {
+            for pattern in patterns {
+                let expected = (0..=text.len() - pattern.len())
+                    .filter(|&i| {
+                        pattern.bytes().zip(text[i..].bytes()).all(|(p, t)| {
+                            iupac_bases(p).unwrap().contains(&t)
+                                || (t == b'N' && n_policy == NPolicy::MatchAnything)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(degenerate_positions(text, pattern, n_policy)?, expected);
+            }
+        }
+        assert!(is_degenerate("ACGN") && !is_degenerate("ACGT"));
+        assert_eq!("any".parse::<NPolicy>()?, NPolicy::MatchAnything);
+        assert!("some".parse::<NPolicy>().is_err());
+        Ok(())
+    }
+}
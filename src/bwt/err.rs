@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum IndexFormatError {
+    #[error("Not a sturgeon FM-index file")]
+    InvalidMagic,
+
+    #[error("Unsupported FM-index version {0}, expected {1}")]
+    UnsupportedVersion(u32, u32),
+
+    #[error("FM-index file is truncated")]
+    Truncated,
+
+    #[error("FM-index file is corrupt: {0}")]
+    Corrupt(String),
+}
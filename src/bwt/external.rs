@@ -0,0 +1,363 @@
+use crate::bwt::counts::get_first_col_starts;
+use crate::bwt::fm::Occurrences;
+use crate::bwt::rank::PackedBwt;
+use crate::bwt::records::{join_records, validate_records, with_record_separator, RecordTable};
+use crate::bwt::suffix_array::{suffix_array_induced_sorting, DenseRanks};
+use crate::utils::{Alphabet, Fasta, InvalidSymbolError};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Peak bytes per text symbol of the in-memory SA-IS construction, from the
+/// text, type map, 32-bit working arrays and the final suffix array.
+const IN_MEMORY_BYTES_PER_SYMBOL: usize = 18;
+/// Bytes per text symbol the external construction holds for the whole run:
+/// the records as read and their joined text, one byte each, and at most one
+/// byte for the rank structure of the BWT built so far.
+const EXTERNAL_BYTES_PER_SYMBOL: usize = 3;
+/// Bytes per block symbol: gap counts, sort keys and the block's SA-IS.
+const BLOCK_BYTES_PER_SYMBOL: usize = 64;
+const MIN_BLOCK_SIZE: usize = 1 << 12;
+
+/// Estimated peak memory of the in-memory construction for `len` symbols.
+pub fn in_memory_footprint(len: usize) -> usize {
+    len.saturating_mul(IN_MEMORY_BYTES_PER_SYMBOL)
+}
+
+/// Largest block size that keeps the external construction for `len` symbols
+/// within `memory_budget` bytes.
+pub fn external_block_size(len: usize, memory_budget: usize) -> Result<usize, Box<dyn Error>> {
+    let block_size = memory_budget.saturating_sub(len.saturating_mul(EXTERNAL_BYTES_PER_SYMBOL))
+        / BLOCK_BYTES_PER_SYMBOL;
+    if block_size < MIN_BLOCK_SIZE.min(len.max(1)) {
+        return Err(format!(
+            "Memory budget of {} bytes is too small for {} symbols",
+            memory_budget, len
+        )
+        .into());
+    }
+    Ok(block_size)
+}
+
+/// Directory of intermediate BWT files, removed again when dropped.
+struct WorkDir {
+    path: PathBuf,
+}
+
+impl WorkDir {
+    fn create(parent: &Path) -> Result<Self, Box<dyn Error>> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let path = parent.join(format!("sturgeon-bwt-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(&path)?;
+        Ok(WorkDir { path })
+    }
+
+    fn file(&self, step: usize) -> PathBuf {
+        self.path.join(format!("bwt-{}", step))
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// BWT of `text` followed by the sentinel, built without a suffix array of the
+/// whole text and written to `output`.
+///
+/// The text is processed in blocks of `block_size` symbols from its end. The
+/// BWT of the suffix already processed lives in a file under `temp_dir`; for
+/// each new block a backward search on it counts the old suffixes smaller than
+/// every block suffix, SA-IS sorts the block suffixes by those counts, and one
+/// streaming pass merges the block into the BWT.
+pub fn external_burrows_wheeler_transform(
+    text: &str,
+    alphabet: &Alphabet,
+    block_size: usize,
+    temp_dir: impl AsRef<Path>,
+    output: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let text = text.as_bytes();
+    let sentinel = alphabet.sentinel();
+    if let Some(position) = text
+        .iter()
+        .position(|&b| b == sentinel || !alphabet.contains(b))
+    {
+        return Err(InvalidSymbolError::new(text[position], position).into());
+    }
+    if block_size == 0 {
+        return Err("Block size must be positive".into());
+    }
+
+    let work = WorkDir::create(temp_dir.as_ref())?;
+    let mut step = 0;
+    fs::write(work.file(step), [sentinel])?;
+    let mut counts = vec![0; alphabet.size()];
+    counts[0] = 1;
+    // Row of the suffix processed so far as a whole, the one holding the sentinel
+    let mut text_row = 0;
+
+    let mut end = text.len();
+    while end > 0 {
+        let start = end.saturating_sub(block_size);
+        let block = &text[start..end];
+        let packed =
+            PackedBwt::from_reader(BufReader::new(File::open(work.file(step))?), alphabet)?;
+        let gaps = block_gaps(block, alphabet, &packed, &counts, text_row)?;
+        drop(packed);
+        let order = sort_block(block, alphabet, &gaps, text_row)?;
+
+        let mut old = BufReader::new(File::open(work.file(step))?);
+        let mut new = RowWriter::create(&work.file(step + 1), alphabet)?;
+        let previous = text[end - 1];
+        let mut copied = 0;
+        for &p in &order {
+            new.copy_rows(&mut old, Some(gaps[p] - copied), previous)?;
+            copied = gaps[p];
+            if p == 0 {
+                text_row = new.rows;
+                new.push(sentinel)?;
+            } else {
+                new.push(block[p - 1])?;
+            }
+        }
+        new.copy_rows(&mut old, None, previous)?;
+        counts = new.finish()?;
+        drop(old);
+        fs::remove_file(work.file(step))?;
+        step += 1;
+        end = start;
+    }
+
+    io::copy(&mut File::open(work.file(step))?, output)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// BWT over all `records` joined by the record separator, as
+/// `records_burrows_wheeler_transform_sa_is` builds it, streamed to `output`.
+pub fn records_burrows_wheeler_transform_external(
+    records: &[Fasta],
+    alphabet: &Alphabet,
+    block_size: usize,
+    temp_dir: impl AsRef<Path>,
+    output: &mut impl Write,
+) -> Result<RecordTable, Box<dyn Error>> {
+    validate_records(records, alphabet)?;
+    let (text, table) = join_records(records)?;
+    let alphabet = with_record_separator(alphabet)?;
+    external_burrows_wheeler_transform(&text, &alphabet, block_size, temp_dir, output)?;
+    Ok(table)
+}
+
+/// Writes the rows of a BWT file and counts its symbols.
+struct RowWriter<'a> {
+    out: BufWriter<File>,
+    alphabet: &'a Alphabet,
+    counts: Vec<usize>,
+    rows: usize,
+}
+
+impl<'a> RowWriter<'a> {
+    fn create(path: &Path, alphabet: &'a Alphabet) -> Result<Self, Box<dyn Error>> {
+        Ok(RowWriter {
+            out: BufWriter::new(File::create(path)?),
+            alphabet,
+            counts: vec![0; alphabet.size()],
+            rows: 0,
+        })
+    }
+
+    fn push(&mut self, symbol: u8) -> Result<(), Box<dyn Error>> {
+        self.out.write_all(&[symbol])?;
+        self.counts[self.alphabet.rank(symbol).unwrap()] += 1;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Copies `rows` rows of the old BWT, or all that are left. The sentinel
+    /// of the old BWT marks the start of the old text, which the new block now
+    /// precedes with `previous`.
+    fn copy_rows(
+        &mut self,
+        old: &mut impl Read,
+        rows: Option<usize>,
+        previous: u8,
+    ) -> Result<(), Box<dyn Error>> {
+        let sentinel = self.alphabet.sentinel();
+        let mut buffer = vec![0u8; 1 << 16];
+        let mut left = rows.unwrap_or(usize::MAX);
+        while left > 0 {
+            let n = old.read(&mut buffer[..left.min(1 << 16)])?;
+            if n == 0 {
+                return match rows {
+                    Some(_) => Err("Intermediate BWT file ended early".into()),
+                    None => Ok(()),
+                };
+            }
+            for &b in &buffer[..n] {
+                self.push(if b == sentinel { previous } else { b })?;
+            }
+            left -= n;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<usize>, Box<dyn Error>> {
+        self.out.flush()?;
+        Ok(self.counts)
+    }
+}
+
+/// For every suffix starting in `block`, the number of suffixes of the old
+/// text (the one after the block) smaller than it, by backward search from
+/// the row of the old text itself.
+fn block_gaps(
+    block: &[u8],
+    alphabet: &Alphabet,
+    packed: &PackedBwt,
+    counts: &[usize],
+    text_row: usize,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let first_col_starts = get_first_col_starts(counts)?;
+    let mut gaps = vec![0; block.len()];
+    let mut row = text_row;
+    for (i, &b) in block.iter().enumerate().rev() {
+        let symbol = alphabet.rank(b).unwrap();
+        let before = if row == 0 {
+            0
+        } else {
+            packed.occurrence(symbol, row - 1)
+        };
+        row = first_col_starts[symbol] + before;
+        gaps[i] = row;
+    }
+    Ok(gaps)
+}
+
+/// Order of the block suffixes. A suffix is smaller than another if fewer
+/// old suffixes are smaller than it, or on a tie if its symbol is smaller, or
+/// then if the suffix after it is; so SA-IS on (gap, symbol) pairs sorts
+/// them. The old text, ending the block, falls between the gaps around it.
+fn sort_block(
+    block: &[u8],
+    alphabet: &Alphabet,
+    gaps: &[usize],
+    text_row: usize,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let size = alphabet.size() as u64;
+    let mut keys = block
+        .iter()
+        .zip(gaps)
+        .map(|(&b, &gap)| 2 * gap as u64 * size + alphabet.rank(b).unwrap() as u64)
+        .collect::<Vec<_>>();
+    keys.push((2 * text_row as u64 + 1) * size);
+
+    let mut names = keys.clone();
+    names.sort_unstable();
+    names.dedup();
+    let encoded = keys
+        .iter()
+        .map(|k| names.binary_search(k).unwrap())
+        .collect::<Vec<_>>();
+    drop(keys);
+    let ranks = DenseRanks { size: names.len() };
+    drop(names);
+
+    let suffixes = suffix_array_induced_sorting(&encoded, &ranks)?;
+    Ok(suffixes.iter().filter(|&p| p < block.len()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::bwt::{
+        burrows_wheeler_transform_sa_is, records_burrows_wheeler_transform_sa_is,
+    };
+    use crate::bwt::external::{
+        external_block_size, external_burrows_wheeler_transform,
+        records_burrows_wheeler_transform_external, EXTERNAL_BYTES_PER_SYMBOL,
+    };
+    use crate::bwt::rank::PackedBwt;
+    use crate::utils::{Fasta, DNA_BW, DNA_BW_N};
+    use std::error::Error;
+
+    #[test]
+    fn test_external_bwt1() -> Result<(), Box<dyn Error>> {
+        let texts = [
+            "GATTACA",
+            "A",
+            "AAAAAAAAAAAAAAAAAAAAA",
+            "ACGTACGTACGTACGTAACGTACGTTTTGCATGCATGCA",
+            "CTTACCGATTTACAGGCATTCTTACCGATTTACAGGCATTCTTACCGA",
+        ];
+        for text in texts {
+            let (expected, _) = burrows_wheeler_transform_sa_is(text, &DNA_BW)?;
+            for block_size in [1, 2, 3, 5, 8, 64] {
+                let mut bwt = Vec::new();
+                external_burrows_wheeler_transform(
+                    text,
+                    &DNA_BW,
+                    block_size,
+                    std::env::temp_dir(),
+                    &mut bwt,
+                )?;
+                assert_eq!(String::from_utf8(bwt)?, expected, "{} {}", text, block_size);
+            }
+        }
+        let mut bwt = Vec::new();
+        assert!(external_burrows_wheeler_transform(
+            "AC$GT",
+            &DNA_BW,
+            2,
+            std::env::temp_dir(),
+            &mut bwt
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_bwt2() -> Result<(), Box<dyn Error>> {
+        let records = vec![
+            Fasta::new("chr1", "GATTACAGGCTTACCGATNNNNNNACGGTTAGCATCAGG"),
+            Fasta::new("chr2", "CATTAACGGATTACAGGCTTACC"),
+            Fasta::new("chr3", "NNNNGATTACA"),
+        ];
+        let (expected, _, expected_table) =
+            records_burrows_wheeler_transform_sa_is(&records, &DNA_BW_N)?;
+        let mut bwt = Vec::new();
+        let table = records_burrows_wheeler_transform_external(
+            &records,
+            &DNA_BW_N,
+            7,
+            std::env::temp_dir(),
+            &mut bwt,
+        )?;
+        assert_eq!(String::from_utf8(bwt)?, expected);
+        assert_eq!(table, expected_table);
+
+        assert_eq!(external_block_size(1000, 1 << 20)?, ((1 << 20) - 3000) / 64);
+        assert!(external_block_size(1 << 30, 1 << 20).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_bwt3() -> Result<(), Box<dyn Error>> {
+        // The rank structure of the BWT fits its byte of the per-symbol budget
+        let bwt = (0..100_000)
+            .map(|i| match i % 5000 {
+                0 => b'$',
+                1..=300 => b'N',
+                _ => b"ACGT"[i * 7 % 4],
+            })
+            .collect::<Vec<_>>();
+        let packed = PackedBwt::new(&bwt, &DNA_BW_N)?;
+        let bytes =
+            packed.codes().len() + 8 * packed.blocks().len() + 6 * 8 * packed.exceptions().runs();
+        assert!(bytes <= bwt.len() * (EXTERNAL_BYTES_PER_SYMBOL - 2));
+        Ok(())
+    }
+}
@@ -1,17 +1,17 @@
-use std::collections::HashMap;
+use crate::utils::SymbolMap;
 use std::error::Error;
 
 pub(crate) fn calculate_fm_index(
     bwt_bytes: &[u8],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     fw_step: usize,
 ) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
-    let char_count = char_map.len(); // Number of distinct characters
+    let char_count = char_map.size(); // Number of distinct characters
     let mut fm_index = Vec::with_capacity(bwt_bytes.len() / fw_step + 1);
 
     let mut fw_row = vec![0; char_count];
     for (i, &byte) in bwt_bytes.iter().enumerate() {
-        if let Some(&idx) = char_map.get(&byte) {
+        if let Some(idx) = char_map.rank(&byte) {
             fw_row[idx] += 1;
         }
 
@@ -23,9 +23,73 @@ pub(crate) fn calculate_fm_index(
     Ok(fm_index)
 }
 
+/// Rank queries over a BWT, answered either from occurrence checkpoints or
+/// from a packed BWT. They only read the BWT, so one index can serve queries
+/// from many threads.
+pub(crate) trait Occurrences: Sync {
+    fn len(&self) -> usize;
+
+    /// Symbol index of the BWT character in `row`.
+    fn symbol(&self, row: usize) -> usize;
+
+    /// Occurrences of `symbol` in BWT rows `0..=row`.
+    fn occurrence(&self, symbol: usize, row: usize) -> usize;
+
+    /// Row of the suffix one character longer than the suffix in `row`.
+    fn last_to_first(&self, first_col_starts: &[usize], row: usize) -> usize {
+        let symbol = self.symbol(row);
+        first_col_starts[symbol] + self.occurrence(symbol, row) - 1
+    }
+}
+
+/// Occurrence checkpoints over a BWT. Counts between checkpoints are recovered
+/// by scanning the BWT bytes from the nearest checkpoint at or before a row.
+pub(crate) struct OccurrenceTable<'a, M> {
+    bwt_bytes: &'a [u8],
+    fm_index: &'a [Vec<usize>],
+    fw_step: usize,
+    char_map: &'a M,
+}
+
+impl<'a, M: SymbolMap<u8>> OccurrenceTable<'a, M> {
+    pub(crate) fn new(
+        bwt_bytes: &'a [u8],
+        fm_index: &'a [Vec<usize>],
+        fw_step: usize,
+        char_map: &'a M,
+    ) -> Self {
+        OccurrenceTable {
+            bwt_bytes,
+            fm_index,
+            fw_step,
+            char_map,
+        }
+    }
+}
+
+impl<M: SymbolMap<u8>> Occurrences for OccurrenceTable<'_, M> {
+    fn len(&self) -> usize {
+        self.bwt_bytes.len()
+    }
+
+    fn symbol(&self, row: usize) -> usize {
+        self.char_map.rank(&self.bwt_bytes[row]).unwrap()
+    }
+
+    fn occurrence(&self, symbol: usize, row: usize) -> usize {
+        let checkpoint = row / self.fw_step;
+        let offset = self.fm_index[checkpoint][symbol];
+        offset
+            + self.bwt_bytes[checkpoint * self.fw_step + 1..=row]
+                .iter()
+                .filter(|b| self.char_map.rank(b) == Some(symbol))
+                .count()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::bwt::fm::calculate_fm_index;
+    use crate::bwt::fm::{calculate_fm_index, OccurrenceTable, Occurrences};
     use std::collections::HashMap;
     use std::error::Error;
 
@@ -56,4 +120,19 @@ mod tests {
         assert_eq!(fm_index, vec![vec![0, 1, 0], vec![1, 3, 2]]);
         Ok(())
     }
+
+    #[test]
+    fn test_occurrence1() -> Result<(), Box<dyn Error>> {
+        let bwt_bytes = "abba$aa".as_bytes();
+        let char_map = HashMap::from([(b'$', 0), (b'a', 1), (b'b', 2)]);
+        let dense = calculate_fm_index(bwt_bytes, &char_map, 1)?;
+        let sparse = calculate_fm_index(bwt_bytes, &char_map, 3)?;
+        let table = OccurrenceTable::new(bwt_bytes, &sparse, 3, &char_map);
+        for (row, counts) in dense.iter().enumerate() {
+            for (symbol, &count) in counts.iter().enumerate() {
+                assert_eq!(table.occurrence(symbol, row), count);
+            }
+        }
+        Ok(())
+    }
 }
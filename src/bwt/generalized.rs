@@ -0,0 +1,267 @@
+use crate::bwt::lcp::lcp_array;
+use crate::bwt::suffix_array::{suffix_array_induced_sorting, DenseRanks};
+use crate::utils::Alphabet;
+use std::collections::VecDeque;
+use std::error::Error;
+
+const NO_TEXT: usize = usize::MAX;
+
+/// Longest substring shared by all texts, with one start offset per text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommonSubstring {
+    pub length: usize,
+    pub positions: Vec<usize>,
+}
+
+/// Shortest substring of one text that occurs in none of the others.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct UniqueSubstring {
+    pub length: usize,
+    pub position: usize,
+}
+
+/// Suffix and LCP arrays over several texts. Each text is followed by its own
+/// separator, which sorts before every letter and occurs nowhere else, so no
+/// common prefix ever runs from one text into the next.
+pub struct GeneralizedSuffixArray {
+    starts: Vec<usize>,
+    lengths: Vec<usize>,
+    owners: Vec<usize>,
+    suffixes: Vec<usize>,
+    lcp: Vec<usize>,
+}
+
+impl GeneralizedSuffixArray {
+    pub fn new(texts: &[&[u8]], alphabet: &Alphabet) -> Result<Self, Box<dyn Error>> {
+        let k = texts.len();
+        let mut encoded = Vec::with_capacity(texts.iter().map(|t| t.len() + 1).sum());
+        let mut owners = Vec::with_capacity(encoded.capacity());
+        let mut starts = Vec::with_capacity(k);
+        for (i, text) in texts.iter().enumerate() {
+            alphabet.validate(text)?;
+            if let Some(p) = text.iter().position(|&b| b == alphabet.sentinel()) {
+                return Err(format!("Text {} contains the sentinel at position {}", i, p).into());
+            }
+            starts.push(encoded.len());
+            // Letters follow the k separators; rank 0 is the sentinel
+            encoded.extend(text.iter().map(|&b| k - 1 + alphabet.rank(b).unwrap()));
+            owners.extend(std::iter::repeat_n(i, text.len()));
+            encoded.push(i);
+            owners.push(NO_TEXT);
+        }
+
+        let ranks = DenseRanks {
+            size: k + alphabet.size() - 1,
+        };
+        let suffixes = suffix_array_induced_sorting(&encoded, &ranks)?.into_usizes();
+        let lcp = lcp_array(&encoded, &suffixes);
+        Ok(GeneralizedSuffixArray {
+            starts,
+            lengths: texts.iter().map(|t| t.len()).collect(),
+            owners,
+            suffixes,
+            lcp,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Text that the suffix in `row` starts in, or `NO_TEXT` for the empty
+    /// suffix and suffixes starting with a separator.
+    fn owner(&self, row: usize) -> usize {
+        self.owners
+            .get(self.suffixes[row])
+            .copied()
+            .unwrap_or(NO_TEXT)
+    }
+
+    fn offset(&self, row: usize) -> usize {
+        self.suffixes[row] - self.starts[self.owner(row)]
+    }
+
+    /// Longest substring that occurs in every text. Found by sliding a window
+    /// over the suffix array that holds a suffix of each text and taking the
+    /// minimum LCP inside it.
+    pub fn longest_common_substring(&self) -> Option<CommonSubstring> {
+        let k = self.len();
+        if k == 1 {
+            return (self.lengths[0] > 0).then(|| CommonSubstring {
+                length: self.lengths[0],
+                positions: vec![0],
+            });
+        }
+
+        // The empty suffix and the separators sort first
+        let first = k + 1;
+        let mut counts = vec![0; k];
+        let mut covered = 0;
+        let mut minima = VecDeque::new();
+        let mut best: Option<(usize, usize, usize)> = None;
+        let mut top = first;
+        for bottom in first..self.suffixes.len() {
+            let owner = self.owner(bottom);
+            counts[owner] += 1;
+            if counts[owner] == 1 {
+                covered += 1;
+            }
+            if bottom > first {
+                while minima
+                    .back()
+                    .is_some_and(|&j| self.lcp[j] >= self.lcp[bottom])
+                {
+                    minima.pop_back();
+                }
+                minima.push_back(bottom);
+            }
+            while covered == k {
+                // Minimum of lcp[top + 1..=bottom]
+                let length = self.lcp[*minima.front().unwrap()];
+                if best.is_none_or(|(l, _, _)| length > l) {
+                    best = Some((length, top, bottom));
+                }
+                let owner = self.owner(top);
+                counts[owner] -= 1;
+                if counts[owner] == 0 {
+                    covered -= 1;
+                }
+                top += 1;
+                while minima.front().is_some_and(|&j| j <= top) {
+                    minima.pop_front();
+                }
+            }
+        }
+
+        let (length, top, bottom) = best.filter(|&(length, _, _)| length > 0)?;
+        let mut positions = vec![None; k];
+        for row in top..=bottom {
+            positions[self.owner(row)].get_or_insert(self.offset(row));
+        }
+        Some(CommonSubstring {
+            length,
+            positions: positions.into_iter().map(Option::unwrap).collect(),
+        })
+    }
+
+    /// For every text, its shortest substring that occurs in no other text,
+    /// preferring the leftmost one. A suffix shares at most `m` characters with
+    /// other texts, where `m` is the LCP with the nearest suffix of another text
+    /// above or below it, so its prefix of length `m + 1` is unique.
+    pub fn shortest_unique_substrings(&self) -> Vec<Option<UniqueSubstring>> {
+        let rows = self.suffixes.len();
+        let owners = (0..rows).map(|row| self.owner(row)).collect::<Vec<_>>();
+
+        let mut above = vec![0; rows];
+        for row in 1..rows {
+            above[row] = if owners[row - 1] != owners[row] {
+                self.lcp[row]
+            } else {
+                above[row - 1].min(self.lcp[row])
+            };
+        }
+        let mut below = vec![0; rows];
+        for row in (0..rows - 1).rev() {
+            below[row] = if owners[row + 1] != owners[row] {
+                self.lcp[row + 1]
+            } else {
+                below[row + 1].min(self.lcp[row + 1])
+            };
+        }
+
+        let mut unique: Vec<Option<UniqueSubstring>> = vec![None; self.len()];
+        for row in 0..rows {
+            let owner = owners[row];
+            if owner == NO_TEXT {
+                continue;
+            }
+            let length = above[row].max(below[row]) + 1;
+            let position = self.offset(row);
+            if position + length > self.lengths[owner] {
+                continue;
+            }
+            let candidate = UniqueSubstring { length, position };
+            if unique[owner].is_none_or(|u| (length, position) < (u.length, u.position)) {
+                unique[owner] = Some(candidate);
+            }
+        }
+        unique
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::generalized::{CommonSubstring, GeneralizedSuffixArray, UniqueSubstring};
+    use crate::utils::alphabet::PROTEIN_ALPHABET;
+    use crate::utils::DNA_BW;
+    use std::error::Error;
+
+    fn assert_common(texts: &[&[u8]], length: usize) -> Result<(), Box<dyn Error>> {
+        let gsa = GeneralizedSuffixArray::new(texts, &DNA_BW)?;
+        let common = gsa.longest_common_substring().unwrap();
+        assert_eq!(common.length, length);
+        let first = &texts[0][common.positions[0]..][..length];
+        for (text, &p) in texts.iter().zip(common.positions.iter()) {
+            assert_eq!(&text[p..p + length], first);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_longest_common_substring1() -> Result<(), Box<dyn Error>> {
+        assert_common(&[b"TCGGTAGATTGCGCCCACTC", b"AGGGGCTCGCAGTGTAAGAA"], 3)
+    }
+
+    #[test]
+    fn test_longest_common_substring2() -> Result<(), Box<dyn Error>> {
+        assert_common(&[b"GATTACA", b"TAGACCA", b"ATACA"], 2)?;
+        let gsa = GeneralizedSuffixArray::new(&[b"ACGT", b"ACGT"], &DNA_BW)?;
+        assert_eq!(
+            gsa.longest_common_substring(),
+            Some(CommonSubstring {
+                length: 4,
+                positions: vec![0, 0]
+            })
+        );
+        let gsa = GeneralizedSuffixArray::new(&[b"AAA", b"CCC"], &DNA_BW)?;
+        assert_eq!(gsa.longest_common_substring(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shortest_unique_substrings1() -> Result<(), Box<dyn Error>> {
+        let texts: [&[u8]; 2] = [b"CCAAGCTGCTAGAGG", b"CATGCTGGGCTGGCT"];
+        let gsa = GeneralizedSuffixArray::new(&texts, &DNA_BW)?;
+        assert_eq!(
+            gsa.shortest_unique_substrings(),
+            vec![
+                Some(UniqueSubstring {
+                    length: 2,
+                    position: 0
+                }),
+                Some(UniqueSubstring {
+                    length: 2,
+                    position: 1
+                })
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_shortest_unique_substrings2() -> Result<(), Box<dyn Error>> {
+        let texts: [&[u8]; 3] = [b"MKV", b"AMKVW", b"KV"];
+        let gsa = GeneralizedSuffixArray::new(&texts, &PROTEIN_ALPHABET)?;
+        let unique = gsa.shortest_unique_substrings();
+        assert_eq!(unique[0], None);
+        assert_eq!(
+            unique[1],
+            Some(UniqueSubstring {
+                length: 1,
+                position: 0
+            })
+        );
+        assert_eq!(unique[2], None);
+        Ok(())
+    }
+}
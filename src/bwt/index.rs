@@ -0,0 +1,609 @@
+use crate::archive::crc32::crc32;
+use crate::bwt::approximate::{bw_approximate_match_position, ApproximateMatch};
+use crate::bwt::batch::par_queries;
+use crate::bwt::bit_vector::BitVector;
+use crate::bwt::bwt::burrows_wheeler_transform_suffixes;
+use crate::bwt::counts::{char_counts, get_first_col_starts};
+use crate::bwt::degenerate::{bw_degenerate_match_position, NPolicy};
+use crate::bwt::err::IndexFormatError;
+use crate::bwt::fm::Occurrences;
+use crate::bwt::matching::{bw_match_position, bw_matching};
+use crate::bwt::rank::{Exceptions, PackedBwt};
+use crate::bwt::records::{
+    join_records, validate_records, with_record_separator, RecordHit, RecordTable, Strand,
+    RECORD_SEPARATOR,
+};
+use crate::bwt::sampled::SampledSuffixArray;
+use crate::utils::{dna_complement, iupac_complement, Alphabet, Fasta};
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"STURGFMI";
+const VERSION: u32 = 5;
+
+/// Self-contained FM-index: the BWT packed with rank blocks, first-column
+/// starts and a sampled suffix array, so patterns can be counted
+/// and located without rebuilding anything from the genome. All records of a
+/// multi-FASTA share one index and hits are mapped back to their record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FMIndex {
+    alphabet: Alphabet,
+    bwt: PackedBwt,
+    counts: Vec<usize>,
+    first_col_starts: Vec<usize>,
+    suffixes: SampledSuffixArray,
+    records: RecordTable,
+}
+
+impl FMIndex {
+    pub fn from_text(
+        text: &str,
+        alphabet: &Alphabet,
+        sa_sample_rate: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_records(&[Fasta::new("", text)], alphabet, sa_sample_rate)
+    }
+
+    pub fn from_records(
+        records: &[Fasta],
+        alphabet: &Alphabet,
+        sa_sample_rate: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        validate_records(records, alphabet)?;
+        let (text, records) = join_records(records)?;
+        let alphabet = with_record_separator(alphabet)?;
+        let (bwt, full_suffixes) = burrows_wheeler_transform_suffixes(&text, &alphabet)?;
+        let suffixes = SampledSuffixArray::from_suffixes(full_suffixes.iter(), sa_sample_rate)?;
+        drop(full_suffixes);
+        let counts = char_counts(bwt.as_bytes(), &alphabet)?;
+        let first_col_starts = get_first_col_starts(&counts)?;
+        let bwt = PackedBwt::new(bwt.as_bytes(), &alphabet)?;
+
+        Ok(FMIndex {
+            alphabet,
+            bwt,
+            counts,
+            first_col_starts,
+            suffixes,
+            records,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bwt.len()
+    }
+
+    pub fn records(&self) -> &RecordTable {
+        &self.records
+    }
+
+    /// Range of BWT rows whose suffixes start with `pattern`.
+    pub fn backward_search(&self, pattern: &str) -> Result<Option<(usize, usize)>, Box<dyn Error>> {
+        bw_matching(
+            &self.first_col_starts,
+            &self.bwt,
+            &self.counts,
+            &self.alphabet,
+            pattern,
+        )
+    }
+
+    pub fn count(&self, pattern: &str) -> Result<usize, Box<dyn Error>> {
+        Ok(self
+            .backward_search(pattern)?
+            .map_or(0, |(top, bottom)| bottom - top + 1))
+    }
+
+    pub fn locate(&self, pattern: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+        bw_match_position(
+            &self.first_col_starts,
+            &self.suffixes,
+            &self.bwt,
+            &self.counts,
+            &self.alphabet,
+            pattern,
+        )
+    }
+
+    pub fn locate_approximate(
+        &self,
+        pattern: &str,
+        max_mismatches: usize,
+    ) -> Result<Vec<ApproximateMatch>, Box<dyn Error>> {
+        bw_approximate_match_position(
+            &self.first_col_starts,
+            &self.suffixes,
+            &self.bwt,
+            &self.counts,
+            &self.alphabet,
+            pattern,
+            max_mismatches,
+        )
+    }
+
+    /// Positions matching `pattern` with its IUPAC codes expanded into their
+    /// bases, and reference Ns treated as `n_policy` says.
+    pub fn locate_degenerate(
+        &self,
+        pattern: &str,
+        n_policy: NPolicy,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        bw_degenerate_match_position(
+            &self.first_col_starts,
+            &self.suffixes,
+            &self.bwt,
+            &self.counts,
+            &self.alphabet,
+            pattern,
+            n_policy,
+        )
+    }
+
+    /// Hits of the degenerate `pattern` on both strands, as record coordinates.
+    pub fn locate_records_degenerate(
+        &self,
+        pattern: &str,
+        n_policy: NPolicy,
+    ) -> Result<Vec<RecordHit>, Box<dyn Error>> {
+        let reverse = iupac_complement(pattern)?;
+        let mut strands = vec![(pattern, Strand::Forward)];
+        if reverse != pattern {
+            strands.push((&reverse, Strand::Reverse));
+        }
+
+        let mut hits = Vec::new();
+        for (strand_pattern, strand) in strands {
+            for position in self.locate_degenerate(strand_pattern, n_policy)? {
+                let hit = self
+                    .records
+                    .hit(position, strand)
+                    .ok_or(format!("Position {} is outside every record", position))?;
+                hits.push(hit);
+            }
+        }
+        hits.sort();
+        Ok(hits)
+    }
+
+    /// Hits of `pattern` on both strands, as record coordinates.
+    pub fn locate_records(&self, pattern: &str) -> Result<Vec<RecordHit>, Box<dyn Error>> {
+        Ok(self
+            .locate_records_approximate(pattern, 0)?
+            .into_iter()
+            .map(|(hit, _)| hit)
+            .collect())
+    }
+
+    /// Hits of `pattern` within `max_mismatches` on both strands, each with its
+    /// mismatch count.
+    pub fn locate_records_approximate(
+        &self,
+        pattern: &str,
+        max_mismatches: usize,
+    ) -> Result<Vec<(RecordHit, usize)>, Box<dyn Error>> {
+        if pattern.as_bytes().contains(&RECORD_SEPARATOR) {
+            return Err("Pattern contains the record separator".into());
+        }
+        let reverse = dna_complement(pattern)?;
+        let mut strands = vec![(pattern, Strand::Forward)];
+        if reverse != pattern {
+            strands.push((&reverse, Strand::Reverse));
+        }
+
+        let mut hits = Vec::new();
+        for (strand_pattern, strand) in strands {
+            let matches = if max_mismatches == 0 {
+                self.locate(strand_pattern)?
+                    .into_iter()
+                    .map(|p| (p, 0))
+                    .collect()
+            } else {
+                self.locate_approximate(strand_pattern, max_mismatches)?
+            };
+            for (position, mismatches) in matches {
+                let hit = self
+                    .records
+                    .hit(position, strand)
+                    .ok_or(format!("Position {} is outside every record", position))?;
+                hits.push((hit, mismatches));
+            }
+        }
+        hits.sort();
+        Ok(hits)
+    }
+
+    /// Answers `query` for every pattern in parallel, with the results in
+    /// pattern order. The index is only read, so all threads share it.
+    pub fn batch<T: Send>(
+        &self,
+        patterns: &[&str],
+        query: impl Fn(&Self, &str) -> Result<T, Box<dyn Error>> + Sync,
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        par_queries(patterns, |pattern| query(self, pattern))
+    }
+
+    /// Writes the index with a CRC-32 after each section, so that a damaged
+    /// file is rejected by [`Self::read`] instead of answering wrong queries.
+    pub fn write(&self, file: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(fs::File::create(file)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        let mut section = Vec::new();
+        write_bytes(&mut section, self.alphabet.symbols())?;
+        write_section(&mut writer, &mut section)?;
+
+        write_usize(&mut section, self.bwt.len())?;
+        write_bytes(&mut section, self.bwt.codes())?;
+        write_u64s(&mut section, self.bwt.blocks())?;
+        write_usizes(&mut section, self.bwt.exceptions().starts())?;
+        write_usizes(&mut section, self.bwt.exceptions().ends())?;
+        write_usizes(&mut section, self.bwt.exceptions().symbols())?;
+        write_section(&mut writer, &mut section)?;
+
+        write_usizes(&mut section, &self.counts)?;
+        write_usizes(&mut section, &self.first_col_starts)?;
+        write_section(&mut writer, &mut section)?;
+
+        write_usize(&mut section, self.suffixes.sample_rate())?;
+        write_usize(&mut section, self.suffixes.len())?;
+        write_u64s(&mut section, self.suffixes.sampled_rows().words())?;
+        write_usizes(&mut section, self.suffixes.samples())?;
+        write_section(&mut writer, &mut section)?;
+
+        write_usize(&mut section, self.records.len())?;
+        for record in 0..self.records.len() {
+            write_bytes(&mut section, self.records.title(record).as_bytes())?;
+            write_usize(&mut section, self.records.start(record))?;
+            write_usize(&mut section, self.records.record_len(record))?;
+        }
+        write_section(&mut writer, &mut section)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read(file: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read(file)?;
+        let mut reader = IndexReader::new(&content);
+        let corrupt = |e: Box<dyn Error>| IndexFormatError::Corrupt(e.to_string());
+
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(IndexFormatError::InvalidMagic.into());
+        }
+        let version = u32::from_le_bytes(reader.take(4)?.try_into()?);
+        if version != VERSION {
+            return Err(IndexFormatError::UnsupportedVersion(version, VERSION).into());
+        }
+
+        let start = reader.offset;
+        let symbols = reader.bytes()?;
+        reader.checksum(start, "alphabet")?;
+        let alphabet = Alphabet::from_symbols(&symbols).map_err(corrupt)?;
+
+        let start = reader.offset;
+        let bwt_len = reader.usize()?;
+        let codes = reader.bytes()?;
+        let blocks = reader.u64s()?;
+        let (run_starts, run_ends, run_symbols) =
+            (reader.usizes()?, reader.usizes()?, reader.usizes()?);
+        reader.checksum(start, "packed BWT")?;
+        if codes.len() != alphabet.size() {
+            return Err(IndexFormatError::Corrupt("inconsistent section sizes".to_string()).into());
+        }
+        let bwt = Exceptions::from_runs(run_starts, run_ends, run_symbols, codes.len())
+            .and_then(|exceptions| PackedBwt::from_parts(bwt_len, codes, blocks, exceptions))
+            .map_err(corrupt)?;
+
+        let start = reader.offset;
+        let counts = reader.usizes()?;
+        let first_col_starts = reader.usizes()?;
+        reader.checksum(start, "symbol counts")?;
+        let bwt_counts = (0..alphabet.size())
+            .map(|s| {
+                bwt_len
+                    .checked_sub(1)
+                    .map_or(0, |row| bwt.occurrence(s, row))
+            })
+            .collect::<Vec<_>>();
+        if counts != bwt_counts
+            || counts.iter().sum::<usize>() != bwt_len
+            || first_col_starts != get_first_col_starts(&counts)?
+        {
+            return Err(
+                IndexFormatError::Corrupt("counts do not match the BWT".to_string()).into(),
+            );
+        }
+
+        let start = reader.offset;
+        let sample_rate = reader.usize()?;
+        let sa_len = reader.usize()?;
+        let sampled_rows = reader.u64s()?;
+        let samples = reader.usizes()?;
+        reader.checksum(start, "suffix array samples")?;
+        if sa_len != bwt_len {
+            return Err(IndexFormatError::Corrupt("inconsistent section sizes".to_string()).into());
+        }
+        let suffixes = BitVector::from_words(sa_len, sampled_rows)
+            .and_then(|rows| SampledSuffixArray::from_parts(sample_rate, rows, samples))
+            .map_err(corrupt)?;
+
+        let start = reader.offset;
+        let record_count = reader.usize()?;
+        let (mut titles, mut starts, mut lengths) = (Vec::new(), Vec::new(), Vec::new());
+        for _ in 0..record_count {
+            titles.push(String::from_utf8(reader.bytes()?).map_err(|e| corrupt(e.into()))?);
+            starts.push(reader.usize()?);
+            lengths.push(reader.usize()?);
+        }
+        reader.checksum(start, "record table")?;
+        // The text ends with the sentinel, which belongs to no record
+        let records = RecordTable::from_parts(titles, starts, lengths, bwt_len.saturating_sub(1))
+            .map_err(corrupt)?;
+
+        Ok(FMIndex {
+            alphabet,
+            bwt,
+            counts,
+            first_col_starts,
+            suffixes,
+            records,
+        })
+    }
+}
+
+/// Writes `section` followed by its CRC-32, and empties it for the next one.
+fn write_section(writer: &mut impl Write, section: &mut Vec<u8>) -> Result<(), Box<dyn Error>> {
+    writer.write_all(section)?;
+    writer.write_all(&crc32(section).to_le_bytes())?;
+    section.clear();
+    Ok(())
+}
+
+fn write_usize(writer: &mut impl Write, value: usize) -> Result<(), Box<dyn Error>> {
+    writer.write_all(&(value as u64).to_le_bytes())?;
+    Ok(())
+}
+
+fn write_bytes(writer: &mut impl Write, values: &[u8]) -> Result<(), Box<dyn Error>> {
+    write_usize(writer, values.len())?;
+    writer.write_all(values)?;
+    Ok(())
+}
+
+fn write_u64s(writer: &mut impl Write, values: &[u64]) -> Result<(), Box<dyn Error>> {
+    write_usize(writer, values.len())?;
+    for &v in values {
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_usizes(writer: &mut impl Write, values: &[usize]) -> Result<(), Box<dyn Error>> {
+    write_usize(writer, values.len())?;
+    for &v in values {
+        write_usize(writer, v)?;
+    }
+    Ok(())
+}
+
+struct IndexReader<'a> {
+    content: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> IndexReader<'a> {
+    fn new(content: &'a [u8]) -> Self {
+        IndexReader { content, offset: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], IndexFormatError> {
+        let end = self
+            .offset
+            .checked_add(n)
+            .ok_or(IndexFormatError::Truncated)?;
+        let slice = self
+            .content
+            .get(self.offset..end)
+            .ok_or(IndexFormatError::Truncated)?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u64(&mut self) -> Result<u64, IndexFormatError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn usize(&mut self) -> Result<usize, IndexFormatError> {
+        Ok(self.u64()? as usize)
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, IndexFormatError> {
+        let n = self.usize()?;
+        Ok(self.take(n)?.to_vec())
+    }
+
+    fn u64s(&mut self) -> Result<Vec<u64>, IndexFormatError> {
+        let n = self.usize()?;
+        (0..n).map(|_| self.u64()).collect()
+    }
+
+    fn usizes(&mut self) -> Result<Vec<usize>, IndexFormatError> {
+        let n = self.usize()?;
+        (0..n).map(|_| self.usize()).collect()
+    }
+
+    /// Checks the CRC-32 stored after the section that began at `start`.
+    fn checksum(&mut self, start: usize, section: &str) -> Result<(), IndexFormatError> {
+        let expected = crc32(&self.content[start..self.offset]);
+        let stored = u32::from_le_bytes(self.take(4)?.try_into().unwrap());
+        if stored != expected {
+            return Err(IndexFormatError::Corrupt(format!(
+                "checksum mismatch in {}",
+                section
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
+    use crate::bwt::degenerate::NPolicy;
+    use crate::bwt::err::IndexFormatError;
+    use crate::bwt::index::FMIndex;
+    use crate::bwt::match_count::bw_match_counts;
+    use crate::bwt::matching::bw_match_positions;
+    use crate::bwt::records::{RecordHit, Strand};
+    use crate::utils::{Fasta, DNA_BW, DNA_BW_N};
+    use std::env::temp_dir;
+    use std::error::Error;
+    use std::fs;
+
+    #[test]
+    fn test_fm_index_query1() -> Result<(), Box<dyn Error>> {
+        let text = "AATCGGGTTCAATCGGGGT";
+        let patterns = ["ATCG", "GGGT", "T", "CA", "ACGT"];
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(text, &DNA_BW)?;
+        let counts = bw_match_counts(&bwt, &patterns, &DNA_BW, 1)?;
+        let positions = bw_match_positions(&bwt, &sa, &patterns, &DNA_BW, 1)?;
+        for sample_rate in [1, 4, 5] {
+            let index = FMIndex::from_text(text, &DNA_BW, sample_rate)?;
+            for (i, pattern) in patterns.iter().enumerate() {
+                assert_eq!(index.count(pattern)?, counts[i]);
+                assert_eq!(index.locate(pattern)?, positions[i]);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fm_index_round_trip1() -> Result<(), Box<dyn Error>> {
+        let index = FMIndex::from_text("GATTACAGATTACACATTAG", &DNA_BW, 3)?;
+        let file = temp_dir().join(format!("sturgeon_fm_index_{}.fmi", std::process::id()));
+        index.write(&file)?;
+        let loaded = FMIndex::read(&file);
+        fs::remove_file(&file)?;
+        let loaded = loaded?;
+        assert_eq!(loaded, index);
+        assert_eq!(loaded.locate("ATTA")?, vec![1, 8, 15]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fm_index_invalid_file1() -> Result<(), Box<dyn Error>> {
+        let file = temp_dir().join(format!("sturgeon_fm_invalid_{}.fmi", std::process::id()));
+        fs::write(&file, b"NOTANINDEX")?;
+        let loaded = FMIndex::read(&file);
+        fs::remove_file(&file)?;
+        assert!(loaded.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_fm_index_invalid_file2() -> Result<(), Box<dyn Error>> {
+        let index = FMIndex::from_text("GATTACAGATTACACATTAG", &DNA_BW, 3)?;
+        let file = temp_dir().join(format!("sturgeon_fm_corrupt_{}.fmi", std::process::id()));
+        index.write(&file)?;
+        let content = fs::read(&file)?;
+
+        // Any flipped byte is caught by the magic, the version or a checksum
+        let mut rejected = true;
+        for position in 0..content.len() {
+            let mut corrupt = content.clone();
+            corrupt[position] ^= 0x10;
+            fs::write(&file, &corrupt)?;
+            rejected &= FMIndex::read(&file).is_err_and(|e| e.is::<IndexFormatError>());
+        }
+        let checksum_error = {
+            let mut corrupt = content.clone();
+            let last = corrupt.len() - 5;
+            corrupt[last] ^= 0x01;
+            fs::write(&file, &corrupt)?;
+            FMIndex::read(&file)
+        };
+        fs::remove_file(&file)?;
+        assert!(rejected);
+        assert!(matches!(
+            checksum_error.unwrap_err().downcast_ref(),
+            Some(IndexFormatError::Corrupt(message)) if message.contains("checksum")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fm_index_records1() -> Result<(), Box<dyn Error>> {
+        let records = vec![
+            Fasta::new("chr1 first", "ACGTTGCA"),
+            Fasta::new("chr2 second", "TTGCANNACG"),
+        ];
+        let index = FMIndex::from_records(&records, &DNA_BW_N, 3)?;
+        assert_eq!(
+            index.locate_records("ACG")?,
+            vec![
+                RecordHit::new("chr1", 0, Strand::Forward),
+                RecordHit::new("chr1", 1, Strand::Reverse),
+                RecordHit::new("chr2", 7, Strand::Forward),
+            ]
+        );
+        // Both would only occur across the record boundary
+        assert_eq!(index.locate_records("CAT")?, vec![]);
+        assert_eq!(index.locate_records("GCATT")?, vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fm_index_degenerate1() -> Result<(), Box<dyn Error>> {
+        let records = vec![
+            Fasta::new("chr1 first", "ACGTTGCA"),
+            Fasta::new("chr2 second", "TTGCANNACG"),
+        ];
+        let index = FMIndex::from_records(&records, &DNA_BW_N, 3)?;
+        // GCR is GCA or GCG, and its reverse complement YGC is CGC or TGC
+        assert_eq!(
+            index.locate_records_degenerate("GCR", NPolicy::NeverMatch)?,
+            vec![
+                RecordHit::new("chr1", 4, Strand::Reverse),
+                RecordHit::new("chr1", 5, Strand::Forward),
+                RecordHit::new("chr2", 1, Strand::Reverse),
+                RecordHit::new("chr2", 2, Strand::Forward),
+            ]
+        );
+        assert_eq!(
+            index.locate_records_degenerate("CANNA", NPolicy::NeverMatch)?,
+            vec![]
+        );
+        assert_eq!(
+            index.locate_records_degenerate("CANNA", NPolicy::MatchAnything)?,
+            vec![RecordHit::new("chr2", 3, Strand::Forward)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_fm_index_batch1() -> Result<(), Box<dyn Error>> {
+        fn shared<T: Send + Sync>(_: &T) {}
+        let text = "GATTACAGATTACACATGCATGGATTACAGGCATCAGATTACA";
+        let index = FMIndex::from_text(text, &DNA_BW, 4)?;
+        shared(&index);
+
+        let kmers = (0..=text.len() - 4)
+            .map(|i| &text[i..i + 4])
+            .collect::<Vec<_>>();
+        let counts = index.batch(&kmers, FMIndex::count)?;
+        let sequential = kmers
+            .iter()
+            .map(|k| index.count(k))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(counts, sequential);
+        assert_eq!(
+            index.batch(&["GATTACA", "CATG"], |index, p| index.locate(p))?,
+            vec![vec![0, 7, 22, 36], vec![14, 18]]
+        );
+        assert!(index.batch(&["ACGT", "ACXT"], FMIndex::count).is_err());
+        Ok(())
+    }
+}
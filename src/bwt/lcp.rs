@@ -0,0 +1,85 @@
+/// Kasai's LCP construction. `suffixes` is a suffix array as produced by SA-IS,
+/// with the empty suffix in row 0, and entry `i` of the result is the length of
+/// the longest common prefix of the suffixes in rows `i - 1` and `i`.
+pub fn lcp_array<T: Copy + Eq>(text: &[T], suffixes: &[usize]) -> Vec<usize> {
+    kasai(text, suffixes, |_| true)
+}
+
+/// Like [`lcp_array`], but a common prefix never extends over `stop`, so that
+/// repeats do not run across record separators.
+pub(crate) fn lcp_array_until(text: &[u8], suffixes: &[usize], stop: u8) -> Vec<usize> {
+    kasai(text, suffixes, |b| b != stop)
+}
+
+fn kasai<T: Copy + Eq>(
+    text: &[T],
+    suffixes: &[usize],
+    matchable: impl Fn(T) -> bool,
+) -> Vec<usize> {
+    let n = text.len();
+    let mut ranks = vec![0; suffixes.len()];
+    for (row, &suffix) in suffixes.iter().enumerate() {
+        ranks[suffix] = row;
+    }
+
+    let mut lcp = vec![0; suffixes.len()];
+    let mut h: usize = 0;
+    for i in 0..n {
+        let row = ranks[i];
+        if row == 0 {
+            h = 0;
+            continue;
+        }
+        let j = suffixes[row - 1];
+        while i + h < n && j + h < n && text[i + h] == text[j + h] && matchable(text[i + h]) {
+            h += 1;
+        }
+        lcp[row] = h;
+        // The suffix at i + 1 shares at least h - 1 characters with its predecessor
+        h = h.saturating_sub(1);
+    }
+    lcp
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::lcp::{lcp_array, lcp_array_until};
+    use crate::bwt::suffix_array_induced_sorting;
+    use crate::utils::DNA_BW;
+    use std::error::Error;
+
+    fn naive_lcp(text: &[u8], suffixes: &[usize]) -> Vec<usize> {
+        let mut lcp = vec![0; suffixes.len()];
+        for row in 1..suffixes.len() {
+            let (a, b) = (&text[suffixes[row - 1]..], &text[suffixes[row]..]);
+            lcp[row] = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+        }
+        lcp
+    }
+
+    #[test]
+    fn test_lcp_array1() -> Result<(), Box<dyn Error>> {
+        let text = b"GATTACAGATTACACATTAG";
+        let suffixes = suffix_array_induced_sorting(text, &DNA_BW)?.into_usizes();
+        assert_eq!(lcp_array(text, &suffixes), naive_lcp(text, &suffixes));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcp_array2() -> Result<(), Box<dyn Error>> {
+        let text = b"banana";
+        let suffixes = vec![6, 5, 3, 1, 0, 4, 2];
+        assert_eq!(lcp_array(text, &suffixes), vec![0, 0, 1, 3, 0, 0, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcp_array_until1() {
+        let text = b"ab|ab|";
+        let suffixes = vec![6, 3, 0, 4, 1, 5, 2];
+        assert_eq!(
+            lcp_array_until(text, &suffixes, b'|'),
+            vec![0, 0, 2, 0, 1, 0, 0]
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use crate::bwt::bucket::{find_bucket_heads, find_bucket_tails};
+use crate::bwt::suffix_array::SuffixIndex;
+use crate::utils::SymbolMap;
 use std::cmp::Ordering;
-use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Debug, Display};
-use std::hash::Hash;
 
 pub const L: u8 = b'L';
 pub const S: u8 = b'S';
@@ -73,67 +73,64 @@ pub(crate) fn lms_substrings_are_equal<T: PartialEq>(
     }
 }
 
-pub(crate) fn guess_lms_sort<T: Copy + Eq + Hash + Debug>(
+pub(crate) fn guess_lms_sort<T: Copy + Eq + Debug, I: SuffixIndex>(
     text_bytes: &[T],
-    char_map: &HashMap<T, usize>,
+    char_map: &impl SymbolMap<T>,
     bucket_sizes: &[usize],
     type_map: &[u8],
-) -> Result<Vec<Option<usize>>, Box<dyn Error>> {
+) -> Result<Vec<I>, Box<dyn Error>> {
     let n = text_bytes.len();
-    let mut guessed_suffix_array = vec![None; n + 1];
+    let mut guessed_suffix_array = vec![I::EMPTY; n + 1];
 
     let mut bucket_tails = find_bucket_tails(bucket_sizes)?;
     for (i, byte) in text_bytes.iter().enumerate() {
         if is_lms_char(type_map, i)? {
 
-            let bucket_index = *char_map.get(byte)
+            let bucket_index = char_map.rank(byte)
                 .ok_or_else(|| format!("Byte {:?} not in map", byte))?;
-            guessed_suffix_array[bucket_tails[bucket_index]] = Some(i);
+            guessed_suffix_array[bucket_tails[bucket_index]] = I::from_usize(i);
             bucket_tails[bucket_index] -= 1;
         }
     }
-    guessed_suffix_array[0] = Some(n);
+    guessed_suffix_array[0] = I::from_usize(n);
 
     Ok(guessed_suffix_array)
 }
 
-pub(crate) fn induce_sort_l<T: Copy + Eq + Hash + Display>(
-    guessed_suffix_array: &mut [Option<usize>],
+pub(crate) fn induce_sort_l<T: Copy + Eq + Display, I: SuffixIndex>(
+    guessed_suffix_array: &mut [I],
     text_bytes: &[T],
-    char_map: &HashMap<T, usize>,
+    char_map: &impl SymbolMap<T>,
     bucket_sizes: &[usize],
     type_map: &[u8],
 ) -> Result<(), Box<dyn Error>> {
     let mut bucket_heads = find_bucket_heads(bucket_sizes)?;
     let n = text_bytes.len();
     for i in 0..=n {
-        let Some(j) = guessed_suffix_array[i] else {
+        let j = guessed_suffix_array[i];
+        if j == I::EMPTY || j.as_usize() == 0 {
             continue;
-        };
-
-        // Check bounds first to avoid unnecessary conditions
-        if j == 0 {
-            continue;
-        };
+        }
+        let j = j.as_usize();
 
         let prev_pos = j - 1;
         if type_map[prev_pos] == L {
             // Use direct array indexing with unwrap_or_else for better performance
-            let bucket_index = *char_map
-                .get(&text_bytes[prev_pos])
+            let bucket_index = char_map
+                .rank(&text_bytes[prev_pos])
                 .expect(&format!("Character {} should exist in char_map", &text_bytes[prev_pos]));
 
-            guessed_suffix_array[bucket_heads[bucket_index]] = Some(prev_pos);
+            guessed_suffix_array[bucket_heads[bucket_index]] = I::from_usize(prev_pos);
             bucket_heads[bucket_index] += 1;
         }
     }
     Ok(())
 }
 
-pub(crate) fn induce_sort_s<T: Copy + Eq + Hash>(
-    guessed_suffix_array: &mut [Option<usize>],
+pub(crate) fn induce_sort_s<T: Copy + Eq, I: SuffixIndex>(
+    guessed_suffix_array: &mut [I],
     text_bytes: &[T],
-    char_map: &HashMap<T, usize>,
+    char_map: &impl SymbolMap<T>,
     bucket_sizes: &[usize],
     type_map: &[u8],
 ) -> Result<(), Box<dyn Error>> {
@@ -141,55 +138,54 @@ pub(crate) fn induce_sort_s<T: Copy + Eq + Hash>(
     let n = text_bytes.len();
 
     for i in (0..=n).rev() {
-        let Some(j) = guessed_suffix_array[i] else {
-            continue;
-        };
-
-        // Check bounds first to avoid unnecessary conditions
-        if j == 0 {
+        let j = guessed_suffix_array[i];
+        if j == I::EMPTY || j.as_usize() == 0 {
             continue;
-        };
+        }
+        let j = j.as_usize();
 
         let prev_pos = j - 1;
         if type_map[prev_pos] == S {
-            let bucket_index = *char_map.get(&text_bytes[prev_pos]).unwrap();
-            guessed_suffix_array[bucket_tails[bucket_index]] = Some(prev_pos);
+            let bucket_index = char_map.rank(&text_bytes[prev_pos]).unwrap();
+            guessed_suffix_array[bucket_tails[bucket_index]] = I::from_usize(prev_pos);
             bucket_tails[bucket_index] -= 1;
         }
     }
     Ok(())
 }
 
-pub(crate) fn accurate_lms_sort<T: Copy + Eq + Hash>(
+pub(crate) fn accurate_lms_sort<T: Copy + Eq, I: SuffixIndex>(
     text_bytes: &[T],
-    char_map: &HashMap<T, usize>,
+    char_map: &impl SymbolMap<T>,
     bucket_sizes: &[usize],
-    summary_suffix_array: &[usize],
-    summary_suffix_offsets: &[usize],
-) -> Result<Vec<Option<usize>>, Box<dyn Error>> {
+    summary_suffix_array: &[I],
+    summary_suffix_offsets: &[I],
+) -> Result<Vec<I>, Box<dyn Error>> {
     let n = text_bytes.len();
     // A suffix for every character, plus the empty suffix
-    let mut suffix_offsets = vec![None; n + 1];
+    let mut suffix_offsets = vec![I::EMPTY; n + 1];
 
     // Find bucket tails for placing suffixes
     let mut bucket_tails = find_bucket_tails(bucket_sizes)?;
 
     // Iterate through summary suffix array in reverse order
     for &suffix_index in summary_suffix_array.iter().skip(2).rev() {
-        let string_index = summary_suffix_offsets[suffix_index];
+        let string_index = summary_suffix_offsets[suffix_index.as_usize()];
 
         // Which bucket does this suffix go into?
-        let bucket_index = *char_map.get(&text_bytes[string_index]).unwrap();
+        let bucket_index = char_map
+            .rank(&text_bytes[string_index.as_usize()])
+            .unwrap();
 
         // Add the suffix at the tail of the bucket
-        suffix_offsets[bucket_tails[bucket_index]] = Some(string_index);
+        suffix_offsets[bucket_tails[bucket_index]] = string_index;
 
         // Move the tail pointer down
         bucket_tails[bucket_index] -= 1;
     }
 
     // Always include the empty suffix at the beginning
-    suffix_offsets[0] = Some(n);
+    suffix_offsets[0] = I::from_usize(n);
 
     Ok(suffix_offsets)
 }
@@ -200,7 +196,7 @@ mod tests {
     use crate::bwt::lms::{
         accurate_lms_sort, build_type_map, guess_lms_sort, induce_sort_l, induce_sort_s, L, S,
     };
-    use crate::bwt::suffix_array::make_summary_suffix_array;
+    use crate::bwt::suffix_array::{make_summary_suffix_array, SuffixIndex};
     use crate::bwt::summary::summarize_suffix_array;
     use std::collections::HashMap;
     use std::error::Error;
@@ -222,11 +218,11 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let guessed_suffix_array =
+        let guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         let gsa = guessed_suffix_array
             .into_iter()
-            .map(|g| g.map(|s| s as i32).unwrap_or(-1))
+            .map(|g| if g == u32::EMPTY { -1 } else { g as i32 })
             .collect::<Vec<_>>();
         assert_eq!(gsa, vec![7, 4, 1, -1, -1, -1, -1, -1]);
         Ok(())
@@ -240,7 +236,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -251,7 +247,7 @@ mod tests {
         )?;
         let gsa = guessed_suffix_array
             .into_iter()
-            .map(|g| g.map(|s| s as i32).unwrap_or(-1))
+            .map(|g| if g == u32::EMPTY { -1 } else { g as i32 })
             .collect::<Vec<_>>();
         assert_eq!(gsa, vec![7, 4, 1, 3, 2, 0, 6, 5]);
         Ok(())
@@ -265,7 +261,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -276,7 +272,7 @@ mod tests {
         )?;
         let gsa = guessed_suffix_array
             .into_iter()
-            .map(|g| g.map(|s| s as i32).unwrap_or(-1))
+            .map(|g| if g == u32::EMPTY { -1 } else { g as i32 })
             .collect::<Vec<_>>();
         assert_eq!(gsa, vec![9, -1, -1, 7, 4, 1, 6, 3, 0, 8]);
         Ok(())
@@ -290,7 +286,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -308,7 +304,7 @@ mod tests {
         )?;
         let gsa = guessed_suffix_array
             .into_iter()
-            .map(|g| g.map(|s| s as i32).unwrap_or(-1))
+            .map(|g| if g == u32::EMPTY { -1 } else { g as i32 })
             .collect::<Vec<_>>();
         assert_eq!(gsa, vec![7, 1, 4, 3, 2, 0, 6, 5]);
         Ok(())
@@ -322,7 +318,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -340,7 +336,7 @@ mod tests {
         )?;
         let gsa = guessed_suffix_array
             .into_iter()
-            .map(|g| g.map(|s| s as i32).unwrap_or(-1))
+            .map(|g| if g == u32::EMPTY { -1 } else { g as i32 })
             .collect::<Vec<_>>();
         assert_eq!(gsa, vec![9, 4, 1, 5, 2, 7, 6, 3, 0, 8]);
         Ok(())
@@ -354,7 +350,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -370,10 +366,7 @@ mod tests {
             &cabbage_bucket,
             &cabbage_types,
         )?;
-        let guessed_suffix_array = guessed_suffix_array
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
+        guessed_suffix_array.retain(|&s| s != u32::EMPTY);
         let (summary_string, summary_alphabet_size, summary_suffix_offsets) =
             summarize_suffix_array(cabbage, &guessed_suffix_array, &cabbage_types)?;
         let cabbage_summary_suffix_array =
@@ -387,7 +380,7 @@ mod tests {
         )?;
         let sa = suffix_array
             .into_iter()
-            .map(|g| g.map(|s| s as i32).unwrap_or(-1))
+            .map(|g| if g == u32::EMPTY { -1 } else { g as i32 })
             .collect::<Vec<_>>();
         assert_eq!(sa, vec![7, 1, 4, -1, -1, -1, -1, -1]);
         Ok(())
@@ -0,0 +1,183 @@
+use crate::bwt::approximate::bw_approximate_matching;
+use crate::bwt::bwt::records_burrows_wheeler_transform_sa_is;
+use crate::bwt::counts::{char_counts, get_first_col_starts};
+use crate::bwt::match_count::bwt_match_count;
+use crate::bwt::rank::PackedBwt;
+use crate::bwt::records::{with_record_separator, RecordTable};
+use crate::utils::{dna_complement, Alphabet, Fasta};
+use rayon::prelude::*;
+use std::error::Error;
+use std::io::Write;
+
+const NUCLEOTIDES: &[u8] = b"ACGT";
+
+/// Occurrence count of the k-mer starting at every position of every record,
+/// for positions with a whole k-mer inside their record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mappability {
+    k: usize,
+    records: RecordTable,
+    counts: Vec<Vec<usize>>,
+}
+
+impl Mappability {
+    /// Counts every k-mer of `records` in an index over all of them, within
+    /// `max_mismatches` and, with `both_strands`, adding the hits of its
+    /// reverse complement. K-mers holding anything but A, C, G and T count 0.
+    pub fn from_records(
+        records: &[Fasta],
+        alphabet: &Alphabet,
+        k: usize,
+        max_mismatches: usize,
+        both_strands: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        if k == 0 {
+            return Err("K-mer length must be positive".into());
+        }
+        let (bwt, _, table) = records_burrows_wheeler_transform_sa_is(records, alphabet)?;
+        let alphabet = with_record_separator(alphabet)?;
+        let occurrences = PackedBwt::new(bwt.as_bytes(), &alphabet)?;
+        let counts = char_counts(bwt.as_bytes(), &alphabet)?;
+        let first_col_starts = get_first_col_starts(&counts)?;
+        drop(bwt);
+
+        let count = |pattern: &str| -> Result<usize, Box<dyn Error>> {
+            if max_mismatches == 0 {
+                bwt_match_count(&first_col_starts, &occurrences, &counts, &alphabet, pattern)
+            } else {
+                Ok(bw_approximate_matching(
+                    &first_col_starts,
+                    &occurrences,
+                    &counts,
+                    &alphabet,
+                    pattern,
+                    max_mismatches,
+                )?
+                .iter()
+                .map(|&(top, bottom, _)| bottom - top + 1)
+                .sum())
+            }
+        };
+        let kmer_count = |kmer: &str| -> Result<usize, Box<dyn Error>> {
+            if !kmer.bytes().all(|b| NUCLEOTIDES.contains(&b)) {
+                return Ok(0);
+            }
+            let mut total = count(kmer)?;
+            if both_strands {
+                // A reverse-complement palindrome already counts both strands
+                let reverse = dna_complement(kmer)?;
+                if reverse != kmer {
+                    total += count(&reverse)?;
+                }
+            }
+            Ok(total)
+        };
+
+        let counts = records
+            .iter()
+            .map(|record| {
+                let positions = (record.len() + 1).saturating_sub(k);
+                (0..positions)
+                    .into_par_iter()
+                    .map(|i| kmer_count(&record.text[i..i + k]).map_err(|e| e.to_string()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Mappability {
+            k,
+            records: table,
+            counts,
+        })
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn records(&self) -> &RecordTable {
+        &self.records
+    }
+
+    /// Counts of the k-mers starting at each position of record `record`.
+    pub fn counts(&self, record: usize) -> &[usize] {
+        &self.counts[record]
+    }
+
+    /// Writes the counts as a bedGraph track, with runs of equal counts merged
+    /// into one zero-based, half-open interval.
+    pub fn write_bedgraph(&self, writer: &mut impl Write) -> Result<(), Box<dyn Error>> {
+        writeln!(
+            writer,
+            "track type=bedGraph name=\"mappability k={}\"",
+            self.k
+        )?;
+        for (record, counts) in self.counts.iter().enumerate() {
+            let name = self.records.name(record);
+            let mut start = 0;
+            for end in 1..=counts.len() {
+                if end == counts.len() || counts[end] != counts[start] {
+                    writeln!(writer, "{}\t{}\t{}\t{}", name, start, end, counts[start])?;
+                    start = end;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::mappability::Mappability;
+    use crate::utils::{Fasta, DNA_BW_N};
+    use std::error::Error;
+
+    #[test]
+    fn test_mappability1() -> Result<(), Box<dyn Error>> {
+        let records = vec![
+            Fasta::new("chr1 first", "ACGTTACGTA"),
+            Fasta::new("chr2", "GGNCACG"),
+        ];
+        let track = Mappability::from_records(&records, &DNA_BW_N, 3, 0, false)?;
+        assert_eq!(track.counts(0), &[3, 2, 1, 1, 1, 3, 2, 1]);
+        assert_eq!(track.counts(1), &[0, 0, 0, 1, 3]);
+
+        // ACG occurs three times and its reverse complement CGT twice
+        let track = Mappability::from_records(&records, &DNA_BW_N, 3, 0, true)?;
+        assert_eq!(track.counts(0), &[5, 5, 1, 1, 2, 5, 5, 2]);
+
+        let mut bedgraph = Vec::new();
+        track.write_bedgraph(&mut bedgraph)?;
+        assert_eq!(
+            String::from_utf8(bedgraph)?,
+            "track type=bedGraph name=\"mappability k=3\"\n\
+             chr1\t0\t2\t5\n\
+             chr1\t2\t4\t1\n\
+             chr1\t4\t5\t2\n\
+             chr1\t5\t7\t5\n\
+             chr1\t7\t8\t2\n\
+             chr2\t0\t3\t0\n\
+             chr2\t3\t4\t1\n\
+             chr2\t4\t5\t5\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mappability2() -> Result<(), Box<dyn Error>> {
+        let records = vec![Fasta::new("chr1", "AAAACAAATAAA")];
+        let track = Mappability::from_records(&records, &DNA_BW_N, 4, 1, false)?;
+        // Brute force over the same windows
+        let text = records[0].text.as_bytes();
+        let expected = (0..=text.len() - 4)
+            .map(|i| {
+                (0..=text.len() - 4)
+                    .filter(|&j| (0..4).filter(|&o| text[i + o] != text[j + o]).count() <= 1)
+                    .count()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(track.counts(0), expected.as_slice());
+        assert!(Mappability::from_records(&records, &DNA_BW_N, 0, 0, false).is_err());
+        Ok(())
+    }
+}
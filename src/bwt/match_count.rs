@@ -1,20 +1,21 @@
+use crate::bwt::batch::par_queries;
 use crate::bwt::counts::{char_counts, get_first_col_starts};
-use crate::bwt::fm::calculate_fm_index;
+use crate::bwt::fm::{calculate_fm_index, OccurrenceTable, Occurrences};
 use crate::bwt::matching::bw_matching;
-use crate::utils::Fasta;
-use std::collections::HashMap;
+use crate::bwt::rank::PackedBwt;
+use crate::utils::{Fasta, SymbolMap};
 use std::error::Error;
 
-fn bwt_match_count(
+pub(crate) fn bwt_match_count(
     first_col_starts: &[usize],
-    fm_index: &[Vec<usize>],
+    occurrences: &impl Occurrences,
     counts: &[usize],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     pattern: &str,
 ) -> Result<usize, Box<dyn Error>> {
     Ok(
         if let Some((top, bottom)) =
-            bw_matching(first_col_starts, fm_index, counts, char_map, pattern)?
+            bw_matching(first_col_starts, occurrences, counts, char_map, pattern)?
         {
             bottom - top + 1
         } else {
@@ -26,7 +27,7 @@ fn bwt_match_count(
 pub fn bw_match_counts_fasta(
     bwt: &str,
     patterns: &[Fasta],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     fw_step: usize,
 ) -> Result<Vec<usize>, Box<dyn Error>> {
     let pattern_strings = patterns.iter().map(|f| f.text.as_str()).collect::<Vec<_>>();
@@ -37,34 +38,52 @@ pub fn bw_match_counts_fasta(
 pub fn bw_match_counts(
     bwt: &str,
     patterns: &[&str],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     fw_step: usize,
 ) -> Result<Vec<usize>, Box<dyn Error>> {
     let bwt_bytes = bwt.as_bytes();
 
+    // Build the occurrence array more efficiently
+    let fm_index = calculate_fm_index(bwt_bytes, char_map, fw_step)?;
+    let occurrences = OccurrenceTable::new(bwt_bytes, &fm_index, fw_step, char_map);
+    bw_match_counts_with(bwt_bytes, &occurrences, patterns, char_map)
+}
+
+/// Same as [`bw_match_counts`], but ranks are answered from a 2-bit packed BWT
+/// instead of per-row checkpoints, which keeps the index close to text size.
+pub fn bw_match_counts_packed(
+    bwt: &str,
+    patterns: &[&str],
+    char_map: &impl SymbolMap<u8>,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let bwt_bytes = bwt.as_bytes();
+    let occurrences = PackedBwt::new(bwt_bytes, char_map)?;
+    bw_match_counts_with(bwt_bytes, &occurrences, patterns, char_map)
+}
+
+fn bw_match_counts_with(
+    bwt_bytes: &[u8],
+    occurrences: &impl Occurrences,
+    patterns: &[&str],
+    char_map: &impl SymbolMap<u8>,
+) -> Result<Vec<usize>, Box<dyn Error>> {
     // Count character occurrences
     let counts = char_counts(bwt_bytes, char_map)?;
 
     // Calculate starting positions in first column
     let first_col_starts = get_first_col_starts(&counts)?;
 
-    // Build the occurrence array more efficiently
-    let fm_index = calculate_fm_index(bwt_bytes, char_map, fw_step)?;
-
-    // Match each pattern
-    let results = patterns
-        .iter()
-        .map(|&pattern| bwt_match_count(&first_col_starts, &fm_index, &counts, char_map, pattern))
-        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
-
-    Ok(results)
+    // Match the patterns in parallel, the index being read-only
+    par_queries(patterns, |pattern| {
+        bwt_match_count(&first_col_starts, occurrences, &counts, char_map, pattern)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
-    use crate::bwt::match_count::bw_match_counts;
-    use crate::utils::DNA_BW;
+    use crate::bwt::match_count::{bw_match_counts, bw_match_counts_packed};
+    use crate::utils::{DNA_BW, DNA_BW_N};
     use std::error::Error;
 
     #[test]
@@ -102,4 +121,16 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_bw_match_counts_packed1() -> Result<(), Box<dyn Error>> {
+        let text = "AATCGGGTTCAATCGGGGTNNATCGRA";
+        let patterns = ["ATCG", "GGGT", "N", "NA", "GR", "T", "CC"];
+        let (bwt, _) = burrows_wheeler_transform_sa_is(text, &DNA_BW_N)?;
+        assert_eq!(
+            bw_match_counts_packed(&bwt, &patterns, &DNA_BW_N)?,
+            bw_match_counts(&bwt, &patterns, &DNA_BW_N, 1)?
+        );
+        Ok(())
+    }
 }
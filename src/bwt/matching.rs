@@ -1,127 +1,152 @@
+use crate::bwt::batch::par_queries;
 use crate::bwt::counts::{char_counts, get_first_col_starts};
-use crate::bwt::fm::calculate_fm_index;
-use std::collections::HashMap;
+use crate::bwt::fm::{calculate_fm_index, OccurrenceTable, Occurrences};
+use crate::bwt::rank::PackedBwt;
+use crate::bwt::sampled::SampledSuffixArray;
+use crate::utils::SymbolMap;
 use std::error::Error;
 
+/// Either a full suffix array or a sampled one that is completed by LF-walks.
+#[derive(Clone, Copy, Debug)]
+pub enum SuffixArrayRef<'a> {
+    Full(&'a [usize]),
+    Sampled(&'a SampledSuffixArray),
+}
+
+impl<'a> From<&'a [usize]> for SuffixArrayRef<'a> {
+    fn from(suffixes: &'a [usize]) -> Self {
+        SuffixArrayRef::Full(suffixes)
+    }
+}
+
+impl<'a> From<&'a Vec<usize>> for SuffixArrayRef<'a> {
+    fn from(suffixes: &'a Vec<usize>) -> Self {
+        SuffixArrayRef::Full(suffixes)
+    }
+}
+
+impl<'a> From<&'a SampledSuffixArray> for SuffixArrayRef<'a> {
+    fn from(suffixes: &'a SampledSuffixArray) -> Self {
+        SuffixArrayRef::Sampled(suffixes)
+    }
+}
+
 pub(crate) fn bw_matching(
     first_col_starts: &[usize],
-    fm_index: &[Vec<usize>],
+    occurrences: &impl Occurrences,
     counts: &[usize],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     pattern: &str,
 ) -> Result<Option<(usize, usize)>, Box<dyn Error>> {
-    let pattern_bytes = pattern.as_bytes();
-    let pattern_len = pattern_bytes.len();
-
     let mut top = 0;
-    let mut bottom = fm_index.len() - 1;
-
-    // Helper function to update the range
-    fn update_range(
-        symbol: usize,
-        top: usize,
-        bottom: usize,
-        first_col_starts: &[usize],
-        fm_index: &[Vec<usize>],
-        is_last_iter: bool,
-    ) -> (usize, usize) {
-        let new_top = if is_last_iter {
-            first_col_starts[symbol]
-        } else {
-            first_col_starts[symbol] + fm_index[top - 1][symbol]
-        };
-        let new_bottom = first_col_starts[symbol] + fm_index[bottom][symbol] - 1;
-        (new_top, new_bottom)
-    }
+    let mut bottom = occurrences.len() - 1;
 
     // Match pattern from end to beginning
-    for (i, &byte) in pattern_bytes.iter().enumerate().rev() {
-        let symbol = *char_map
-            .get(&byte)
-            .ok_or(format!("Symbol {} not found in char_map", byte.to_string()))?;
+    for &byte in pattern.as_bytes().iter().rev() {
+        let symbol = char_map
+            .rank(&byte)
+            .ok_or(format!("Symbol {} not found in char_map", byte))?;
 
         if counts[symbol] == 0 {
             return Ok(None);
         }
 
-        let is_last_iter = i == pattern_len - 1;
-        (top, bottom) = update_range(
-            symbol,
-            top,
-            bottom,
-            first_col_starts,
-            fm_index,
-            is_last_iter,
-        );
-
-        if top > bottom {
+        let before = if top == 0 {
+            0
+        } else {
+            occurrences.occurrence(symbol, top - 1)
+        };
+        let through = occurrences.occurrence(symbol, bottom);
+        if through == before {
             return Ok(None);
         }
+        top = first_col_starts[symbol] + before;
+        bottom = first_col_starts[symbol] + through - 1;
     }
     Ok(Some((top, bottom)))
 }
 
-pub fn bw_match_positions(
+pub fn bw_match_positions<'a>(
     bwt: &str,
-    suffixes: &[usize],
+    suffixes: impl Into<SuffixArrayRef<'a>>,
     patterns: &[&str],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     fw_step: usize,
 ) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
     let bwt_bytes = bwt.as_bytes();
 
+    // Build the occurrence array more efficiently
+    let fm_index = calculate_fm_index(bwt_bytes, char_map, fw_step)?;
+    let occurrences = OccurrenceTable::new(bwt_bytes, &fm_index, fw_step, char_map);
+    bw_match_positions_with(bwt_bytes, suffixes.into(), &occurrences, patterns, char_map)
+}
+
+/// Same as [`bw_match_positions`] with ranks taken from a 2-bit packed BWT.
+pub fn bw_match_positions_packed<'a>(
+    bwt: &str,
+    suffixes: impl Into<SuffixArrayRef<'a>>,
+    patterns: &[&str],
+    char_map: &impl SymbolMap<u8>,
+) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+    let bwt_bytes = bwt.as_bytes();
+    let occurrences = PackedBwt::new(bwt_bytes, char_map)?;
+    bw_match_positions_with(bwt_bytes, suffixes.into(), &occurrences, patterns, char_map)
+}
+
+fn bw_match_positions_with(
+    bwt_bytes: &[u8],
+    suffixes: SuffixArrayRef,
+    occurrences: &impl Occurrences,
+    patterns: &[&str],
+    char_map: &impl SymbolMap<u8>,
+) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
     // Count character occurrences
     let counts = char_counts(bwt_bytes, char_map)?;
 
     // Calculate starting positions in first column
     let first_col_starts = get_first_col_starts(&counts)?;
 
-    // Build the occurrence array more efficiently
-    let fm_index = calculate_fm_index(bwt_bytes, char_map, fw_step)?;
-
-    // Match each pattern
-    let results = patterns
-        .iter()
-        .map(|&pattern| {
-            bw_match_position(
-                &first_col_starts,
-                suffixes,
-                &fm_index,
-                &counts,
-                char_map,
-                pattern,
-            )
-        })
-        .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
-
-    Ok(results)
+    // Match the patterns in parallel, the index being read-only
+    par_queries(patterns, |pattern| {
+        bw_match_position(
+            &first_col_starts,
+            suffixes,
+            occurrences,
+            &counts,
+            char_map,
+            pattern,
+        )
+    })
 }
 
-pub fn bw_match_position(
+pub fn bw_match_position<'a>(
     first_col_starts: &[usize],
-    suffixes: &[usize],
-    fm_index: &[Vec<usize>],
+    suffixes: impl Into<SuffixArrayRef<'a>>,
+    occurrences: &impl Occurrences,
     counts: &[usize],
-    char_map: &HashMap<u8, usize>,
+    char_map: &impl SymbolMap<u8>,
     pattern: &str,
 ) -> Result<Vec<usize>, Box<dyn Error>> {
-    Ok(
-        if let Some((top, bottom)) =
-            bw_matching(first_col_starts, fm_index, counts, char_map, pattern)?
-        {
-            let mut starts = suffixes[top..=bottom].to_vec();
-            starts.sort();
-            starts
-        } else {
-            Vec::new()
-        },
-    )
+    let Some((top, bottom)) =
+        bw_matching(first_col_starts, occurrences, counts, char_map, pattern)?
+    else {
+        return Ok(Vec::new());
+    };
+    let mut starts = match suffixes.into() {
+        SuffixArrayRef::Full(suffixes) => suffixes[top..=bottom].to_vec(),
+        SuffixArrayRef::Sampled(suffixes) => (top..=bottom)
+            .map(|row| suffixes.locate(row, |r| occurrences.last_to_first(first_col_starts, r)))
+            .collect(),
+    };
+    starts.sort();
+    Ok(starts)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
-    use crate::bwt::matching::bw_match_positions;
+    use crate::bwt::matching::{bw_match_positions, bw_match_positions_packed};
+    use crate::bwt::sampled::SampledSuffixArray;
     use crate::utils::DNA_BW;
     use std::collections::HashMap;
     use std::error::Error;
@@ -168,4 +193,22 @@ mod tests {
         assert_eq!(matches, vec![vec![0, 1, 4]]);
         Ok(())
     }
+
+    #[test]
+    fn test_bw_match_position5() -> Result<(), Box<dyn Error>> {
+        let text = "AATCGGGTTCAATCGGGGTAATCG";
+        let patterns = ["ATCG", "GGGT", "AA", "T", "CC"];
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(text, &DNA_BW)?;
+        let expected = bw_match_positions(&bwt, &sa, &patterns, &DNA_BW, 1)?;
+        for sample_rate in [1, 2, 5, 32] {
+            let sampled = SampledSuffixArray::from_suffix_array(&sa, sample_rate)?;
+            for fw_step in [1, 4] {
+                let matches = bw_match_positions(&bwt, &sampled, &patterns, &DNA_BW, fw_step)?;
+                assert_eq!(matches, expected);
+            }
+            let matches = bw_match_positions_packed(&bwt, &sampled, &patterns, &DNA_BW)?;
+            assert_eq!(matches, expected);
+        }
+        Ok(())
+    }
 }
@@ -1,13 +1,46 @@
+mod approximate;
+mod batch;
+mod bidirectional;
+mod bit_vector;
 mod bucket;
 mod bwt;
 mod counts;
+mod degenerate;
+mod err;
+mod external;
 mod fm;
+mod generalized;
+mod index;
+mod lcp;
 mod lms;
+mod mappability;
 mod match_count;
 mod matching;
+mod r_index;
+mod rank;
+mod records;
+mod repeats;
+mod run_length;
+mod sampled;
 mod suffix_array;
 mod summary;
 
-pub use bwt::{fasta_burrows_wheeler_transform, fasta_burrows_wheeler_transform_sa_is};
-pub use match_count::{bw_match_counts, bw_match_counts_fasta};
+pub use bidirectional::BidirectionalIndex;
+pub use bwt::{
+    burrows_wheeler_transform_sa_is, fasta_burrows_wheeler_transform,
+    fasta_burrows_wheeler_transform_sa_is, inverse_burrows_wheeler_transform,
+    records_burrows_wheeler_transform_sa_is,
+};
+pub use degenerate::{is_degenerate, NPolicy};
+pub use external::{
+    external_block_size, in_memory_footprint, records_burrows_wheeler_transform_external,
+};
+pub use index::FMIndex;
+pub use mappability::Mappability;
+pub use match_count::{bw_match_counts, bw_match_counts_fasta, bw_match_counts_packed};
+pub use matching::{bw_match_positions, bw_match_positions_packed, SuffixArrayRef};
+pub use r_index::RIndex;
+pub use records::{RecordHit, RecordTable, Strand};
+pub use repeats::RepeatIndex;
+pub use sampled::SampledSuffixArray;
 pub use suffix_array::{suffix_array, suffix_array_induced_sorting};
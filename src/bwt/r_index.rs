@@ -0,0 +1,212 @@
+use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
+use crate::bwt::counts::{char_counts, get_first_col_starts};
+use crate::bwt::fm::Occurrences;
+use crate::bwt::matching::bw_matching;
+use crate::bwt::records::RECORD_SEPARATOR;
+use crate::bwt::run_length::RunLengthBwt;
+use crate::utils::Alphabet;
+use std::error::Error;
+
+/// Run-length compressed FM-index (r-index). Besides the run-length BWT it
+/// keeps two suffix array samples per run: the entry at the end of each run,
+/// which supplies a toehold during backward search, and the pair of entries
+/// around each run start, from which `phi` recovers the suffix of the previous
+/// row. Everything is O(r) words for r runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RIndex {
+    alphabet: Alphabet,
+    bwt: RunLengthBwt,
+    counts: Vec<usize>,
+    first_col_starts: Vec<usize>,
+    run_end_samples: Vec<usize>,
+    /// Suffix array entries at run starts, ascending...
+    phi_keys: Vec<usize>,
+    /// ...and the entry of the row above each of them.
+    phi_values: Vec<usize>,
+}
+
+impl RIndex {
+    pub fn from_text(text: &str, alphabet: &Alphabet) -> Result<Self, Box<dyn Error>> {
+        let (bwt, suffixes) = burrows_wheeler_transform_sa_is(text, alphabet)?;
+        Self::from_bwt(&bwt, &suffixes, alphabet)
+    }
+
+    /// Index over a BWT and its full suffix array, which is only read here.
+    pub fn from_bwt(
+        bwt: &str,
+        suffixes: &[usize],
+        alphabet: &Alphabet,
+    ) -> Result<Self, Box<dyn Error>> {
+        if bwt.len() != suffixes.len() {
+            return Err("BWT and suffix array differ in length".into());
+        }
+        let counts = char_counts(bwt.as_bytes(), alphabet)?;
+        let first_col_starts = get_first_col_starts(&counts)?;
+        let runs = RunLengthBwt::new(bwt.as_bytes(), alphabet)?;
+
+        let run_end_samples = (0..runs.runs())
+            .map(|run| suffixes[runs.run_end(run)])
+            .collect();
+        let mut phi = (1..runs.runs())
+            .map(|run| {
+                let row = runs.run_start(run);
+                (suffixes[row], suffixes[row - 1])
+            })
+            .collect::<Vec<_>>();
+        phi.sort();
+
+        Ok(RIndex {
+            alphabet: alphabet.clone(),
+            bwt: runs,
+            counts,
+            first_col_starts,
+            run_end_samples,
+            phi_keys: phi.iter().map(|&(key, _)| key).collect(),
+            phi_values: phi.iter().map(|&(_, value)| value).collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bwt.len()
+    }
+
+    pub fn runs(&self) -> usize {
+        self.bwt.runs()
+    }
+
+    pub fn count(&self, pattern: &str) -> Result<usize, Box<dyn Error>> {
+        self.check_pattern(pattern)?;
+        Ok(bw_matching(
+            &self.first_col_starts,
+            &self.bwt,
+            &self.counts,
+            &self.alphabet,
+            pattern,
+        )?
+        .map_or(0, |(top, bottom)| bottom - top + 1))
+    }
+
+    /// Sorted text positions of `pattern`. Backward search tracks the suffix
+    /// of the bottom row of the range, then `phi` walks up the range.
+    pub fn locate(&self, pattern: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+        self.check_pattern(pattern)?;
+        let mut top = 0;
+        let mut bottom = self.len() - 1;
+        let mut toehold = *self.run_end_samples.last().unwrap();
+        for &byte in pattern.as_bytes().iter().rev() {
+            let symbol = self
+                .alphabet
+                .rank(byte)
+                .ok_or(format!("Symbol {} not found in char_map", byte))?;
+            let before = if top == 0 {
+                0
+            } else {
+                self.bwt.occurrence(symbol, top - 1)
+            };
+            let through = self.bwt.occurrence(symbol, bottom);
+            if through == before {
+                return Ok(Vec::new());
+            }
+            toehold = if self.bwt.symbol(bottom) == symbol {
+                toehold - 1
+            } else {
+                // The last occurrence in the range ends a run, so it is sampled
+                let row = self.bwt.select(symbol, through).unwrap();
+                self.run_end_samples[self.bwt.run_of(row)] - 1
+            };
+            top = self.first_col_starts[symbol] + before;
+            bottom = self.first_col_starts[symbol] + through - 1;
+        }
+
+        let mut positions = Vec::with_capacity(bottom - top + 1);
+        positions.push(toehold);
+        for _ in top..bottom {
+            toehold = self.phi(toehold);
+            positions.push(toehold);
+        }
+        positions.sort();
+        Ok(positions)
+    }
+
+    /// Patterns may not hold the sentinel or the record separator, whose
+    /// matches would step the toehold before the start of the text.
+    fn check_pattern(&self, pattern: &str) -> Result<(), Box<dyn Error>> {
+        if pattern.as_bytes().contains(&self.alphabet.sentinel()) {
+            return Err("Pattern contains the sentinel".into());
+        }
+        if pattern.as_bytes().contains(&RECORD_SEPARATOR) {
+            return Err("Pattern contains the record separator".into());
+        }
+        Ok(())
+    }
+
+    /// Suffix array entry of the row above the row holding suffix `position`.
+    fn phi(&self, position: usize) -> usize {
+        let i = self.phi_keys.partition_point(|&k| k <= position) - 1;
+        self.phi_values[i] + position - self.phi_keys[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::bwt::burrows_wheeler_transform_sa_is;
+    use crate::bwt::match_count::bw_match_counts;
+    use crate::bwt::matching::bw_match_positions;
+    use crate::bwt::r_index::RIndex;
+    use crate::utils::{DNA_BW, DNA_BW_N};
+    use std::error::Error;
+
+    #[test]
+    fn test_r_index1() -> Result<(), Box<dyn Error>> {
+        let text = "AATCGGGTTCAATCGGGGT";
+        let patterns = ["ATCG", "GGGT", "T", "CA", "ACGT", "", "AATCGGGTTCAATCGGGGT"];
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(text, &DNA_BW)?;
+        let counts = bw_match_counts(&bwt, &patterns, &DNA_BW, 1)?;
+        let positions = bw_match_positions(&bwt, &sa, &patterns, &DNA_BW, 1)?;
+        let index = RIndex::from_text(text, &DNA_BW)?;
+        for (i, pattern) in patterns.iter().enumerate() {
+            assert_eq!(index.count(pattern)?, counts[i]);
+            assert_eq!(index.locate(pattern)?, positions[i]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_r_index2() -> Result<(), Box<dyn Error>> {
+        // Strains of one genome differing by a few substitutions
+        let strain = "GATTACAGGCTTACCGATNACGGTTAGCATCAGGCATTAACG";
+        let text = (0..12)
+            .map(|i| {
+                let mut s = strain.as_bytes().to_vec();
+                s[i * 3] = b"ACGT"[i % 4];
+                String::from_utf8(s).unwrap()
+            })
+            .collect::<String>();
+        let (bwt, sa) = burrows_wheeler_transform_sa_is(&text, &DNA_BW_N)?;
+        let index = RIndex::from_bwt(&bwt, &sa, &DNA_BW_N)?;
+        assert!(index.runs() < text.len() / 3);
+
+        let patterns = (0..text.len() - 8)
+            .step_by(7)
+            .map(|i| &text[i..i + 1 + i % 8])
+            .chain(["CATCAGG", "TTTT", "NACGG", "GGGGGGG"])
+            .collect::<Vec<_>>();
+        let counts = bw_match_counts(&bwt, &patterns, &DNA_BW_N, 4)?;
+        let positions = bw_match_positions(&bwt, &sa, &patterns, &DNA_BW_N, 4)?;
+        for (i, pattern) in patterns.iter().enumerate() {
+            assert_eq!(index.count(pattern)?, counts[i], "{}", pattern);
+            assert_eq!(index.locate(pattern)?, positions[i], "{}", pattern);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_r_index3() -> Result<(), Box<dyn Error>> {
+        let index = RIndex::from_text("AATCGGGTTCAATCGGGGT", &DNA_BW)?;
+        assert!(index.locate("$").is_err());
+        assert!(index.locate("GT$").is_err());
+        assert!(index.count("T$").is_err());
+        assert!(index.locate("GT|AA").is_err());
+        Ok(())
+    }
+}
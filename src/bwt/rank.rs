@@ -0,0 +1,457 @@
+use crate::bwt::fm::Occurrences;
+use crate::utils::SymbolMap;
+use std::error::Error;
+use std::io::{Cursor, Read, Seek};
+
+const CODES: usize = 4;
+const SYMBOLS_PER_WORD: usize = 32;
+const BLOCK_WORDS: usize = 4;
+const BLOCK_SYMBOLS: usize = SYMBOLS_PER_WORD * BLOCK_WORDS;
+/// Block layout: counts of every code before the block, then the packed words.
+/// Eight `u64`s keep one block inside a single cache line.
+const BLOCK_LEN: usize = CODES + BLOCK_WORDS;
+const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
+const EXCEPTION: u8 = u8::MAX;
+/// Code that exception rows share with the least frequent coded symbol, so
+/// that only this symbol pays for them and the most frequent one does not.
+const SHARED_CODE: u8 = CODES as u8 - 1;
+
+/// BWT packed at 2 bits per symbol with interleaved rank blocks. The four most
+/// frequent symbols get a 2-bit code; rows holding any other symbol ('$', N and
+/// other IUPAC codes) are stored as the last code and kept as runs in
+/// [`Exceptions`], which stay few even for the long N stretches of assemblies.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PackedBwt {
+    len: usize,
+    codes: Vec<u8>,
+    blocks: Vec<u64>,
+    exceptions: Exceptions,
+}
+
+/// Rows of the symbols without a 2-bit code, as maximal runs of one symbol.
+/// Each run and each symbol's list of runs carries the number of rows before
+/// it, so ranks cost one binary search.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Exceptions {
+    starts: Vec<usize>,
+    ends: Vec<usize>,
+    symbols: Vec<usize>,
+    /// Exception rows before each run.
+    before: Vec<usize>,
+    /// Per symbol, its runs and its rows before each of them.
+    symbol_runs: Vec<Vec<usize>>,
+    symbol_before: Vec<Vec<usize>>,
+}
+
+impl Exceptions {
+    /// Runs `[starts[k], ends[k])` of symbol `symbols[k]`, in row order.
+    pub(crate) fn from_runs(
+        starts: Vec<usize>,
+        ends: Vec<usize>,
+        symbols: Vec<usize>,
+        symbol_count: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        if starts.len() != ends.len() || starts.len() != symbols.len() {
+            return Err("Exception runs have inconsistent sizes".into());
+        }
+        let mut before = Vec::with_capacity(starts.len());
+        let mut symbol_runs = vec![Vec::new(); symbol_count];
+        let mut symbol_before = vec![Vec::new(); symbol_count];
+        let mut symbol_totals = vec![0; symbol_count];
+        let (mut total, mut previous_end) = (0, 0);
+        for k in 0..starts.len() {
+            if starts[k] < previous_end || ends[k] <= starts[k] || symbols[k] >= symbol_count {
+                return Err("Exception runs are not ordered".into());
+            }
+            before.push(total);
+            symbol_runs[symbols[k]].push(k);
+            symbol_before[symbols[k]].push(symbol_totals[symbols[k]]);
+            total += ends[k] - starts[k];
+            symbol_totals[symbols[k]] += ends[k] - starts[k];
+            previous_end = ends[k];
+        }
+        Ok(Exceptions {
+            starts,
+            ends,
+            symbols,
+            before,
+            symbol_runs,
+            symbol_before,
+        })
+    }
+
+    pub(crate) fn starts(&self) -> &[usize] {
+        &self.starts
+    }
+
+    pub(crate) fn ends(&self) -> &[usize] {
+        &self.ends
+    }
+
+    pub(crate) fn symbols(&self) -> &[usize] {
+        &self.symbols
+    }
+
+    pub(crate) fn runs(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Exception rows in `[0, end)` among the runs `runs`, whose rows before
+    /// each are `before`.
+    fn runs_rank(
+        &self,
+        runs: impl Fn(usize) -> usize,
+        count: usize,
+        before: &[usize],
+        end: usize,
+    ) -> usize {
+        let k = partition_point(count, |k| self.starts[runs(k)] < end);
+        if k == 0 {
+            return 0;
+        }
+        let run = runs(k - 1);
+        before[k - 1] + end.min(self.ends[run]) - self.starts[run]
+    }
+
+    /// Rows of any exception symbol in `[0, end)`.
+    fn rank_all(&self, end: usize) -> usize {
+        self.runs_rank(|k| k, self.runs(), &self.before, end)
+    }
+
+    /// Rows of exception `symbol` in `[0, end)`.
+    fn rank(&self, symbol: usize, end: usize) -> usize {
+        let runs = &self.symbol_runs[symbol];
+        self.runs_rank(|k| runs[k], runs.len(), &self.symbol_before[symbol], end)
+    }
+
+    fn symbol_at(&self, row: usize) -> Option<usize> {
+        let k = self.starts.partition_point(|&s| s <= row);
+        (k > 0 && row < self.ends[k - 1]).then(|| self.symbols[k - 1])
+    }
+
+    /// Adds `row`, which follows every row added so far.
+    fn push(&mut self, symbol: usize, row: usize) {
+        let k = self.starts.len();
+        if k > 0 && self.symbols[k - 1] == symbol && self.ends[k - 1] == row {
+            self.ends[k - 1] += 1;
+            return;
+        }
+        let total = self
+            .before
+            .last()
+            .map_or(0, |&b| b + self.ends[k - 1] - self.starts[k - 1]);
+        let symbol_total = self.symbol_runs[symbol].last().map_or(0, |&r| {
+            *self.symbol_before[symbol].last().unwrap() + self.ends[r] - self.starts[r]
+        });
+        self.starts.push(row);
+        self.ends.push(row + 1);
+        self.symbols.push(symbol);
+        self.before.push(total);
+        self.symbol_runs[symbol].push(k);
+        self.symbol_before[symbol].push(symbol_total);
+    }
+}
+
+/// First `k` in `[0, count)` for which `pred` fails, `pred` holding on a prefix.
+fn partition_point(count: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+impl PackedBwt {
+    pub(crate) fn new(
+        bwt_bytes: &[u8],
+        char_map: &impl SymbolMap<u8>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_reader(Cursor::new(bwt_bytes), char_map)
+    }
+
+    /// Packs a BWT read in two passes, one to pick the codes and one to pack,
+    /// so a BWT on disk never has to be loaded whole.
+    pub(crate) fn from_reader(
+        mut reader: impl Read + Seek,
+        char_map: &impl SymbolMap<u8>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let rank = |b: &u8| {
+            char_map
+                .rank(b)
+                .ok_or(format!("Symbol {} not found in char_map", b))
+        };
+        let mut chunk = [0u8; BLOCK_SYMBOLS];
+
+        let mut counts = vec![0; char_map.size()];
+        loop {
+            let filled = read_chunk(&mut reader, &mut chunk)?;
+            for b in &chunk[..filled] {
+                counts[rank(b)?] += 1;
+            }
+            if filled < BLOCK_SYMBOLS {
+                break;
+            }
+        }
+        let len = counts.iter().sum::<usize>();
+        let mut by_frequency = (0..counts.len()).collect::<Vec<_>>();
+        by_frequency.sort_by_key(|&s| (std::cmp::Reverse(counts[s]), s));
+        let mut codes = vec![EXCEPTION; counts.len()];
+        for (code, &s) in by_frequency.iter().take(CODES).enumerate() {
+            codes[s] = code as u8;
+        }
+
+        reader.rewind()?;
+        let mut exceptions =
+            Exceptions::from_runs(Vec::new(), Vec::new(), Vec::new(), counts.len())?;
+        let mut blocks = Vec::with_capacity((len / BLOCK_SYMBOLS + 1) * BLOCK_LEN);
+        let mut totals = [0u64; CODES];
+        loop {
+            let filled = read_chunk(&mut reader, &mut chunk)?;
+            let block_start = blocks.len() / BLOCK_LEN * BLOCK_SYMBOLS;
+            let header = totals;
+            let mut words = [0u64; BLOCK_WORDS];
+            for (i, b) in chunk[..filled].iter().enumerate() {
+                let s = rank(b)?;
+                let code = match codes[s] {
+                    EXCEPTION => {
+                        exceptions.push(s, block_start + i);
+                        SHARED_CODE
+                    }
+                    code => code,
+                };
+                words[i / SYMBOLS_PER_WORD] |= (code as u64) << (2 * (i % SYMBOLS_PER_WORD));
+                totals[code as usize] += 1;
+            }
+            // A full last chunk is followed by an empty block, so that a rank
+            // over the whole BWT needs no special case
+            blocks.extend_from_slice(&header);
+            blocks.extend_from_slice(&words);
+            if filled < BLOCK_SYMBOLS {
+                break;
+            }
+        }
+
+        Ok(PackedBwt {
+            len,
+            codes,
+            blocks,
+            exceptions,
+        })
+    }
+
+    pub(crate) fn from_parts(
+        len: usize,
+        codes: Vec<u8>,
+        blocks: Vec<u64>,
+        exceptions: Exceptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        if blocks.len() != (len / BLOCK_SYMBOLS + 1) * BLOCK_LEN
+            || codes.len() != exceptions.symbol_runs.len()
+            || exceptions.ends.last().is_some_and(|&end| end > len)
+        {
+            return Err("Packed BWT sections have inconsistent sizes".into());
+        }
+        let mut coded = codes
+            .iter()
+            .copied()
+            .filter(|&c| c != EXCEPTION)
+            .collect::<Vec<_>>();
+        coded.sort_unstable();
+        if !coded.into_iter().eq(0..CODES.min(codes.len()) as u8) {
+            return Err("Packed BWT codes are not one per frequent symbol".into());
+        }
+        // Every block header must hold the counts of the blocks before it
+        let mut totals = [0u64; CODES];
+        for block in blocks.chunks_exact(BLOCK_LEN) {
+            if block[..CODES] != totals {
+                return Err("Packed BWT rank blocks are inconsistent".into());
+            }
+            for &word in &block[CODES..] {
+                for (code, total) in totals.iter_mut().enumerate() {
+                    *total += code_matches(word, code as u8, u64::MAX) as u64;
+                }
+            }
+        }
+
+        let bwt = PackedBwt {
+            len,
+            codes,
+            blocks,
+            exceptions,
+        };
+        // Rows may only hold assigned codes, and exception rows the shared one
+        for code in 0..CODES as u8 {
+            let rows = bwt.code_rank(code, len);
+            if (rows > 0 && !bwt.codes.contains(&code))
+                || (code == SHARED_CODE && rows < bwt.exceptions.rank_all(len))
+            {
+                return Err("Packed BWT rows hold codes of no symbol".into());
+            }
+        }
+        Ok(bwt)
+    }
+
+    pub(crate) fn codes(&self) -> &[u8] {
+        &self.codes
+    }
+
+    pub(crate) fn blocks(&self) -> &[u64] {
+        &self.blocks
+    }
+
+    pub(crate) fn exceptions(&self) -> &Exceptions {
+        &self.exceptions
+    }
+
+    /// Occurrences of `code` in rows `[0, end)`, exceptions included as
+    /// [`SHARED_CODE`].
+    fn code_rank(&self, code: u8, end: usize) -> usize {
+        let block = &self.blocks[end / BLOCK_SYMBOLS * BLOCK_LEN..][..BLOCK_LEN];
+        let within = end % BLOCK_SYMBOLS;
+
+        let mut rank = block[code as usize] as usize;
+        let words = &block[CODES..];
+        for &word in &words[..within / SYMBOLS_PER_WORD] {
+            rank += code_matches(word, code, u64::MAX);
+        }
+        let rest = within % SYMBOLS_PER_WORD;
+        if rest > 0 {
+            rank += code_matches(
+                words[within / SYMBOLS_PER_WORD],
+                code,
+                (1 << (2 * rest)) - 1,
+            );
+        }
+        rank
+    }
+}
+
+/// Symbols of `word` coded as `code`, among those selected by `mask`.
+fn code_matches(word: u64, code: u8, mask: u64) -> usize {
+    let x = word ^ (code as u64 * EVEN_BITS);
+    (!(x | x >> 1) & EVEN_BITS & mask).count_ones() as usize
+}
+
+/// Fills `chunk` from `reader` unless the input ends first; returns the
+/// number of bytes read.
+fn read_chunk(reader: &mut impl Read, chunk: &mut [u8]) -> Result<usize, Box<dyn Error>> {
+    let mut filled = 0;
+    while filled < chunk.len() {
+        match reader.read(&mut chunk[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+impl Occurrences for PackedBwt {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn symbol(&self, row: usize) -> usize {
+        let word = self.blocks
+            [row / BLOCK_SYMBOLS * BLOCK_LEN + CODES + row % BLOCK_SYMBOLS / SYMBOLS_PER_WORD];
+        let code = (word >> (2 * (row % SYMBOLS_PER_WORD)) & 3) as u8;
+        if code == SHARED_CODE {
+            if let Some(symbol) = self.exceptions.symbol_at(row) {
+                return symbol;
+            }
+        }
+        self.codes.iter().position(|&c| c == code).unwrap()
+    }
+
+    fn occurrence(&self, symbol: usize, row: usize) -> usize {
+        match self.codes[symbol] {
+            EXCEPTION => self.exceptions.rank(symbol, row + 1),
+            SHARED_CODE => self.code_rank(SHARED_CODE, row + 1) - self.exceptions.rank_all(row + 1),
+            code => self.code_rank(code, row + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::fm::{calculate_fm_index, Occurrences};
+    use crate::bwt::rank::{PackedBwt, BLOCK_LEN};
+    use crate::utils::DNA_BW_N;
+    use std::error::Error;
+
+    #[test]
+    fn test_packed_bwt1() -> Result<(), Box<dyn Error>> {
+        // Long enough to span several blocks, with N and '$' as exceptions
+        let bwt = (0..300)
+            .map(|i| match i {
+                17 => b'$',
+                40..=45 | 250 => b'N',
+                _ => b"ACGTTGCA"[i * 7 % 8],
+            })
+            .collect::<Vec<_>>();
+        let packed = PackedBwt::new(&bwt, &DNA_BW_N)?;
+        let dense = calculate_fm_index(&bwt, &DNA_BW_N, 1)?;
+        assert_eq!(packed.len(), bwt.len());
+        for (row, counts) in dense.iter().enumerate() {
+            assert_eq!(packed.symbol(row), DNA_BW_N.rank(bwt[row]).unwrap());
+            for (symbol, &count) in counts.iter().enumerate() {
+                assert_eq!(packed.occurrence(symbol, row), count);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_bwt2() -> Result<(), Box<dyn Error>> {
+        // Exactly one block, so the trailing block holds the totals
+        let bwt = b"ACGT".repeat(32);
+        let packed = PackedBwt::new(&bwt, &DNA_BW_N)?;
+        assert_eq!(packed.occurrence(DNA_BW_N.rank(b'T').unwrap(), 127), 32);
+        assert_eq!(packed.occurrence(DNA_BW_N.rank(b'$').unwrap(), 127), 0);
+        let parts = PackedBwt::from_parts(
+            packed.len(),
+            packed.codes().to_vec(),
+            packed.blocks().to_vec(),
+            packed.exceptions().clone(),
+        )?;
+        assert_eq!(parts, packed);
+
+        let mut blocks = packed.blocks().to_vec();
+        blocks[BLOCK_LEN] += 1;
+        let corrupt = PackedBwt::from_parts(
+            packed.len(),
+            packed.codes().to_vec(),
+            blocks,
+            packed.exceptions().clone(),
+        );
+        assert!(corrupt.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_bwt3() -> Result<(), Box<dyn Error>> {
+        // Long stretches of N take one run each, however long
+        let bwt = (0..4000)
+            .map(|i| match i {
+                0 => b'$',
+                1000..=1299 | 2000..=2299 => b'N',
+                1500 => b'R',
+                _ => b"ACGT"[i % 4],
+            })
+            .collect::<Vec<_>>();
+        let packed = PackedBwt::new(&bwt, &DNA_BW_N)?;
+        assert_eq!(packed.exceptions().runs(), 4);
+        let dense = calculate_fm_index(&bwt, &DNA_BW_N, 1)?;
+        for (row, counts) in dense.iter().enumerate() {
+            assert_eq!(packed.symbol(row), DNA_BW_N.rank(bwt[row]).unwrap());
+            for (symbol, &count) in counts.iter().enumerate() {
+                assert_eq!(packed.occurrence(symbol, row), count);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+use crate::utils::{Alphabet, Fasta};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// Placed between records so that no pattern match can span two of them. It
+/// sorts after every sequence symbol, which keeps SA-IS buckets in byte order.
+pub const RECORD_SEPARATOR: u8 = b'|';
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Strand {
+    Forward,
+    Reverse,
+}
+
+impl Display for Strand {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            Strand::Forward => write!(f, "+"),
+            Strand::Reverse => write!(f, "-"),
+        }
+    }
+}
+
+/// A match resolved to its source record. `offset` is 0-based on the forward
+/// strand of the record, whichever strand the pattern matched.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RecordHit {
+    pub title: String,
+    pub offset: usize,
+    pub strand: Strand,
+}
+
+impl RecordHit {
+    pub fn new(title: &str, offset: usize, strand: Strand) -> Self {
+        RecordHit {
+            title: title.to_owned(),
+            offset,
+            strand,
+        }
+    }
+}
+
+impl Display for RecordHit {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.title, self.offset, self.strand)
+    }
+}
+
+/// Titles and start offsets of the records concatenated into one index text.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecordTable {
+    titles: Vec<String>,
+    starts: Vec<usize>,
+    lengths: Vec<usize>,
+}
+
+impl RecordTable {
+    /// Table of records at `starts`, which must be ordered and end by `text_len`.
+    pub(crate) fn from_parts(
+        titles: Vec<String>,
+        starts: Vec<usize>,
+        lengths: Vec<usize>,
+        text_len: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        if titles.len() != starts.len() || starts.len() != lengths.len() {
+            return Err("Record table sections have inconsistent sizes".into());
+        }
+        let mut previous_end = 0;
+        for (&start, &len) in starts.iter().zip(&lengths) {
+            match start.checked_add(len) {
+                Some(end) if start >= previous_end && end <= text_len => previous_end = end,
+                _ => return Err("Records are not ordered inside the text".into()),
+            }
+        }
+        Ok(RecordTable {
+            titles,
+            starts,
+            lengths,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn title(&self, record: usize) -> &str {
+        &self.titles[record]
+    }
+
+    /// First word of the title, as used for sequence names in SAM and BED.
+    pub fn name(&self, record: usize) -> &str {
+        self.titles[record].split_whitespace().next().unwrap_or("")
+    }
+
+    pub fn start(&self, record: usize) -> usize {
+        self.starts[record]
+    }
+
+    pub fn record_len(&self, record: usize) -> usize {
+        self.lengths[record]
+    }
+
+    /// Record containing text position `position` and the offset inside it.
+    pub fn resolve(&self, position: usize) -> Option<(usize, usize)> {
+        let record = self
+            .starts
+            .partition_point(|&s| s <= position)
+            .checked_sub(1)?;
+        let offset = position - self.starts[record];
+        (offset < self.lengths[record]).then_some((record, offset))
+    }
+
+    pub(crate) fn hit(&self, position: usize, strand: Strand) -> Option<RecordHit> {
+        let (record, offset) = self.resolve(position)?;
+        Some(RecordHit::new(self.name(record), offset, strand))
+    }
+}
+
+/// Concatenates the records with [`RECORD_SEPARATOR`] between them.
+pub(crate) fn join_records(records: &[Fasta]) -> Result<(String, RecordTable), Box<dyn Error>> {
+    let mut text = String::with_capacity(records.iter().map(|r| r.len() + 1).sum());
+    let mut table = RecordTable::default();
+    for (i, record) in records.iter().enumerate() {
+        if record.text.as_bytes().contains(&RECORD_SEPARATOR) {
+            return Err(format!(
+                "Record {} contains the separator '{}'",
+                record.title, RECORD_SEPARATOR as char
+            )
+            .into());
+        }
+        if i > 0 {
+            text.push(RECORD_SEPARATOR as char);
+        }
+        table.titles.push(record.title.clone());
+        table.starts.push(text.len());
+        table.lengths.push(record.len());
+        text.push_str(&record.text);
+    }
+    Ok((text, table))
+}
+
+/// Checks every record against `alphabet`, naming the record at fault.
+pub(crate) fn validate_records(
+    records: &[Fasta],
+    alphabet: &Alphabet,
+) -> Result<(), Box<dyn Error>> {
+    for record in records {
+        alphabet
+            .validate(record.text.as_bytes())
+            .map_err(|e| format!("Record {}: {}", record.title, e))?;
+    }
+    Ok(())
+}
+
+/// `alphabet` with the record separator appended as its largest symbol.
+pub(crate) fn with_record_separator(alphabet: &Alphabet) -> Result<Alphabet, Box<dyn Error>> {
+    alphabet.with_symbol(RECORD_SEPARATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::records::{join_records, Strand, RECORD_SEPARATOR};
+    use crate::utils::Fasta;
+    use std::error::Error;
+
+    #[test]
+    fn test_join_records1() -> Result<(), Box<dyn Error>> {
+        let records = vec![
+            Fasta::new("chr1 first", "ACGT"),
+            Fasta::new("chr2 second", "GG"),
+            Fasta::new("chr3", "TTA"),
+        ];
+        let (text, table) = join_records(&records)?;
+        assert_eq!(text, format!("ACGT{0}GG{0}TTA", RECORD_SEPARATOR as char));
+        assert_eq!(table.resolve(0), Some((0, 0)));
+        assert_eq!(table.resolve(3), Some((0, 3)));
+        assert_eq!(table.resolve(4), None);
+        assert_eq!(table.resolve(6), Some((1, 1)));
+        assert_eq!(table.resolve(10), Some((2, 2)));
+        assert_eq!(
+            table.hit(8, Strand::Reverse).unwrap().to_string(),
+            "chr3:0:-"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+use crate::bwt::lcp::lcp_array_until;
+use crate::bwt::records::{
+    join_records, validate_records, with_record_separator, RecordTable, RECORD_SEPARATOR,
+};
+use crate::bwt::suffix_array::suffix_array_induced_sorting;
+use crate::utils::{Alphabet, Fasta};
+use std::error::Error;
+
+/// A substring of `length` characters that starts at every position in
+/// `positions`, sorted ascending.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Repeat {
+    pub length: usize,
+    pub positions: Vec<usize>,
+}
+
+/// `length` characters starting at `start` that repeat with period `period`,
+/// covering at least two full copies. `period` is always the smallest one.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct TandemRepeat {
+    pub start: usize,
+    pub period: usize,
+    pub length: usize,
+}
+
+impl TandemRepeat {
+    pub fn copies(&self) -> usize {
+        self.length / self.period
+    }
+}
+
+/// Suffix array and LCP array over all records of a multi-FASTA, joined by the
+/// record separator so that no repeat spans two records.
+pub struct RepeatIndex {
+    text: Vec<u8>,
+    suffixes: Vec<usize>,
+    lcp: Vec<usize>,
+    records: RecordTable,
+}
+
+impl RepeatIndex {
+    pub fn from_records(records: &[Fasta], alphabet: &Alphabet) -> Result<Self, Box<dyn Error>> {
+        validate_records(records, alphabet)?;
+        let (text, records) = join_records(records)?;
+        let alphabet = with_record_separator(alphabet)?;
+        let text = text.into_bytes();
+        let suffixes = suffix_array_induced_sorting(&text, &alphabet)?.into_usizes();
+        let lcp = lcp_array_until(&text, &suffixes, RECORD_SEPARATOR);
+        Ok(RepeatIndex {
+            text,
+            suffixes,
+            lcp,
+            records,
+        })
+    }
+
+    pub fn records(&self) -> &RecordTable {
+        &self.records
+    }
+
+    pub fn longest_repeats(&self) -> Vec<Repeat> {
+        longest_repeats(&self.text, &self.suffixes, &self.lcp)
+    }
+
+    pub fn maximal_repeats(&self, min_length: usize) -> Vec<Repeat> {
+        maximal_repeats(&self.text, &self.suffixes, &self.lcp, min_length)
+    }
+
+    pub fn tandem_repeats(&self, max_period: usize, min_copies: usize) -> Vec<TandemRepeat> {
+        tandem_repeats(
+            &self.text,
+            &self.suffixes,
+            &self.lcp,
+            max_period,
+            min_copies,
+        )
+    }
+}
+
+/// All repeats of the greatest length that occurs at least twice.
+pub(crate) fn longest_repeats(text: &[u8], suffixes: &[usize], lcp: &[usize]) -> Vec<Repeat> {
+    match lcp.iter().max() {
+        Some(&longest) if longest > 0 => maximal_repeats(text, suffixes, lcp, longest),
+        _ => Vec::new(),
+    }
+}
+
+/// Repeats of at least `min_length` that can be extended neither to the left
+/// nor to the right without losing an occurrence.
+pub(crate) fn maximal_repeats(
+    text: &[u8],
+    suffixes: &[usize],
+    lcp: &[usize],
+    min_length: usize,
+) -> Vec<Repeat> {
+    let min_length = min_length.max(1);
+    let mut repeats = Vec::new();
+    let mut report = |length: usize, top: usize, bottom: usize| {
+        let mut positions = suffixes[top..=bottom].to_vec();
+        // Right-maximal by construction; left-maximal if an occurrence starts
+        // a record, or unless every occurrence is preceded by the same character
+        let starts_record = |p: usize| p == 0 || text[p - 1] == RECORD_SEPARATOR;
+        let left_maximal = positions.iter().any(|&p| starts_record(p))
+            || positions
+                .iter()
+                .any(|&p| text[p - 1] != text[positions[0] - 1]);
+        if length >= min_length && left_maximal {
+            positions.sort();
+            repeats.push(Repeat { length, positions });
+        }
+    };
+
+    // Bottom-up traversal of the lcp-intervals; `stack` holds (lcp, left bound)
+    let mut stack = vec![(0, 0)];
+    for row in 1..=suffixes.len() {
+        let value = lcp.get(row).copied().unwrap_or(0);
+        let mut top = row - 1;
+        while value < stack.last().unwrap().0 {
+            let (length, bound) = stack.pop().unwrap();
+            report(length, bound, row - 1);
+            top = bound;
+        }
+        if value > stack.last().unwrap().0 {
+            stack.push((value, top));
+        }
+    }
+    repeats.sort_by(|a, b| (&a.positions, a.length).cmp(&(&b.positions, b.length)));
+    repeats
+}
+
+/// Runs with a primitive period of at most `max_period` that hold at least
+/// `min_copies` full copies (and never fewer than two). For each period the
+/// text is probed every `period` positions: a longest-common-extension query
+/// extends forward and a short scan extends backward.
+pub(crate) fn tandem_repeats(
+    text: &[u8],
+    suffixes: &[usize],
+    lcp: &[usize],
+    max_period: usize,
+    min_copies: usize,
+) -> Vec<TandemRepeat> {
+    let n = text.len();
+    let lce = LongestCommonExtension::new(suffixes, lcp);
+    let min_copies = min_copies.max(2);
+    let mut repeats = Vec::new();
+    for period in 1..=max_period.min(n / 2) {
+        let mut covered = 0;
+        for probe in (0..n - period).step_by(period) {
+            if probe + period < covered {
+                continue;
+            }
+            let forward = lce.query(probe, probe + period);
+            let backward = (1..period)
+                .take_while(|&k| {
+                    k <= probe
+                        && text[probe - k] == text[probe + period - k]
+                        && text[probe - k] != RECORD_SEPARATOR
+                })
+                .count();
+            let start = probe - backward;
+            let length = period + forward + backward;
+            if length / period < min_copies || !is_primitive(&text[start..start + period]) {
+                continue;
+            }
+            covered = start + length;
+            repeats.push(TandemRepeat {
+                start,
+                period,
+                length,
+            });
+        }
+    }
+    repeats.sort();
+    repeats.dedup();
+    repeats
+}
+
+fn is_primitive(unit: &[u8]) -> bool {
+    let p = unit.len();
+    (1..p)
+        .filter(|&d| p.is_multiple_of(d))
+        .all(|d| unit[d..] != unit[..p - d])
+}
+
+/// Rows of the LCP array covered by one minimum of the block sparse table.
+const LCE_BLOCK: usize = 64;
+
+/// Longest common prefix of any two suffixes as a range minimum over the LCP
+/// array. Minima of whole blocks of [`LCE_BLOCK`] rows go into a sparse table
+/// of `u32`, saturating, so beyond the inverse suffix array it takes a small
+/// fraction of the LCP array; the partial blocks at both ends are scanned.
+struct LongestCommonExtension<'a> {
+    lcp: &'a [usize],
+    ranks: Vec<usize>,
+    table: Vec<Vec<u32>>,
+}
+
+impl<'a> LongestCommonExtension<'a> {
+    fn new(suffixes: &[usize], lcp: &'a [usize]) -> Self {
+        let mut ranks = vec![0; suffixes.len()];
+        for (row, &suffix) in suffixes.iter().enumerate() {
+            ranks[suffix] = row;
+        }
+        let minima = lcp
+            .chunks(LCE_BLOCK)
+            .map(|block| (*block.iter().min().unwrap()).min(u32::MAX as usize) as u32)
+            .collect::<Vec<_>>();
+        let blocks = minima.len();
+        let mut table = vec![minima];
+        let mut width = 1;
+        while 2 * width <= blocks {
+            let previous = table.last().unwrap();
+            let level = (0..=blocks - 2 * width)
+                .map(|i| previous[i].min(previous[i + width]))
+                .collect::<Vec<_>>();
+            table.push(level);
+            width *= 2;
+        }
+        LongestCommonExtension { lcp, ranks, table }
+    }
+
+    fn query(&self, i: usize, j: usize) -> usize {
+        let (a, b) = (
+            self.ranks[i].min(self.ranks[j]),
+            self.ranks[i].max(self.ranks[j]),
+        );
+        // Minimum of lcp[a + 1..=b]
+        let (first, last) = ((a + 1) / LCE_BLOCK, b / LCE_BLOCK);
+        if last <= first + 1 {
+            return self.lcp[a + 1..=b].iter().copied().min().unwrap();
+        }
+        let ends = self.lcp[a + 1..(first + 1) * LCE_BLOCK]
+            .iter()
+            .chain(&self.lcp[last * LCE_BLOCK..=b])
+            .copied()
+            .min()
+            .unwrap();
+        let level = (last - first - 1).ilog2() as usize;
+        let blocks = self.table[level][first + 1].min(self.table[level][last - (1 << level)]);
+        ends.min(blocks as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::lcp::lcp_array;
+    use crate::bwt::repeats::{
+        longest_repeats, maximal_repeats, tandem_repeats, LongestCommonExtension, Repeat,
+        RepeatIndex, TandemRepeat,
+    };
+    use crate::bwt::suffix_array_induced_sorting;
+    use crate::utils::{Fasta, DNA_BW};
+    use std::error::Error;
+
+    #[test]
+    fn test_longest_repeats1() -> Result<(), Box<dyn Error>> {
+        let text = b"GATTACAGATTACACATTAG";
+        let suffixes = suffix_array_induced_sorting(text, &DNA_BW)?.into_usizes();
+        let lcp = lcp_array(text, &suffixes);
+        assert_eq!(
+            longest_repeats(text, &suffixes, &lcp),
+            vec![Repeat {
+                length: 7,
+                positions: vec![0, 7]
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_maximal_repeats1() -> Result<(), Box<dyn Error>> {
+        let text = b"GATTACAGATTACACATTAG";
+        let suffixes = suffix_array_induced_sorting(text, &DNA_BW)?.into_usizes();
+        let lcp = lcp_array(text, &suffixes);
+        let repeats = maximal_repeats(text, &suffixes, &lcp, 3);
+        assert_eq!(
+            repeats,
+            vec![
+                Repeat {
+                    length: 7,
+                    positions: vec![0, 7]
+                },
+                Repeat {
+                    length: 4,
+                    positions: vec![1, 8, 15]
+                },
+                Repeat {
+                    length: 3,
+                    positions: vec![4, 11, 13]
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tandem_repeats1() -> Result<(), Box<dyn Error>> {
+        let text = b"GCACACACATTTTTGAGGAGGC";
+        let suffixes = suffix_array_induced_sorting(text, &DNA_BW)?.into_usizes();
+        let lcp = lcp_array(text, &suffixes);
+        assert_eq!(
+            tandem_repeats(text, &suffixes, &lcp, 3, 2),
+            vec![
+                TandemRepeat {
+                    start: 1,
+                    period: 2,
+                    length: 8
+                },
+                TandemRepeat {
+                    start: 9,
+                    period: 1,
+                    length: 5
+                },
+                TandemRepeat {
+                    start: 14,
+                    period: 3,
+                    length: 7
+                },
+                TandemRepeat {
+                    start: 16,
+                    period: 1,
+                    length: 2
+                },
+                TandemRepeat {
+                    start: 19,
+                    period: 1,
+                    length: 2
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_longest_common_extension1() -> Result<(), Box<dyn Error>> {
+        // Long enough for the block table, with repeats spanning many blocks
+        let unit = (0..300u64)
+            .map(|i| b"ACGT"[((i * i * 7 + i / 3) % 4) as usize])
+            .collect::<Vec<_>>();
+        let text = [&unit[..], b"GATTACA", &unit[..], &unit[..150]].concat();
+        let suffixes = suffix_array_induced_sorting(&text, &DNA_BW)?.into_usizes();
+        let lcp = lcp_array(&text, &suffixes);
+        let lce = LongestCommonExtension::new(&suffixes, &lcp);
+        for (i, j) in (0..text.len())
+            .step_by(7)
+            .flat_map(|i| (i + 1..text.len()).step_by(11).map(move |j| (i, j)))
+        {
+            let naive = text[i..]
+                .iter()
+                .zip(&text[j..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            assert_eq!(lce.query(i, j), naive);
+        }
+        assert_eq!(lce.query(0, 307), 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_index2() -> Result<(), Box<dyn Error>> {
+        // Occurrences at record starts all follow the separator
+        let records = vec![
+            Fasta::new("a", "TTTTTTT"),
+            Fasta::new("b", "ACGTAGA"),
+            Fasta::new("c", "ACGTAGC"),
+        ];
+        let index = RepeatIndex::from_records(&records, &DNA_BW)?;
+        assert_eq!(
+            index.maximal_repeats(4),
+            vec![
+                Repeat {
+                    length: 6,
+                    positions: vec![0, 1]
+                },
+                Repeat {
+                    length: 5,
+                    positions: vec![0, 1, 2]
+                },
+                Repeat {
+                    length: 4,
+                    positions: vec![0, 1, 2, 3]
+                },
+                Repeat {
+                    length: 6,
+                    positions: vec![8, 16]
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeat_index1() -> Result<(), Box<dyn Error>> {
+        let records = vec![Fasta::new("a", "ACGTAC"), Fasta::new("b", "GTACCC")];
+        let index = RepeatIndex::from_records(&records, &DNA_BW)?;
+        // "GTAC" occurs in both records; nothing spans the separator
+        assert_eq!(
+            index.longest_repeats(),
+            vec![Repeat {
+                length: 4,
+                positions: vec![2, 7]
+            }]
+        );
+        assert_eq!(
+            index.tandem_repeats(1, 3),
+            vec![TandemRepeat {
+                start: 10,
+                period: 1,
+                length: 3
+            }]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use crate::bwt::fm::Occurrences;
+use crate::utils::SymbolMap;
+use std::error::Error;
+
+/// BWT stored as maximal runs of equal symbols. Besides the run heads, each
+/// symbol keeps the indices of its runs and the number of its occurrences
+/// before each of them, so rank and select cost a few binary searches and the
+/// space grows with the number of runs rather than with the text.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct RunLengthBwt {
+    len: usize,
+    run_starts: Vec<usize>,
+    run_symbols: Vec<usize>,
+    symbol_runs: Vec<Vec<usize>>,
+    /// Per symbol, occurrences before each of its runs plus the total.
+    symbol_totals: Vec<Vec<usize>>,
+}
+
+impl RunLengthBwt {
+    pub(crate) fn new(
+        bwt_bytes: &[u8],
+        char_map: &impl SymbolMap<u8>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut run_starts = Vec::new();
+        let mut run_symbols = Vec::new();
+        let mut symbol_runs = vec![Vec::new(); char_map.size()];
+        let mut symbol_totals = vec![vec![0]; char_map.size()];
+        for (row, byte) in bwt_bytes.iter().enumerate() {
+            let symbol = char_map
+                .rank(byte)
+                .ok_or(format!("Symbol {} not found in char_map", byte))?;
+            if run_symbols.last() != Some(&symbol) {
+                symbol_runs[symbol].push(run_starts.len());
+                let total = *symbol_totals[symbol].last().unwrap();
+                symbol_totals[symbol].push(total);
+                run_starts.push(row);
+                run_symbols.push(symbol);
+            }
+            *symbol_totals[symbol].last_mut().unwrap() += 1;
+        }
+        Ok(RunLengthBwt {
+            len: bwt_bytes.len(),
+            run_starts,
+            run_symbols,
+            symbol_runs,
+            symbol_totals,
+        })
+    }
+
+    pub(crate) fn runs(&self) -> usize {
+        self.run_starts.len()
+    }
+
+    pub(crate) fn run_start(&self, run: usize) -> usize {
+        self.run_starts[run]
+    }
+
+    pub(crate) fn run_end(&self, run: usize) -> usize {
+        self.run_starts.get(run + 1).map_or(self.len, |&s| s) - 1
+    }
+
+    /// Index of the run holding `row`.
+    pub(crate) fn run_of(&self, row: usize) -> usize {
+        self.run_starts.partition_point(|&s| s <= row) - 1
+    }
+
+    /// Row of the `k`-th occurrence (1-based) of `symbol`.
+    pub(crate) fn select(&self, symbol: usize, k: usize) -> Option<usize> {
+        let totals = &self.symbol_totals[symbol];
+        if k == 0 || k > *totals.last().unwrap() {
+            return None;
+        }
+        let i = totals.partition_point(|&t| t < k) - 1;
+        Some(self.run_starts[self.symbol_runs[symbol][i]] + k - totals[i] - 1)
+    }
+}
+
+impl Occurrences for RunLengthBwt {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn symbol(&self, row: usize) -> usize {
+        self.run_symbols[self.run_of(row)]
+    }
+
+    fn occurrence(&self, symbol: usize, row: usize) -> usize {
+        let run = self.run_of(row);
+        let i = self.symbol_runs[symbol].partition_point(|&r| r < run);
+        let before = self.symbol_totals[symbol][i];
+        if self.run_symbols[run] == symbol {
+            before + row - self.run_starts[run] + 1
+        } else {
+            before
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::fm::{calculate_fm_index, Occurrences};
+    use crate::bwt::run_length::RunLengthBwt;
+    use crate::utils::DNA_BW;
+    use std::error::Error;
+
+    #[test]
+    fn test_run_length_bwt1() -> Result<(), Box<dyn Error>> {
+        let bwt = b"AAAC$CCGGGGTTAAAAT";
+        let runs = RunLengthBwt::new(bwt, &DNA_BW)?;
+        let dense = calculate_fm_index(bwt, &DNA_BW, 1)?;
+        assert_eq!(runs.runs(), 8);
+        assert_eq!((runs.run_start(5), runs.run_end(5)), (11, 12));
+        for (row, counts) in dense.iter().enumerate() {
+            assert_eq!(runs.symbol(row), DNA_BW.rank(bwt[row]).unwrap());
+            for (symbol, &count) in counts.iter().enumerate() {
+                assert_eq!(runs.occurrence(symbol, row), count);
+                if count > 0 {
+                    let selected = runs.select(symbol, count).unwrap();
+                    assert!(selected <= row && runs.occurrence(symbol, selected) == count);
+                }
+            }
+        }
+        assert_eq!(runs.select(DNA_BW.rank(b'A').unwrap(), 5), Some(14));
+        assert_eq!(runs.select(DNA_BW.rank(b'A').unwrap(), 8), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::bwt::bit_vector::BitVector;
+use std::error::Error;
+
+/// Suffix array that only keeps the entries whose text position is a multiple
+/// of `sample_rate`. Other rows are recovered by walking the LF-mapping until a
+/// sampled row is reached, so memory is traded against locate time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampledSuffixArray {
+    sample_rate: usize,
+    sampled_rows: BitVector,
+    samples: Vec<usize>,
+}
+
+impl SampledSuffixArray {
+    pub fn from_suffix_array(
+        suffixes: &[usize],
+        sample_rate: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::from_suffixes(suffixes.iter().copied(), sample_rate)
+    }
+
+    /// Samples the suffix array entries of `suffixes`, which is read twice.
+    pub(crate) fn from_suffixes(
+        suffixes: impl Iterator<Item = usize> + Clone,
+        sample_rate: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        if sample_rate == 0 {
+            return Err("Suffix array sample rate must be positive.".into());
+        }
+        let sampled_rows = BitVector::from_bits(suffixes.clone().map(|s| s % sample_rate == 0));
+        let samples = suffixes
+            .filter(|&s| s % sample_rate == 0)
+            .collect::<Vec<_>>();
+        Ok(SampledSuffixArray {
+            sample_rate,
+            sampled_rows,
+            samples,
+        })
+    }
+
+    pub(crate) fn from_parts(
+        sample_rate: usize,
+        sampled_rows: BitVector,
+        samples: Vec<usize>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let len = sampled_rows.len();
+        if sample_rate == 0
+            || samples.len() != sampled_rows.rank(len)
+            || samples.iter().any(|&s| s >= len)
+        {
+            return Err("Sampled suffix array sections are inconsistent".into());
+        }
+        Ok(SampledSuffixArray {
+            sample_rate,
+            sampled_rows,
+            samples,
+        })
+    }
+
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    pub fn len(&self) -> usize {
+        self.sampled_rows.len()
+    }
+
+    pub(crate) fn sampled_rows(&self) -> &BitVector {
+        &self.sampled_rows
+    }
+
+    pub(crate) fn samples(&self) -> &[usize] {
+        &self.samples
+    }
+
+    pub(crate) fn sample(&self, row: usize) -> Option<usize> {
+        if self.sampled_rows.get(row) {
+            Some(self.samples[self.sampled_rows.rank(row)])
+        } else {
+            None
+        }
+    }
+
+    /// Text position of the suffix in `row`, following `lf` until a sampled row.
+    pub(crate) fn locate(&self, row: usize, lf: impl Fn(usize) -> usize) -> usize {
+        let mut row = row;
+        let mut steps = 0;
+        loop {
+            if let Some(position) = self.sample(row) {
+                return position + steps;
+            }
+            row = lf(row);
+            steps += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::sampled::SampledSuffixArray;
+    use std::error::Error;
+
+    #[test]
+    fn test_sampled_suffix_array1() -> Result<(), Box<dyn Error>> {
+        let suffixes = vec![7, 1, 4, 3, 2, 0, 6, 5];
+        let sampled = SampledSuffixArray::from_suffix_array(&suffixes, 2)?;
+        let sampled_values = (0..suffixes.len())
+            .map(|r| sampled.sample(r))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sampled_values,
+            vec![None, None, Some(4), None, Some(2), Some(0), Some(6), None]
+        );
+        Ok(())
+    }
+}
@@ -3,10 +3,9 @@ use crate::bwt::lms::{
     accurate_lms_sort, build_type_map, guess_lms_sort, induce_sort_l, induce_sort_s,
 };
 use crate::bwt::summary::summarize_suffix_array;
-use std::collections::{HashMap, HashSet};
+use crate::utils::SymbolMap;
 use std::error::Error;
 use std::fmt::{Debug, Display};
-use std::hash::Hash;
 
 pub fn suffix_array(text: &str) -> Result<Vec<usize>, Box<dyn Error>> {
     let text_bytes = text.as_bytes();
@@ -72,10 +71,136 @@ pub(crate) fn suffix_array_bytes(text_bytes: &[u8]) -> Result<Vec<usize>, Box<dy
     Ok(suffix)
 }
 
-pub fn suffix_array_induced_sorting<T: Copy + Display + Eq + Hash + Into<usize> + Ord + Debug>(
+/// Integer type of suffix array entries during induced sorting. `EMPTY` marks
+/// slots that are not filled yet, so texts must be shorter than it.
+pub(crate) trait SuffixIndex: Copy + Debug + Display + Ord {
+    const EMPTY: Self;
+
+    fn from_usize(value: usize) -> Self;
+
+    fn as_usize(self) -> usize;
+}
+
+impl SuffixIndex for u32 {
+    const EMPTY: Self = u32::MAX;
+
+    fn from_usize(value: usize) -> Self {
+        value as u32
+    }
+
+    fn as_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl SuffixIndex for usize {
+    const EMPTY: Self = usize::MAX;
+
+    fn from_usize(value: usize) -> Self {
+        value
+    }
+
+    fn as_usize(self) -> usize {
+        self
+    }
+}
+
+/// Symbol map of a text that is already encoded as ranks `0..size`, such as a
+/// summary string.
+pub(crate) struct DenseRanks {
+    pub(crate) size: usize,
+}
+
+impl<I: SuffixIndex> SymbolMap<I> for DenseRanks {
+    fn rank(&self, symbol: &I) -> Option<usize> {
+        let symbol = symbol.as_usize();
+        (symbol < self.size).then_some(symbol)
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Suffix array in the narrowest entry width that holds every position.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SuffixArray {
+    Narrow(Vec<u32>),
+    Wide(Vec<usize>),
+}
+
+impl SuffixArray {
+    pub fn len(&self) -> usize {
+        match self {
+            SuffixArray::Narrow(suffixes) => suffixes.len(),
+            SuffixArray::Wide(suffixes) => suffixes.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> SuffixArrayIter<'_> {
+        match self {
+            SuffixArray::Narrow(suffixes) => SuffixArrayIter::Narrow(suffixes.iter()),
+            SuffixArray::Wide(suffixes) => SuffixArrayIter::Wide(suffixes.iter()),
+        }
+    }
+
+    /// The entries as `usize`, which copies a narrow suffix array.
+    pub fn into_usizes(self) -> Vec<usize> {
+        match self {
+            SuffixArray::Narrow(suffixes) => suffixes.into_iter().map(|s| s as usize).collect(),
+            SuffixArray::Wide(suffixes) => suffixes,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SuffixArrayIter<'a> {
+    Narrow(std::slice::Iter<'a, u32>),
+    Wide(std::slice::Iter<'a, usize>),
+}
+
+impl Iterator for SuffixArrayIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        match self {
+            SuffixArrayIter::Narrow(suffixes) => suffixes.next().map(|&s| s as usize),
+            SuffixArrayIter::Wide(suffixes) => suffixes.next().copied(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            SuffixArrayIter::Narrow(suffixes) => suffixes.size_hint(),
+            SuffixArrayIter::Wide(suffixes) => suffixes.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for SuffixArrayIter<'_> {}
+
+/// Suffix array by SA-IS, with the empty suffix first. Texts shorter than
+/// `u32::MAX` are sorted and returned with 32-bit entries, which halves the
+/// memory of the suffix array.
+pub fn suffix_array_induced_sorting<T: Copy + Display + Ord + Debug>(
+    text_bytes: &[T],
+    char_map: &impl SymbolMap<T>,
+) -> Result<SuffixArray, Box<dyn Error>> {
+    if text_bytes.len() < u32::EMPTY as usize {
+        suffix_array_induced_sorting_with::<T, u32>(text_bytes, char_map).map(SuffixArray::Narrow)
+    } else {
+        suffix_array_induced_sorting_with::<T, usize>(text_bytes, char_map).map(SuffixArray::Wide)
+    }
+}
+
+pub(crate) fn suffix_array_induced_sorting_with<T: Copy + Display + Ord + Debug, I: SuffixIndex>(
     text_bytes: &[T],
-    char_map: &HashMap<T, usize>,
-) -> Result<Vec<usize>, Box<dyn Error>> {
+    char_map: &impl SymbolMap<T>,
+) -> Result<Vec<I>, Box<dyn Error>> {
     // Step 1: Prepare necessary data structures
     let labels = build_type_map(text_bytes)?; // Renamed `type_map` to `labels`
     let buckets = char_buckets(text_bytes, char_map)?; // Renamed `bucket_sizes` to `buckets`
@@ -99,11 +224,8 @@ pub fn suffix_array_induced_sorting<T: Copy + Display + Eq + Hash + Into<usize>
         &labels,
     )?;
 
-    // Flatten and collect the guessed suffix array
-    let guessed_suffix_array = guessed_suffix_array
-        .into_iter()
-        .flatten()
-        .collect::<Vec<_>>();
+    // Drop the slots that were never filled
+    guessed_suffix_array.retain(|&s| s != I::EMPTY);
 
     // Step 4: Summarize the guessed suffix array
     let (summary_string, alphabet_size, offsets) =
@@ -125,41 +247,33 @@ pub fn suffix_array_induced_sorting<T: Copy + Display + Eq + Hash + Into<usize>
     induce_sort_l(&mut suffix_array, text_bytes, char_map, &buckets, &labels)?;
     induce_sort_s(&mut suffix_array, text_bytes, char_map, &buckets, &labels)?;
 
-    // Final result: Flatten and return the suffix array
-    Ok(suffix_array.into_iter().flatten().collect::<Vec<_>>())
+    // Final result: drop the unfilled slots and return the suffix array
+    suffix_array.retain(|&s| s != I::EMPTY);
+    Ok(suffix_array)
 }
-pub(crate) fn make_summary_suffix_array(
-    summary_string: &[usize],
+pub(crate) fn make_summary_suffix_array<I: SuffixIndex>(
+    summary_string: &[I],
     summary_alphabet_size: usize,
-) -> Result<Vec<usize>, Box<dyn Error>> {
+) -> Result<Vec<I>, Box<dyn Error>> {
     if summary_alphabet_size == summary_string.len() {
         // Every character appears exactly once, so we can use bucket sort
-        let mut summary_suffix_array = vec![usize::MAX; summary_string.len() + 1];
+        let mut summary_suffix_array = vec![I::EMPTY; summary_string.len() + 1];
 
         // Always include the empty suffix at the beginning
-        summary_suffix_array[0] = summary_string.len();
+        summary_suffix_array[0] = I::from_usize(summary_string.len());
 
         // Direct indexing approach - more efficient than looping
         for (x, &y) in summary_string.iter().enumerate() {
-            summary_suffix_array[y + 1] = x;
+            summary_suffix_array[y.as_usize() + 1] = I::from_usize(x);
         }
 
         Ok(summary_suffix_array)
     } else {
         // More complex case - use recursion
-        let mut char_set = summary_string
-            .iter()
-            .copied()
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
-        char_set.sort();
-        let char_map = char_set
-            .into_iter()
-            .enumerate()
-            .map(|(i, s)| (s, i))
-            .collect::<HashMap<_, _>>();
-        suffix_array_induced_sorting(summary_string, &char_map)
+        let names = DenseRanks {
+            size: summary_alphabet_size,
+        };
+        suffix_array_induced_sorting_with(summary_string, &names)
     }
 }
 
@@ -169,8 +283,11 @@ mod tests {
     use crate::bwt::lms::{build_type_map, guess_lms_sort, induce_sort_l, induce_sort_s};
     use crate::bwt::suffix_array::{
         make_summary_suffix_array, suffix_array, suffix_array_induced_sorting,
+        suffix_array_induced_sorting_with, SuffixArray, SuffixIndex,
     };
     use crate::bwt::summary::summarize_suffix_array;
+    use crate::utils::alphabet::PROTEIN_ALPHABET;
+    use crate::utils::DNA_BW;
     use std::collections::HashMap;
     use std::error::Error;
 
@@ -215,7 +332,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -231,10 +348,7 @@ mod tests {
             &cabbage_bucket,
             &cabbage_types,
         )?;
-        let guessed_suffix_array = guessed_suffix_array
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
+        guessed_suffix_array.retain(|&s| s != u32::EMPTY);
         let (summary_string, summary_alphabet_size, summary_suffix_offsets) =
             summarize_suffix_array(cabbage, &guessed_suffix_array, &cabbage_types)?;
         let cabbage_summary_suffix_array =
@@ -250,7 +364,7 @@ mod tests {
             .map(|n| (b'a' + n, n as usize))
             .collect::<HashMap<_, _>>();
         assert_eq!(
-            suffix_array_induced_sorting(text.as_bytes(), &char_map)?,
+            suffix_array_induced_sorting(text.as_bytes(), &char_map)?.into_usizes(),
             vec![7, 1, 4, 3, 2, 0, 6, 5]
         );
         Ok(())
@@ -263,9 +377,38 @@ mod tests {
             .map(|n| (b'a' + n, n as usize))
             .collect::<HashMap<_, _>>();
         assert_eq!(
-            suffix_array_induced_sorting(text.as_bytes(), &char_map)?,
+            suffix_array_induced_sorting(text.as_bytes(), &char_map)?.into_usizes(),
             vec![9, 1, 4, 2, 5, 7, 0, 3, 6, 8]
         );
         Ok(())
     }
+
+    #[test]
+    fn test_suffix_array_induced_sorting3() -> Result<(), Box<dyn Error>> {
+        let text = "MKVLAAGIVGLLLAMKVWYHHQRPEKQA";
+        let expected = suffix_array(&format!("{}$", text))?;
+        assert_eq!(
+            suffix_array_induced_sorting(text.as_bytes(), &PROTEIN_ALPHABET)?.into_usizes(),
+            expected
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_suffix_array_induced_sorting4() -> Result<(), Box<dyn Error>> {
+        // Repeats force several levels of recursion
+        let text = "GATTACA".repeat(40) + &"AC".repeat(33) + "GATTACAT";
+        let narrow = suffix_array_induced_sorting_with::<u8, u32>(text.as_bytes(), &DNA_BW)?;
+        let wide = suffix_array_induced_sorting_with::<u8, usize>(text.as_bytes(), &DNA_BW)?;
+        assert_eq!(
+            narrow.iter().map(|&s| s.as_usize()).collect::<Vec<_>>(),
+            wide
+        );
+        assert_eq!(wide, suffix_array(&format!("{}$", text))?);
+        assert_eq!(
+            suffix_array_induced_sorting(text.as_bytes(), &DNA_BW)?,
+            SuffixArray::Narrow(narrow)
+        );
+        Ok(())
+    }
 }
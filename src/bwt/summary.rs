@@ -1,25 +1,26 @@
 use crate::bwt::lms::{is_lms_char, lms_substrings_are_equal};
+use crate::bwt::suffix_array::SuffixIndex;
 use std::error::Error;
 
-pub(crate) fn summarize_suffix_array<T: PartialEq>(
+pub(crate) fn summarize_suffix_array<T: PartialEq, I: SuffixIndex>(
     text_bytes: &[T],
-    guessed_suffix_array: &[usize],
+    guessed_suffix_array: &[I],
     type_map: &[u8],
-) -> Result<(Vec<usize>, usize, Vec<usize>), Box<dyn Error>> {
+) -> Result<(Vec<I>, usize, Vec<I>), Box<dyn Error>> {
     // We will use this array to store the names of LMS substrings
-    let mut lms_names = vec![-1; text_bytes.len() + 1];
+    let mut lms_names = vec![I::EMPTY; text_bytes.len() + 1];
 
     // Keep track of what names we've allocated
     let mut current_name = 0;
 
     // Where in the original string was the last LMS suffix we checked?
-    let mut last_lms_suffix_offset = guessed_suffix_array[0];
+    let mut last_lms_suffix_offset = guessed_suffix_array[0].as_usize();
 
     // We know that the first LMS-substring will be the empty suffix at position 0
-    lms_names[last_lms_suffix_offset] = current_name;
+    lms_names[last_lms_suffix_offset] = I::from_usize(current_name);
 
     // For each suffix in the suffix array...
-    for &suffix_offset in guessed_suffix_array.iter().skip(1) {
+    for suffix_offset in guessed_suffix_array.iter().skip(1).map(|s| s.as_usize()) {
         // We only care about LMS suffixes
         if !is_lms_char(type_map, suffix_offset)? {
             continue;
@@ -36,7 +37,7 @@ pub(crate) fn summarize_suffix_array<T: PartialEq>(
         last_lms_suffix_offset = suffix_offset;
 
         // Store the name of this LMS suffix
-        lms_names[suffix_offset] = current_name;
+        lms_names[suffix_offset] = I::from_usize(current_name);
     }
 
     // Allocate vectors with estimated capacity to avoid reallocations
@@ -45,14 +46,14 @@ pub(crate) fn summarize_suffix_array<T: PartialEq>(
 
     // Extract only the valid LMS names and their positions
     for (index, &name) in lms_names.iter().enumerate() {
-        if name != -1 {
-            summary_suffix_offsets.push(index);
-            summary_string.push(name as usize);
+        if name != I::EMPTY {
+            summary_suffix_offsets.push(I::from_usize(index));
+            summary_string.push(name);
         }
     }
 
     // The summary alphabet size is one larger than the largest numbered character
-    let summary_alphabet_size = (current_name + 1) as usize;
+    let summary_alphabet_size = current_name + 1;
 
     Ok((
         summary_string,
@@ -65,6 +66,7 @@ pub(crate) fn summarize_suffix_array<T: PartialEq>(
 mod tests {
     use crate::bwt::bucket::char_buckets;
     use crate::bwt::lms::{build_type_map, guess_lms_sort, induce_sort_l, induce_sort_s};
+    use crate::bwt::suffix_array::SuffixIndex;
     use crate::bwt::summary::summarize_suffix_array;
     use std::collections::HashMap;
     use std::error::Error;
@@ -77,7 +79,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -93,10 +95,7 @@ mod tests {
             &cabbage_bucket,
             &cabbage_types,
         )?;
-        let guessed_suffix_array = guessed_suffix_array
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
+        guessed_suffix_array.retain(|&s| s != u32::EMPTY);
         let (summary_string, summary_alphabet_size, summary_suffix_offsets) =
             summarize_suffix_array(cabbage, &guessed_suffix_array, &cabbage_types)?;
         assert_eq!(summary_string, vec![1, 2, 0]);
@@ -113,7 +112,7 @@ mod tests {
             .collect::<HashMap<_, _>>();
         let cabbage_bucket = char_buckets(cabbage, &char_map)?;
         let cabbage_types = build_type_map(cabbage)?;
-        let mut guessed_suffix_array =
+        let mut guessed_suffix_array: Vec<u32> =
             guess_lms_sort(cabbage, &char_map, &cabbage_bucket, &cabbage_types)?;
         induce_sort_l(
             &mut guessed_suffix_array,
@@ -129,10 +128,7 @@ mod tests {
             &cabbage_bucket,
             &cabbage_types,
         )?;
-        let guessed_suffix_array = guessed_suffix_array
-            .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
+        guessed_suffix_array.retain(|&s| s != u32::EMPTY);
         let (summary_string, summary_alphabet_size, summary_suffix_offsets) =
             summarize_suffix_array(cabbage, &guessed_suffix_array, &cabbage_types)?;
         assert_eq!(summary_string, vec![1, 1, 2, 0]);
@@ -1,13 +1,20 @@
 use crate::burrows_wheeler::BWTArgs;
 use crate::bwt::{
-    bw_match_counts, bw_match_counts_fasta, fasta_burrows_wheeler_transform,
-    fasta_burrows_wheeler_transform_sa_is,
+    bw_match_counts, bw_match_counts_fasta, bw_match_counts_packed, bw_match_positions,
+    bw_match_positions_packed, fasta_burrows_wheeler_transform,
+    fasta_burrows_wheeler_transform_sa_is, RIndex, RecordHit, RecordTable, SampledSuffixArray,
+    Strand, SuffixArrayRef,
+};
+use crate::mapping::{
+    write_matches, MatchFormat, PatternMatches, SamRecord, SamWriter, StrandMatches,
 };
 use crate::utils::{dna_complement, print_hms, Fasta, DNA_BW_N};
-use clap::Parser;
+use clap::{value_parser, Parser};
 use rayon::prelude::*;
 use std::cmp::max;
 use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter};
 use std::time::Instant;
 
 #[derive(Parser)]
@@ -17,6 +24,32 @@ pub struct BWTMatchingArgs {
     genome_file: String,
     #[arg(long, required = true, value_name = "pattern_file")]
     pattern_file: String,
+
+    #[arg(long, required = false)]
+    positions: bool,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "1")]
+    sa_sample: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize))]
+    fw_step: Option<usize>,
+
+    /// Match on a run-length compressed r-index instead of the packed BWT,
+    /// which has no suffix array samples or checkpoints to tune.
+    #[arg(long, required = false, conflicts_with_all = ["sa_sample", "fw_step"])]
+    run_length: bool,
+
+    /// Write the positions of both strands to this SAM file.
+    #[arg(long, required = false, value_name = "SAM")]
+    sam: Option<String>,
+
+    /// Format of the per-pattern results: tsv or json.
+    #[arg(long, required = false, value_name = "FORMAT", default_value = "tsv")]
+    format: String,
+
+    /// Write the per-pattern results here instead of to standard output.
+    #[arg(long, required = false, value_name = "OUTPUT")]
+    output: Option<String>,
 }
 
 impl BWTMatchingArgs {
@@ -27,29 +60,192 @@ impl BWTMatchingArgs {
     pub fn get_pattern_file(&self) -> Result<String, Box<dyn Error>> {
         Ok(self.pattern_file.to_owned())
     }
+
+    pub fn get_sa_sample(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self.sa_sample)
+    }
+
+    pub fn get_fw_step(&self) -> Result<Option<usize>, Box<dyn Error>> {
+        Ok(self.fw_step)
+    }
+
+    pub fn get_format(&self) -> Result<MatchFormat, Box<dyn Error>> {
+        self.format.parse()
+    }
+
+    pub fn get_output(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.output.to_owned())
+    }
 }
 
 pub fn run_bwt_matching(args: BWTMatchingArgs) -> Result<(), Box<dyn Error>> {
     let genome_file = args.get_genome_file()?;
     let fasta = Fasta::read_file_component(&genome_file)?;
+    let format = args.get_format()?;
 
     let pattern_file = args.get_pattern_file()?;
     let patterns = Fasta::read_file(&pattern_file)?;
+    let titles = patterns.iter().map(|p| p.title.clone()).collect::<Vec<_>>();
     let reverse_patterns = patterns
         .iter()
         .map(|f| dna_complement(&f.text))
         .collect::<Result<Vec<_>, _>>()?;
     let patterns = patterns.into_iter().map(|p| p.text).collect::<Vec<_>>();
     let patterns = patterns.iter().map(|p| p.as_str()).collect::<Vec<&str>>();
-    let patterns = [patterns, reverse_patterns.iter().map(|s| s.as_str()).collect()].concat();
-    println!("{:?}", patterns);
+    let patterns = [
+        patterns,
+        reverse_patterns.iter().map(|s| s.as_str()).collect(),
+    ]
+    .concat();
 
     let start = Instant::now();
 
-    let (bwt, _) = fasta_burrows_wheeler_transform_sa_is(&fasta, &DNA_BW_N)?;
-    let pattern_matches = bw_match_counts(&bwt, &patterns, &DNA_BW_N, 1)?;
-    print_hms(&start);
+    let (bwt, suffixes) = fasta_burrows_wheeler_transform_sa_is(&fasta, &DNA_BW_N)?;
+    let fw_step = args.get_fw_step()?;
+    let counts_only = !args.positions && args.sam.is_none();
+    let hits = if args.run_length {
+        let index = RIndex::from_bwt(&bwt, &suffixes, &DNA_BW_N)?;
+        drop(suffixes);
+        if counts_only {
+            patterns
+                .iter()
+                .map(|p| index.count(p).map(StrandMatches::from_count))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            patterns
+                .iter()
+                .map(|p| index.locate(p).map(StrandMatches::from_positions))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+    } else if counts_only {
+        let pattern_matches = match fw_step {
+            Some(fw_step) => bw_match_counts(&bwt, &patterns, &DNA_BW_N, fw_step)?,
+            None => bw_match_counts_packed(&bwt, &patterns, &DNA_BW_N)?,
+        };
+        pattern_matches
+            .into_iter()
+            .map(StrandMatches::from_count)
+            .collect()
+    } else {
+        let sa_sample = args.get_sa_sample()?;
+        let pattern_positions = if sa_sample > 1 {
+            let sampled = SampledSuffixArray::from_suffix_array(&suffixes, sa_sample)?;
+            drop(suffixes);
+            match_positions(&bwt, &sampled, &patterns, fw_step)?
+        } else {
+            match_positions(&bwt, &suffixes, &patterns, fw_step)?
+        };
+        pattern_positions
+            .into_iter()
+            .map(StrandMatches::from_positions)
+            .collect()
+    };
+
+    if let Some(file) = &args.sam {
+        print_hms(&start);
+        let records = RecordTable::from_parts(
+            vec![fasta.title.clone()],
+            vec![0],
+            vec![fasta.len()],
+            fasta.len(),
+        )?;
+        let pattern_positions = hits
+            .into_iter()
+            .map(|h| h.positions.unwrap_or_default())
+            .collect::<Vec<_>>();
+        return write_sam(file, &records, &titles, &patterns, &pattern_positions);
+    }
+
+    let matches = pattern_matches(&titles, &patterns, hits);
+    match args.get_output()? {
+        Some(file) => {
+            print_hms(&start);
+            write_matches(&mut BufWriter::new(File::create(file)?), &matches, format)
+        }
+        None => write_matches(&mut io::stdout().lock(), &matches, format),
+    }
+}
+
+/// Pairs the hits of every forward pattern with those of its reverse
+/// complement, which follow all forward patterns in `patterns` and `hits`.
+fn pattern_matches(
+    titles: &[String],
+    patterns: &[&str],
+    hits: Vec<StrandMatches>,
+) -> Vec<PatternMatches> {
+    let n = titles.len();
+    let mut hits = hits.into_iter();
+    let forward = hits.by_ref().take(n).collect::<Vec<_>>();
+    titles
+        .iter()
+        .zip(forward)
+        .zip(hits)
+        .enumerate()
+        .map(|(i, ((title, forward), reverse))| {
+            // A reverse-complement palindrome only matches once, on the forward strand
+            let reverse = match (patterns[n + i] == patterns[i], &forward.positions) {
+                (false, _) => reverse,
+                (true, Some(_)) => StrandMatches::from_positions(Vec::new()),
+                (true, None) => StrandMatches::from_count(0),
+            };
+            PatternMatches::new(title, patterns[i], forward, reverse)
+        })
+        .collect()
+}
 
-    println!("{:?}", pattern_matches);
+/// `patterns` holds the forward patterns followed by their reverse
+/// complements, and `positions` their exact hits in the same order.
+fn write_sam(
+    file: &str,
+    records: &RecordTable,
+    titles: &[String],
+    patterns: &[&str],
+    positions: &[Vec<usize>],
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = SamWriter::create(file, records)?;
+    let n = titles.len();
+    for (i, title) in titles.iter().enumerate() {
+        let mut hits = positions[i]
+            .iter()
+            .map(|&p| RecordHit::new(records.name(0), p, Strand::Forward))
+            .collect::<Vec<_>>();
+        // A reverse-complement palindrome matches both strands at once
+        if patterns[n + i] != patterns[i] {
+            hits.extend(
+                positions[n + i]
+                    .iter()
+                    .map(|&p| RecordHit::new(records.name(0), p, Strand::Reverse)),
+            );
+        }
+        hits.sort();
+        if hits.is_empty() {
+            writer.write(&SamRecord::unmapped(title, patterns[i], None))?;
+        }
+        let mapq = if hits.len() == 1 { 60 } else { 0 };
+        for (j, hit) in hits.iter().enumerate() {
+            writer.write(&SamRecord::from_hit(
+                title,
+                patterns[i],
+                None,
+                hit,
+                0,
+                mapq,
+                j > 0,
+            )?)?;
+        }
+    }
+    writer.finish()?;
     Ok(())
 }
+
+fn match_positions<'a>(
+    bwt: &str,
+    suffixes: impl Into<SuffixArrayRef<'a>>,
+    patterns: &[&str],
+    fw_step: Option<usize>,
+) -> Result<Vec<Vec<usize>>, Box<dyn Error>> {
+    match fw_step {
+        Some(fw_step) => bw_match_positions(bwt, suffixes, patterns, &DNA_BW_N, fw_step),
+        None => bw_match_positions_packed(bwt, suffixes, patterns, &DNA_BW_N),
+    }
+}
@@ -0,0 +1,63 @@
+use crate::archive::{compress_fasta, decompress_fasta};
+use crate::utils::print_hms;
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::fs;
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct CompressArgs {
+    #[arg(long, required = true, value_name = "FASTA")]
+    input: String,
+
+    #[arg(long, required = true, value_name = "ARCHIVE")]
+    output: String,
+
+    /// Sequence bytes per independently compressed block.
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "1048576")]
+    block_size: usize,
+}
+
+impl CompressArgs {
+    pub fn get_block_size(&self) -> Result<usize, Box<dyn Error>> {
+        if self.block_size == 0 {
+            return Err("Block size must be positive".into());
+        }
+        Ok(self.block_size)
+    }
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct DecompressArgs {
+    #[arg(long, required = true, value_name = "ARCHIVE")]
+    input: String,
+
+    #[arg(long, required = true, value_name = "FASTA")]
+    output: String,
+}
+
+pub fn run_compress(args: CompressArgs) -> Result<(), Box<dyn Error>> {
+    let content = fs::read(&args.input)?;
+    let start = Instant::now();
+    let archive = compress_fasta(&content, args.get_block_size()?)?;
+    print_hms(&start);
+    fs::write(&args.output, &archive)?;
+    println!(
+        "{} bytes -> {} bytes ({:.3} bits per byte)",
+        content.len(),
+        archive.len(),
+        8.0 * archive.len() as f64 / content.len().max(1) as f64
+    );
+    Ok(())
+}
+
+pub fn run_decompress(args: DecompressArgs) -> Result<(), Box<dyn Error>> {
+    let archive = fs::read(&args.input)?;
+    let start = Instant::now();
+    let content = decompress_fasta(&archive)?;
+    print_hms(&start);
+    fs::write(&args.output, content)?;
+    Ok(())
+}
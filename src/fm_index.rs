@@ -0,0 +1,221 @@
+use crate::bwt::{is_degenerate, FMIndex, NPolicy, RecordHit};
+use crate::mapping::{SamRecord, SamWriter};
+use crate::utils::{
+    dna_complement, print_hms, Fasta, SequenceReader, SequenceRecord, IUPAC_ALPHABET,
+};
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct IndexArgs {
+    #[arg(long, required = true, value_name = "genome_file")]
+    genome_file: String,
+
+    #[arg(long, required = true, value_name = "OUTPUT")]
+    output: String,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "32")]
+    sa_sample: usize,
+}
+
+impl IndexArgs {
+    pub fn get_genome_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.genome_file.to_owned())
+    }
+
+    pub fn get_output(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.output.to_owned())
+    }
+}
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct QueryArgs {
+    #[arg(long, required = true, value_name = "INDEX")]
+    index: String,
+
+    #[arg(long, required = true, value_name = "pattern_file")]
+    pattern_file: String,
+
+    #[arg(short = 'd', required = false, value_parser = value_parser!(usize), default_value = "0")]
+    mismatches: usize,
+
+    /// Whether reference Ns match IUPAC codes in the patterns: never or any.
+    #[arg(long, required = false, value_name = "POLICY", default_value = "never")]
+    n_policy: String,
+
+    /// Write the hits to this SAM file instead of printing them.
+    #[arg(long, required = false, value_name = "SAM")]
+    sam: Option<String>,
+
+    /// Print only the number of hits of each pattern on both strands.
+    #[arg(long, required = false, conflicts_with = "sam")]
+    count: bool,
+
+    /// Patterns read and searched in parallel at a time.
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "65536")]
+    chunk_size: usize,
+}
+
+impl QueryArgs {
+    pub fn get_index(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.index.to_owned())
+    }
+
+    pub fn get_pattern_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.pattern_file.to_owned())
+    }
+
+    pub fn get_mismatches(&self) -> Result<usize, Box<dyn Error>> {
+        Ok(self.mismatches)
+    }
+
+    pub fn get_n_policy(&self) -> Result<NPolicy, Box<dyn Error>> {
+        self.n_policy.parse()
+    }
+
+    pub fn get_chunk_size(&self) -> Result<usize, Box<dyn Error>> {
+        if self.chunk_size == 0 {
+            return Err("Chunk size must be positive".into());
+        }
+        Ok(self.chunk_size)
+    }
+}
+
+pub fn run_index(args: IndexArgs) -> Result<(), Box<dyn Error>> {
+    let genome_file = args.get_genome_file()?;
+    let records = Fasta::read_file(&genome_file)?
+        .iter()
+        .map(|f| f.upper())
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let index = FMIndex::from_records(&records, &IUPAC_ALPHABET, args.sa_sample)?;
+    index.write(args.get_output()?)?;
+    print_hms(&start);
+    Ok(())
+}
+
+/// Streams the patterns in chunks of `--chunk-size`, each searched in
+/// parallel, and prints one line per pattern in input order: title, hit count
+/// and hits, or only the count with `--count`.
+pub fn run_query(args: QueryArgs) -> Result<(), Box<dyn Error>> {
+    let start = Instant::now();
+    let index = FMIndex::read(args.get_index()?)?;
+    let mut patterns = SequenceReader::open(args.get_pattern_file()?)?;
+    let chunk_size = args.get_chunk_size()?;
+    let mismatches = args.get_mismatches()?;
+    let n_policy = args.get_n_policy()?;
+    // Patterns with ambiguity codes are expanded instead of matched literally
+    let search =
+        |index: &FMIndex, pattern: &str| -> Result<Vec<(RecordHit, usize)>, Box<dyn Error>> {
+            if !is_degenerate(pattern) {
+                return index.locate_records_approximate(pattern, mismatches);
+            }
+            if mismatches > 0 {
+                return Err(format!(
+                    "Degenerate pattern {} cannot be searched with mismatches",
+                    pattern
+                )
+                .into());
+            }
+            Ok(index
+                .locate_records_degenerate(pattern, n_policy)?
+                .into_iter()
+                .map(|hit| (hit, 0))
+                .collect())
+        };
+    // Exact counts come straight from the BWT ranges, without locating
+    let count = |index: &FMIndex, pattern: &str| -> Result<usize, Box<dyn Error>> {
+        if mismatches > 0 || is_degenerate(pattern) {
+            return Ok(search(index, pattern)?.len());
+        }
+        let reverse = dna_complement(pattern)?;
+        let mut total = index.count(pattern)?;
+        if reverse != pattern {
+            total += index.count(&reverse)?;
+        }
+        Ok(total)
+    };
+
+    let mut sam = match &args.sam {
+        Some(file) => Some(SamWriter::create(file, index.records())?),
+        None => None,
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    loop {
+        let chunk = patterns
+            .next_chunk(chunk_size)?
+            .iter()
+            .map(|p| p.upper())
+            .collect::<Vec<_>>();
+        if chunk.is_empty() {
+            break;
+        }
+        let texts = chunk.iter().map(|p| p.text.as_str()).collect::<Vec<_>>();
+        if args.count {
+            for (pattern, n) in chunk.iter().zip(index.batch(&texts, count)?) {
+                writeln!(out, "{}\t{}", pattern.title, n)?;
+            }
+            continue;
+        }
+        for (pattern, hits) in chunk.iter().zip(index.batch(&texts, search)?) {
+            if let Some(writer) = sam.as_mut() {
+                write_sam_hits(writer, pattern, &hits)?;
+                continue;
+            }
+            let hits = hits
+                .iter()
+                .map(|(h, d)| {
+                    if mismatches == 0 {
+                        h.to_string()
+                    } else {
+                        format!("{}:{}", h, d)
+                    }
+                })
+                .collect::<Vec<_>>();
+            writeln!(out, "{}\t{}\t{}", pattern.title, hits.len(), hits.join(","))?;
+        }
+    }
+    out.flush()?;
+    drop(out);
+    if let Some(writer) = sam {
+        writer.finish()?;
+        print_hms(&start);
+    }
+    Ok(())
+}
+
+fn write_sam_hits(
+    writer: &mut SamWriter<impl Write>,
+    pattern: &SequenceRecord,
+    hits: &[(RecordHit, usize)],
+) -> Result<(), Box<dyn Error>> {
+    if hits.is_empty() {
+        writer.write(&SamRecord::unmapped(
+            &pattern.title,
+            &pattern.text,
+            pattern.quality.as_deref(),
+        ))?;
+        return Ok(());
+    }
+    // The first hit with the fewest mismatches is the primary one
+    let primary = (0..hits.len()).min_by_key(|&i| hits[i].1).unwrap();
+    let mapq = if hits.len() == 1 { 60 } else { 0 };
+    for (i, (hit, d)) in hits.iter().enumerate() {
+        let record = SamRecord::from_hit(
+            &pattern.title,
+            &pattern.text,
+            pattern.quality.as_deref(),
+            hit,
+            *d,
+            mapq,
+            i != primary,
+        )?;
+        writer.write(&record)?;
+    }
+    Ok(())
+}
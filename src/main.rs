@@ -1,29 +1,45 @@
 #![allow(dead_code)]
 
+use crate::align::{run_align, AlignArgs};
+use crate::align_reads::{run_align_reads, AlignReadsArgs};
 use crate::burrows_wheeler::{run_bwt, BWTArgs};
 use crate::bwt_matching::{run_bwt_matching, BWTMatchingArgs};
+use crate::compress::{run_compress, run_decompress, CompressArgs, DecompressArgs};
 use crate::cyclo::{run_convo_cyclo, run_cyclo, run_leader_cyclo, CycloArgs};
 use crate::dosr::{run_median, run_random, DosRArgs};
+use crate::fm_index::{run_index, run_query, IndexArgs, QueryArgs};
+use crate::mappability::{run_mappability, MappabilityArgs};
 use crate::ori::{run_ori, OriArgs};
 use crate::pylogeny::{run_phylogeny, PhylogenyArgs};
+use crate::repeats::{run_repeats, RepeatsArgs};
+use crate::smems::{run_smems, SmemsArgs};
 use crate::synteny::{run_synteny, SyntenyArgs};
 use crate::translate::{run_translation, TranslateArgs};
 use crate::upgma::{run_neighbor_join, run_upgma};
 use clap::{Parser, Subcommand};
 use std::error::Error;
 
+mod align;
+mod align_reads;
+mod archive;
 mod burrows_wheeler;
 mod bwt;
 mod bwt_matching;
+mod compress;
 mod cyclo;
 mod dosr;
+mod fm_index;
 mod genome;
 mod graph;
 mod manhattan;
+mod mappability;
+mod mapping;
 mod motif;
 mod ori;
 mod peptide;
 mod pylogeny;
+mod repeats;
+mod smems;
 mod synteny;
 mod translate;
 mod translation;
@@ -39,26 +55,44 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    #[command(name = "align")]
+    Align(AlignArgs),
+    #[command(name = "align-reads")]
+    AlignReads(AlignReadsArgs),
     #[command(name = "bwt")]
     BWT(BWTArgs),
     #[command(name = "bwt-matching")]
     BWTMatching(BWTMatchingArgs),
+    #[command(name = "compress")]
+    Compress(CompressArgs),
     #[command(name = "convolution")]
     ConvolutionCyclopeptideSequencing(CycloArgs),
     #[command(name = "cyclo-sequence")]
     CyclopeptideSequencing(CycloArgs),
+    #[command(name = "decompress")]
+    Decompress(DecompressArgs),
     #[command(name = "dosr-median")]
     DosRMedian(DosRArgs),
     #[command(name = "dosr-random")]
     DosRRandom(DosRArgs),
+    #[command(name = "index")]
+    Index(IndexArgs),
     #[command(name = "leaderboard")]
     LeaderBoardCyclopeptideSequencing(CycloArgs),
+    #[command(name = "mappability")]
+    Mappability(MappabilityArgs),
     #[command(name = "neighbor-join")]
     NeighborJoin(PhylogenyArgs),
     #[command(name = "ori")]
     Ori(OriArgs),
     #[command(name = "phylogeny")]
     Phylogeny(PhylogenyArgs),
+    #[command(name = "query")]
+    Query(QueryArgs),
+    #[command(name = "repeats")]
+    Repeats(RepeatsArgs),
+    #[command(name = "smems")]
+    Smems(SmemsArgs),
     #[command(name = "synteny")]
     Synteny(SyntenyArgs),
     #[command(name = "translate")]
@@ -70,16 +104,25 @@ enum Commands {
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
     match cli.command {
+        Commands::Align(args) => run_align(args),
+        Commands::AlignReads(args) => run_align_reads(args),
         Commands::BWT(args) => run_bwt(args),
         Commands::BWTMatching(args) => run_bwt_matching(args),
+        Commands::Compress(args) => run_compress(args),
         Commands::ConvolutionCyclopeptideSequencing(args) => run_convo_cyclo(args),
         Commands::CyclopeptideSequencing(args) => run_cyclo(args),
+        Commands::Decompress(args) => run_decompress(args),
         Commands::DosRMedian(args) => run_median(args),
         Commands::DosRRandom(args) => run_random(args),
+        Commands::Index(args) => run_index(args),
         Commands::LeaderBoardCyclopeptideSequencing(args) => run_leader_cyclo(args),
+        Commands::Mappability(args) => run_mappability(args),
         Commands::NeighborJoin(args) => run_neighbor_join(args),
         Commands::Ori(args) => run_ori(args),
         Commands::Phylogeny(args) => run_phylogeny(args),
+        Commands::Query(args) => run_query(args),
+        Commands::Repeats(args) => run_repeats(args),
+        Commands::Smems(args) => run_smems(args),
         Commands::Synteny(args) => run_synteny(args),
         Commands::Translate(args) => run_translation(args),
         Commands::UPGMA(args) => run_upgma(args),
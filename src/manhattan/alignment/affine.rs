@@ -1,5 +1,6 @@
 use crate::manhattan::alignment::affine_classes::{AffineBacktrack, AffineScore};
-use crate::manhattan::alignment::alignment::AlignmentResult;
+use crate::manhattan::alignment::alignment::{AlignmentMode, AlignmentResult, AlignmentSpan, Node};
+use crate::manhattan::alignment::scoring::ScoringScheme;
 use crate::manhattan::direction::Direction;
 use num::{Bounded, Num};
 use std::error::Error;
@@ -20,21 +21,21 @@ where
 fn affine_backtrack<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    gap_opening: T,
-    gap_extension: T,
-) -> Result<(AffineBacktrack, T, Direction), Box<dyn Error>>
+    scoring: &ScoringScheme<T>,
+    mode: AlignmentMode,
+) -> Result<(AffineBacktrack, T, Node), Box<dyn Error>>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
 {
+    scoring.check(s, t)?;
+    let (gap_opening, gap_extension) = scoring.affine_penalties();
     let s_bytes = s.as_bytes();
     let t_bytes = t.as_bytes();
 
     let (s_len, t_len) = (s.len(), t.len());
 
-    let mut scores = AffineScore::new(s_len + 1, t_len + 1, gap_opening, gap_extension);
-    let mut backtrack = AffineBacktrack::new(s_len + 1, t_len + 1);
+    let mut scores = AffineScore::new(s_len + 1, t_len + 1, gap_opening, gap_extension, mode);
+    let mut backtrack = AffineBacktrack::new(s_len + 1, t_len + 1, mode);
 
     // Fill matrices
     for i in 1..=s_len {
@@ -59,11 +60,7 @@ where
                 Direction::Diagonal
             };
             // Calculate score for match/mismatch
-            let match_score = if s_bytes[i - 1] == t_bytes[j - 1] {
-                match_reward
-            } else {
-                -mismatch_penalty
-            };
+            let match_score = scoring.score(s_bytes[i - 1], t_bytes[j - 1]);
 
             // Fill M matrix
             let diagonal_from_diagonal = add_score(scores.diagonal[i - 1][j - 1], match_score);
@@ -80,41 +77,57 @@ where
             } else {
                 Direction::Diagonal
             };
+
+            // Local alignment can start anywhere
+            if mode == AlignmentMode::Local && scores.diagonal[i][j] <= T::zero() {
+                scores.diagonal[i][j] = T::zero();
+                backtrack.diagonal[i][j] = Direction::Start;
+            }
         }
     }
 
-    let score = scores.diagonal[s_len][t_len]
-        .max(scores.up[s_len][t_len])
-        .max(scores.left[s_len][t_len]);
-    let score_matrix = if score == scores.left[s_len][t_len] {
-        Direction::Left
-    } else if score == scores.up[s_len][t_len] {
-        Direction::Up
-    } else {
-        Direction::Diagonal
+    // The alignment ends at the first best node it may end at
+    let ends: Vec<Node> = match mode {
+        AlignmentMode::Global => vec![(s_len, t_len)],
+        AlignmentMode::Local => (0..=s_len)
+            .flat_map(|i| (0..=t_len).map(move |j| (i, j)))
+            .collect(),
+        AlignmentMode::Fitting => (0..=s_len).map(|i| (i, t_len)).collect(),
+        AlignmentMode::Overlap => (0..=t_len).map(|j| (s_len, j)).collect(),
     };
-    Ok((backtrack, score, score_matrix))
+    let mut end = ends[0];
+    for &(i, j) in &ends[1..] {
+        if scores.diagonal[i][j] > scores.diagonal[end.0][end.1] {
+            end = (i, j);
+        }
+    }
+    Ok((backtrack, scores.diagonal[end.0][end.1], end))
 }
-fn affine_gap_alignment<T>(
+
+pub fn affine_gap_alignment<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    gap_opening: T,
-    gap_extension: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<AlignmentResult<T>, Box<dyn Error>>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
 {
-    let (backtrack, score, score_matrix) = affine_backtrack(
-        s,
-        t,
-        match_reward,
-        mismatch_penalty,
-        gap_opening,
-        gap_extension,
-    )?;
-    backtrack_affine(&backtrack, s, t, score, &score_matrix)
+    affine_alignment(s, t, scoring, AlignmentMode::Global)
+}
+
+/// Alignment in any `mode` with the gap opening and extension penalties of
+/// `scoring`; linear gaps are scored as equal opening and extension.
+pub fn affine_alignment<T>(
+    s: &str,
+    t: &str,
+    scoring: &ScoringScheme<T>,
+    mode: AlignmentMode,
+) -> Result<AlignmentResult<T>, Box<dyn Error>>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
+{
+    let (backtrack, score, end) = affine_backtrack(s, t, scoring, mode)?;
+    Ok(backtrack_affine(&backtrack, s, t, score, end))
 }
 
 fn backtrack_affine<T>(
@@ -122,76 +135,92 @@ fn backtrack_affine<T>(
     s: &str,
     t: &str,
     score: T,
-    score_matrix: &Direction,
-) -> Result<AlignmentResult<T>, Box<dyn Error>>
+    (end_i, end_j): Node,
+) -> AlignmentResult<T>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
 {
     let s_bytes = s.as_bytes();
     let t_bytes = t.as_bytes();
-    let mut align1 = String::new();
-    let mut align2 = String::new();
-    let mut i = s.len();
-    let mut j = t.len();
-    let mut current_matrix = *score_matrix;
-    if current_matrix == Direction::Diagonal {
-        current_matrix = backtrack.diagonal[i][j]
-    } else if current_matrix == Direction::Up {
-        current_matrix = backtrack.up[i][j]
-    } else {
-        current_matrix = backtrack.left[i][j]
-    }
+    let mut align1 = Vec::new();
+    let mut align2 = Vec::new();
+    let (mut i, mut j) = (end_i, end_j);
+    // Matrix the path is in: the M matrix (Diagonal) or a gap matrix
+    let mut current_matrix = Direction::Diagonal;
 
     while i > 0 || j > 0 {
         match current_matrix {
-            Direction::Coordinate(x, y) => {
-                i = x;
-                j = y;
-            }
-            Direction::Diagonal => {
-                let next_matrix = backtrack.diagonal[i][j];
-                if next_matrix == Direction::Diagonal {
-                    align1 = format!("{}{}", s_bytes[i - 1] as char, align1);
-                    align2 = format!("{}{}", t_bytes[j - 1] as char, align2);
+            Direction::Diagonal => match backtrack.diagonal[i][j] {
+                Direction::Diagonal => {
+                    align1.push(s_bytes[i - 1]);
+                    align2.push(t_bytes[j - 1]);
                     i -= 1;
                     j -= 1;
                 }
-                current_matrix = next_matrix;
-            }
+                Direction::Left => current_matrix = Direction::Left,
+                Direction::Up => current_matrix = Direction::Up,
+                _ => break,
+            },
             Direction::Left => {
-                align1 = format!("-{}", align1);
-                align2 = format!("{}{}", t_bytes[j - 1] as char, align2);
-                current_matrix = backtrack.left[i][j];
+                align1.push(b'-');
+                align2.push(t_bytes[j - 1]);
+                if backtrack.left[i][j] != Direction::Left {
+                    current_matrix = Direction::Diagonal;
+                }
                 j -= 1;
             }
-            Direction::None => break,
-            Direction::Start => {
-                i = 0;
-                j = 0;
-            }
-            Direction::Up => {
-                align1 = format!("{}{}", s_bytes[i - 1] as char, align1);
-                align2 = format!("-{}", align2);
-                current_matrix = backtrack.up[i][j];
+            _ => {
+                align1.push(s_bytes[i - 1]);
+                align2.push(b'-');
+                if backtrack.up[i][j] != Direction::Up {
+                    current_matrix = Direction::Diagonal;
+                }
                 i -= 1;
             }
         }
     }
 
-    // Reverse and convert to strings
-
-    Ok(AlignmentResult::new(score, &align1, &align2))
+    align1.reverse();
+    align2.reverse();
+    let span = AlignmentSpan {
+        s: i..end_i,
+        t: j..end_j,
+    };
+    AlignmentResult::with_span(
+        score,
+        &String::from_utf8_lossy(&align1),
+        &String::from_utf8_lossy(&align2),
+        span,
+    )
 }
 #[cfg(test)]
 mod tests {
-    use crate::manhattan::alignment::affine::affine_gap_alignment;
-    use crate::manhattan::alignment::alignment::AlignmentResult;
+    use crate::manhattan::alignment::affine::{affine_alignment, affine_gap_alignment};
+    use crate::manhattan::alignment::alignment::{AlignmentMode, AlignmentResult, AlignmentSpan};
+    use crate::manhattan::alignment::fitting::fitting_alignment;
+    use crate::manhattan::alignment::global::global_alignment;
+    use crate::manhattan::alignment::local::local_alignment;
+    use crate::manhattan::alignment::overlap::overlap_alignment;
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
     use std::error::Error;
 
     #[test]
     fn test_affine_gap_alignment1() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("GA", "GTTA", 1, 3, 2, 1)?,
+            affine_gap_alignment(
+                "GA",
+                "GTTA",
+                &ScoringScheme::match_mismatch(
+                    1,
+                    3,
+                    GapModel::Affine {
+                        opening: 2,
+                        extension: 1
+                    }
+                )
+            )?,
             AlignmentResult::new(-1, "G--A", "GTTA")
         );
         Ok(())
@@ -200,7 +229,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment2() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("TTT", "TT", 1, 5, 3, 1)?,
+            affine_gap_alignment(
+                "TTT",
+                "TT",
+                &ScoringScheme::match_mismatch(
+                    1,
+                    5,
+                    GapModel::Affine {
+                        opening: 3,
+                        extension: 1
+                    }
+                )
+            )?,
             AlignmentResult::new(-1, "TTT", "TT-")
         );
         Ok(())
@@ -209,7 +249,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment3() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("GAT", "AT", 1, 5, 5, 1)?,
+            affine_gap_alignment(
+                "GAT",
+                "AT",
+                &ScoringScheme::match_mismatch(
+                    1,
+                    5,
+                    GapModel::Affine {
+                        opening: 5,
+                        extension: 1
+                    }
+                )
+            )?,
             AlignmentResult::new(-3, "GAT", "-AT")
         );
         Ok(())
@@ -218,7 +269,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment4() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("CCAT", "GAT", 1, 5, 2, 1)?,
+            affine_gap_alignment(
+                "CCAT",
+                "GAT",
+                &ScoringScheme::match_mismatch(
+                    1,
+                    5,
+                    GapModel::Affine {
+                        opening: 2,
+                        extension: 1
+                    }
+                )
+            )?,
             AlignmentResult::new(-3, "CC-AT", "--GAT")
         );
         Ok(())
@@ -227,7 +289,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment5() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("CAGGT", "TAC", 1, 2, 3, 2)?,
+            affine_gap_alignment(
+                "CAGGT",
+                "TAC",
+                &ScoringScheme::match_mismatch(
+                    1,
+                    2,
+                    GapModel::Affine {
+                        opening: 3,
+                        extension: 2
+                    }
+                )
+            )?,
             AlignmentResult::new(-8, "CAGGT", "TAC--")
         );
         Ok(())
@@ -236,7 +309,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment6() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("GTTCCAGGTA", "CAGTAGTCGT", 2, 3, 3, 2)?,
+            affine_gap_alignment(
+                "GTTCCAGGTA",
+                "CAGTAGTCGT",
+                &ScoringScheme::match_mismatch(
+                    2,
+                    3,
+                    GapModel::Affine {
+                        opening: 3,
+                        extension: 2
+                    }
+                )
+            )?,
             AlignmentResult::new(-8, "--GTTCCAG--GTA", "CAGT---AGTCGT-")
         );
         Ok(())
@@ -245,7 +329,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment7() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("AGCTAGCCTAG", "GT", 1, 3, 1, 1)?,
+            affine_gap_alignment(
+                "AGCTAGCCTAG",
+                "GT",
+                &ScoringScheme::match_mismatch(
+                    1,
+                    3,
+                    GapModel::Affine {
+                        opening: 1,
+                        extension: 1
+                    }
+                )
+            )?,
             AlignmentResult::new(-7, "AGCTAGCCTAG", "-G-T-------")
         );
         Ok(())
@@ -254,7 +349,18 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment8() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("AA", "CAGTGTCAGTA", 2, 1, 2, 1)?,
+            affine_gap_alignment(
+                "AA",
+                "CAGTGTCAGTA",
+                &ScoringScheme::match_mismatch(
+                    2,
+                    1,
+                    GapModel::Affine {
+                        opening: 2,
+                        extension: 1
+                    }
+                )
+            )?,
             AlignmentResult::new(-7, "-A--------A", "CAGTGTCAGTA")
         );
         Ok(())
@@ -263,9 +369,121 @@ mod tests {
     #[test]
     fn test_affine_gap_alignment9() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            affine_gap_alignment("ACGTA", "ACT", 5, 2, 15, 5)?,
+            affine_gap_alignment(
+                "ACGTA",
+                "ACT",
+                &ScoringScheme::match_mismatch(
+                    5,
+                    2,
+                    GapModel::Affine {
+                        opening: 15,
+                        extension: 5
+                    }
+                )
+            )?,
             AlignmentResult::new(-12, "ACGTA", "ACT--")
         );
         Ok(())
     }
+
+    /// Score of an alignment under affine gaps, from its two rows.
+    fn rescore(align1: &str, align2: &str, scoring: &ScoringScheme<i32>) -> i32 {
+        let (opening, extension) = scoring.affine_penalties();
+        let mut score = 0;
+        let mut previous = (false, false);
+        for (a, b) in align1.bytes().zip(align2.bytes()) {
+            let gaps = (a == b'-', b == b'-');
+            score += match gaps {
+                (false, false) => scoring.score(a, b),
+                _ if gaps == previous => -extension,
+                _ => -opening,
+            };
+            previous = gaps;
+        }
+        score
+    }
+
+    #[test]
+    fn test_affine_alignment1() -> Result<(), Box<dyn Error>> {
+        let scoring = ScoringScheme::match_mismatch(
+            2,
+            3,
+            GapModel::Affine {
+                opening: 4,
+                extension: 1,
+            },
+        );
+        assert_eq!(
+            affine_alignment("TTGACCCGGTT", "AACGACCCGCC", &scoring, AlignmentMode::Local)?,
+            AlignmentResult::with_span(12, "GACCCG", "GACCCG", AlignmentSpan { s: 2..8, t: 3..9 })
+        );
+        assert_eq!(
+            affine_alignment("TTACGTTTTTGCAA", "ACGGCA", &scoring, AlignmentMode::Fitting)?,
+            AlignmentResult::with_span(
+                4,
+                "ACGTTTTTGCA",
+                "ACG-----GCA",
+                AlignmentSpan { s: 2..13, t: 0..6 }
+            )
+        );
+        assert_eq!(
+            affine_alignment("CCCCGATTACA", "GATTAGGG", &scoring, AlignmentMode::Overlap)?,
+            AlignmentResult::with_span(
+                5,
+                "GATTACA",
+                "GATTA--",
+                AlignmentSpan { s: 4..11, t: 0..5 }
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_affine_alignment2() -> Result<(), Box<dyn Error>> {
+        // Equal opening and extension penalties score like linear gaps
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..200 {
+            let mut random = |len: usize| {
+                (0..rng.gen_range(0..=len))
+                    .map(|_| b"ACGT"[rng.gen_range(0..4)] as char)
+                    .collect::<String>()
+            };
+            let (s, t) = (random(20), random(20));
+            let linear = ScoringScheme::match_mismatch(2, 1, GapModel::Linear(2));
+            let equal = ScoringScheme::match_mismatch(
+                2,
+                1,
+                GapModel::Affine {
+                    opening: 2,
+                    extension: 2,
+                },
+            );
+            let affine = ScoringScheme::match_mismatch(
+                2,
+                1,
+                GapModel::Affine {
+                    opening: 3,
+                    extension: 1,
+                },
+            );
+            let modes = [
+                (AlignmentMode::Global, global_alignment(&s, &t, &linear)?),
+                (AlignmentMode::Local, local_alignment(&s, &t, &linear)?),
+                (AlignmentMode::Fitting, fitting_alignment(&s, &t, &linear)?),
+                (AlignmentMode::Overlap, overlap_alignment(&s, &t, &linear)?),
+            ];
+            for (mode, expected) in modes {
+                let result = affine_alignment(&s, &t, &equal, mode)?;
+                assert_eq!(result.score(), expected.score(), "{:?} {} {}", mode, s, t);
+
+                let result = affine_alignment(&s, &t, &affine, mode)?;
+                let span = result.span().clone();
+                let score = rescore(result.alignment1(), result.alignment2(), &affine);
+                assert_eq!(*result.score(), score, "{:?} {} {}", mode, s, t);
+                assert_eq!(result.alignment1().replace('-', ""), s[span.s]);
+                assert_eq!(result.alignment2().replace('-', ""), t[span.t]);
+            }
+        }
+        Ok(())
+    }
 }
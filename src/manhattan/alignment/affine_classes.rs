@@ -1,3 +1,4 @@
+use crate::manhattan::alignment::alignment::AlignmentMode;
 use crate::manhattan::direction::Direction;
 use num::{Bounded, Num};
 
@@ -9,22 +10,39 @@ pub struct AffineScore<T> {
 }
 
 impl<T: Num + Copy + Bounded> AffineScore<T> {
-    pub fn new(rows: usize, cols: usize, gap_opening: T, gap_extension: T) -> Self {
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        gap_opening: T,
+        gap_extension: T,
+        mode: AlignmentMode,
+    ) -> Self {
         let mut diagonal = vec![vec![T::zero(); cols]; rows];
         let mut up = vec![vec![T::zero(); cols]; rows];
         let mut left = vec![vec![T::zero(); cols]; rows];
 
+        // Only global alignments pay for skipping a prefix of s
         for i in 1..rows {
-            up[i][0] = up[i - 1][0] - if i == 1 { gap_opening } else { gap_extension };
             left[i][0] = T::min_value();
-            diagonal[i][0] = diagonal[i - 1][0] - if i == 1 { gap_opening } else { gap_extension }
+            if mode == AlignmentMode::Global {
+                up[i][0] = up[i - 1][0] - if i == 1 { gap_opening } else { gap_extension };
+                diagonal[i][0] =
+                    diagonal[i - 1][0] - if i == 1 { gap_opening } else { gap_extension }
+            } else {
+                up[i][0] = T::min_value();
+            }
         }
 
-        // Initialize first row
+        // Initialize first row; local alignments skip a prefix of t for free
         for j in 1..cols {
             up[0][j] = T::min_value();
-            left[0][j] = left[0][j - 1] - if j == 1 { gap_opening } else { gap_extension };
-            diagonal[0][j] = diagonal[0][j - 1] - if j == 1 { gap_opening } else { gap_extension };
+            if mode == AlignmentMode::Local {
+                left[0][j] = T::min_value();
+            } else {
+                left[0][j] = left[0][j - 1] - if j == 1 { gap_opening } else { gap_extension };
+                diagonal[0][j] =
+                    diagonal[0][j - 1] - if j == 1 { gap_opening } else { gap_extension };
+            }
         }
         AffineScore { diagonal, up, left }
     }
@@ -37,19 +55,27 @@ pub struct AffineBacktrack {
 }
 
 impl AffineBacktrack {
-    pub fn new(rows: usize, cols: usize) -> Self {
+    pub fn new(rows: usize, cols: usize, mode: AlignmentMode) -> Self {
         let mut diagonal = vec![vec![Direction::None; cols]; rows];
         let mut up = vec![vec![Direction::None; cols]; rows];
         let mut left = vec![vec![Direction::None; cols]; rows];
         for i in 1..rows {
-            up[i][0] = Direction::Up;
-            diagonal[i][0] = Direction::Up;
+            if mode == AlignmentMode::Global {
+                up[i][0] = Direction::Up;
+                diagonal[i][0] = Direction::Up;
+            } else {
+                diagonal[i][0] = Direction::Start;
+            }
         }
 
         // Initialize first row
         for j in 1..cols {
-            left[0][j] = Direction::Left;
-            diagonal[0][j] = Direction::Left;
+            if mode == AlignmentMode::Local {
+                diagonal[0][j] = Direction::Start;
+            } else {
+                left[0][j] = Direction::Left;
+                diagonal[0][j] = Direction::Left;
+            }
         }
         AffineBacktrack { diagonal, up, left }
     }
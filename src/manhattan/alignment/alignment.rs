@@ -1,16 +1,256 @@
-use std::fmt::Debug;
+use crate::manhattan::alignment::cigar::Cigar;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Node `(i, j)` of the alignment graph: `i` symbols of `s` and `j` of `t`
+/// aligned so far.
+pub type Node = (usize, usize);
+
+/// Score and rows of an alignment of `s` (`alignment1`) with `t`
+/// (`alignment2`), with the ranges of `s` and `t` that the rows cover.
 #[derive(Debug, Clone, PartialEq)]
 pub struct AlignmentResult<T> {
     score: T,
     alignment1: String,
     alignment2: String,
+    span: AlignmentSpan,
 }
+
 impl<T> AlignmentResult<T> {
+    /// Alignment covering the whole of both strings.
     pub fn new(score: T, alignment1: &str, alignment2: &str) -> Self {
+        let span = AlignmentSpan {
+            s: 0..residues(alignment1.as_bytes()),
+            t: 0..residues(alignment2.as_bytes()),
+        };
+        Self::with_span(score, alignment1, alignment2, span)
+    }
+
+    pub fn with_span(score: T, alignment1: &str, alignment2: &str, span: AlignmentSpan) -> Self {
         AlignmentResult {
             score,
             alignment1: alignment1.to_owned(),
             alignment2: alignment2.to_owned(),
+            span,
+        }
+    }
+
+    pub fn score(&self) -> &T {
+        &self.score
+    }
+
+    pub fn alignment1(&self) -> &str {
+        &self.alignment1
+    }
+
+    pub fn alignment2(&self) -> &str {
+        &self.alignment2
+    }
+
+    /// Start and end of the alignment in `s` and in `t`.
+    pub fn span(&self) -> &AlignmentSpan {
+        &self.span
+    }
+
+    /// CIGAR of `t` against `s` as the reference.
+    pub fn cigar(&self) -> Result<Cigar, Box<dyn Error>> {
+        Cigar::from_alignment(&self.alignment1, &self.alignment2)
+    }
+
+    /// Number of alignment columns.
+    pub fn len(&self) -> usize {
+        self.alignment1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alignment1.is_empty()
+    }
+
+    pub fn matches(&self) -> usize {
+        self.columns().filter(|(a, b)| a == b).count()
+    }
+
+    pub fn mismatches(&self) -> usize {
+        self.columns()
+            .filter(|&(a, b)| a != b && a != b'-' && b != b'-')
+            .count()
+    }
+
+    /// Number of columns with a gap in either row.
+    pub fn gaps(&self) -> usize {
+        self.columns()
+            .filter(|&(a, b)| a == b'-' || b == b'-')
+            .count()
+    }
+
+    /// Percentage of the columns that are matches.
+    pub fn identity(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        100.0 * self.matches() as f64 / self.len() as f64
+    }
+
+    /// The rows in blocks of `width` columns separated by blank lines, as
+    /// BLAST prints them: each row between the positions of its first and
+    /// last symbol, and a middle line with `mark` of every column without
+    /// gaps.
+    pub fn blocks(&self, width: usize, mark: impl Fn(u8, u8) -> char) -> String {
+        let width = width.max(1);
+        let (top, bottom) = (self.alignment1.as_bytes(), self.alignment2.as_bytes());
+        let digits = self.span.s.end.max(self.span.t.end).to_string().len();
+        // Symbols of s and of t before the current block
+        let (mut i, mut j) = (self.span.s.start, self.span.t.start);
+        let mut blocks = String::new();
+        for start in (0..top.len()).step_by(width) {
+            let end = (start + width).min(top.len());
+            let marks = top[start..end]
+                .iter()
+                .zip(&bottom[start..end])
+                .map(|(&a, &b)| match (a, b) {
+                    (b'-', _) | (_, b'-') => ' ',
+                    _ => mark(a, b),
+                })
+                .collect::<String>();
+            blocks += &block_row('s', &top[start..end], &mut i, digits);
+            blocks += &format!("{:pad$}{}\n", "", marks.trim_end(), pad = digits + 3);
+            blocks += &block_row('t', &bottom[start..end], &mut j, digits);
+            blocks.push('\n');
         }
+        blocks
+    }
+
+    fn columns(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.alignment1.bytes().zip(self.alignment2.bytes())
+    }
+}
+
+impl<T: Display> AlignmentResult<T> {
+    /// Score, identities, mismatches and gaps on one line.
+    pub fn summary(&self) -> String {
+        format!(
+            "Score: {}, Identities: {}/{} ({:.1}%), Mismatches: {}, Gaps: {}/{}",
+            self.score,
+            self.matches(),
+            self.len(),
+            self.identity(),
+            self.mismatches(),
+            self.gaps(),
+            self.len()
+        )
+    }
+}
+
+/// The [`AlignmentResult::summary`], then [`AlignmentResult::blocks`] marking
+/// identities with `|`. The formatter width, as in `{:80}`, sets the block
+/// width, 60 columns by default.
+impl<T: Display> Display for AlignmentResult<T> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let identity = |a, b| if a == b { '|' } else { ' ' };
+        writeln!(f, "{}\n", self.summary())?;
+        write!(f, "{}", self.blocks(f.width().unwrap_or(60), identity))
+    }
+}
+
+/// Symbols of an alignment row, without its gaps.
+fn residues(row: &[u8]) -> usize {
+    row.iter().filter(|&&b| b != b'-').count()
+}
+
+/// One row of a block, advancing `pos` past its symbols. A row of gaps only
+/// shows the position of the symbol before it at both ends.
+fn block_row(label: char, row: &[u8], pos: &mut usize, digits: usize) -> String {
+    let before = *pos;
+    *pos += residues(row);
+    let first = if *pos > before { before + 1 } else { before };
+    format!(
+        "{} {:>digits$} {} {}\n",
+        label,
+        first,
+        String::from_utf8_lossy(row),
+        pos,
+        digits = digits
+    )
+}
+
+/// Ranges of the two input strings covered by an alignment, which local and
+/// fitting alignments do not span completely.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignmentSpan {
+    pub s: Range<usize>,
+    pub t: Range<usize>,
+}
+
+/// Which parts of the two strings an alignment must cover: `Global` both
+/// entirely, `Local` any substrings, `Fitting` the whole of `t` against a
+/// substring of `s`, and `Overlap` a suffix of `s` against a prefix of `t`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AlignmentMode {
+    Global,
+    Local,
+    Fitting,
+    Overlap,
+}
+
+impl FromStr for AlignmentMode {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "global" => Ok(AlignmentMode::Global),
+            "local" => Ok(AlignmentMode::Local),
+            "fitting" => Ok(AlignmentMode::Fitting),
+            "overlap" => Ok(AlignmentMode::Overlap),
+            _ => Err(format!(
+                "Unknown alignment mode {}, expected global, local, fitting or overlap",
+                s
+            )
+            .into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manhattan::alignment::alignment::{AlignmentResult, AlignmentSpan};
+    use std::error::Error;
+
+    #[test]
+    fn test_alignment_result1() -> Result<(), Box<dyn Error>> {
+        let result = AlignmentResult::new(8, "PLEASANTLY", "-ME--AN-LY");
+        assert_eq!(result.span(), &AlignmentSpan { s: 0..10, t: 0..6 });
+        assert_eq!(result.cigar()?.to_string(), "1D2M2D2M1D2M");
+        assert_eq!(
+            (result.matches(), result.mismatches(), result.gaps()),
+            (5, 1, 4)
+        );
+        assert_eq!(result.identity(), 50.0);
+
+        let result =
+            AlignmentResult::with_span(5, "C-TT", "CATT", AlignmentSpan { s: 0..3, t: 9..13 });
+        assert_eq!(result.cigar()?.to_string(), "1M1I2M");
+        assert_eq!(result.identity(), 75.0);
+        assert_eq!(AlignmentResult::new(0, "", "").identity(), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_alignment_result2() -> Result<(), Box<dyn Error>> {
+        let result = AlignmentResult::new(8, "PLEASANTLY", "-ME--AN-LY");
+        assert_eq!(
+            format!("{:6}", result),
+            "Score: 8, Identities: 5/10 (50.0%), Mismatches: 1, Gaps: 4/10\n\n\
+             s  1 PLEASA 6\n       |  |\nt  1 -ME--A 3\n\n\
+             s  7 NTLY 10\n     | ||\nt  4 N-LY 6\n\n"
+        );
+        let result =
+            AlignmentResult::with_span(5, "C-TT", "CATT", AlignmentSpan { s: 0..3, t: 9..13 });
+        assert_eq!(
+            result.blocks(2, |_, _| '*'),
+            "s  1 C- 1\n     *\nt 10 CA 11\n\ns  2 TT 3\n     **\nt 12 TT 13\n\n"
+        );
+        Ok(())
     }
 }
@@ -1,10 +1,12 @@
-use crate::manhattan::alignment::alignment::AlignmentResult;
+use crate::manhattan::alignment::alignment::{AlignmentResult, AlignmentSpan};
 use crate::manhattan::direction::Direction;
 use num::Num;
 use std::error::Error;
 use std::fmt::Debug;
 use std::ops::{Mul, Neg};
 
+/// Alignment along the pointers of `backtrack` from the sink, with the ranges
+/// of `s` and `t` it covers.
 pub fn backtrack_alignment<T>(
     backtrack: &[Vec<Direction>],
     s: &str,
@@ -20,8 +22,12 @@ where
     let mut align2 = String::new();
     let mut i = s.len();
     let mut j = t.len();
+    let mut end = None;
 
     while i > 0 || j > 0 {
+        if !matches!(backtrack[i][j], Direction::Coordinate(..)) {
+            end.get_or_insert((i, j));
+        }
         match backtrack[i][j] {
             Direction::Coordinate(x, y) => {
                 i = x;
@@ -44,12 +50,15 @@ where
                 align2 = format!("-{}", align2);
                 i -= 1;
             }
-            Direction::Start => {
-                i = 0;
-                j = 0;
-            }
+            // Local alignments start here; keep (i, j) as the start of the span
+            Direction::Start => break,
         }
     }
 
-    Ok(AlignmentResult::new(score, &align1, &align2))
+    let (end_i, end_j) = end.unwrap_or((i, j));
+    let span = AlignmentSpan {
+        s: i..end_i,
+        t: j..end_j,
+    };
+    Ok(AlignmentResult::with_span(score, &align1, &align2, span))
 }
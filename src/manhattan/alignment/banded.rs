@@ -0,0 +1,474 @@
+use crate::manhattan::alignment::alignment::{AlignmentMode, AlignmentResult, AlignmentSpan, Node};
+use crate::manhattan::alignment::scoring::ScoringScheme;
+use crate::manhattan::direction::Direction;
+use num::{FromPrimitive, Num};
+use std::error::Error;
+use std::fmt::Debug;
+use std::ops::{Mul, Neg};
+use std::str::FromStr;
+
+/// Width the adaptive band starts from before it is doubled.
+const ADAPTIVE_START_WIDTH: usize = 16;
+
+/// Diagonals `i - j` of the alignment graph that a banded alignment visits:
+/// those between the diagonals of its first and last node, widened by
+/// `Width` on each side, or by a width doubled until no path leaving the
+/// band can score as well as the best path inside it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Band {
+    Width(usize),
+    Adaptive,
+}
+
+impl FromStr for Band {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("adaptive") {
+            return Ok(Band::Adaptive);
+        }
+        s.parse::<usize>()
+            .map(Band::Width)
+            .map_err(|_| format!("Invalid band {}, expected a width or adaptive", s).into())
+    }
+}
+
+/// Backtrack pointers of the nodes inside a band, row by row.
+struct BandedBacktrack {
+    /// Column of the first node of each row.
+    starts: Vec<usize>,
+    rows: Vec<Vec<Direction>>,
+}
+
+impl BandedBacktrack {
+    fn get(&self, (i, j): Node) -> Direction {
+        self.rows[i][j - self.starts[i]]
+    }
+}
+
+/// Score of node `j` of a band row starting at column `start`, or `None`
+/// outside the band.
+fn band_score<T: Copy>(row: &[T], start: usize, j: usize) -> Option<T> {
+    j.checked_sub(start).and_then(|k| row.get(k)).copied()
+}
+
+/// Fills the band `lo..=hi` of diagonals as `global_backtrack` or
+/// `fitting_backtrack` fill the whole matrix, so that ties are broken the
+/// same way. Returns the pointers, the score and the node the path ends at.
+fn banded_backtrack<T>(
+    s: &[u8],
+    t: &[u8],
+    scoring: &ScoringScheme<T>,
+    indel_penalty: T,
+    mode: AlignmentMode,
+    (lo, hi): (isize, isize),
+) -> (BandedBacktrack, T, Node)
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+{
+    let (n, m) = (s.len(), t.len());
+    let mut backtrack = BandedBacktrack {
+        starts: Vec::with_capacity(n + 1),
+        rows: Vec::with_capacity(n + 1),
+    };
+    let mut previous: Vec<T> = Vec::new();
+    let mut previous_start = 0;
+    let mut end = (0, m);
+    let mut best = None;
+
+    for i in 0..=n {
+        let start = (i as isize - hi).max(0) as usize;
+        let stop = ((i as isize - lo) as usize).min(m);
+        let mut scores = Vec::with_capacity(stop + 1 - start);
+        let mut pointers = Vec::with_capacity(stop + 1 - start);
+        for j in start..=stop {
+            let (score, pointer) = if i == 0 && j == 0 {
+                (T::zero(), Direction::None)
+            } else if i == 0 {
+                (*scores.last().unwrap() - indel_penalty, Direction::Left)
+            } else if j == 0 {
+                match mode {
+                    AlignmentMode::Global => (previous[0] - indel_penalty, Direction::Up),
+                    _ => (T::zero(), Direction::Start),
+                }
+            } else {
+                // The diagonal predecessor shares the diagonal, so it is in the band
+                let diagonal = band_score(&previous, previous_start, j - 1)
+                    .map(|score| score + scoring.score(s[i - 1], t[j - 1]));
+                let up =
+                    band_score(&previous, previous_start, j).map(|score| score - indel_penalty);
+                let left = scores.last().map(|&score: &T| score - indel_penalty);
+                if diagonal >= up && diagonal >= left {
+                    (diagonal.unwrap(), Direction::Diagonal)
+                } else if left >= up {
+                    (left.unwrap(), Direction::Left)
+                } else {
+                    (up.unwrap(), Direction::Up)
+                }
+            };
+            scores.push(score);
+            pointers.push(pointer);
+        }
+        // Fitting alignments end at the first row with the best last column
+        if mode == AlignmentMode::Fitting && stop == m {
+            let score = scores[m - start];
+            if best.is_none_or(|best| score > best) {
+                best = Some(score);
+                end = (i, m);
+            }
+        }
+        backtrack.starts.push(start);
+        backtrack.rows.push(pointers);
+        previous = scores;
+        previous_start = start;
+    }
+
+    if mode == AlignmentMode::Global {
+        end = (n, m);
+        best = band_score(&previous, previous_start, m);
+    }
+    (backtrack, best.unwrap(), end)
+}
+
+fn banded_path<T>(
+    backtrack: &BandedBacktrack,
+    s: &[u8],
+    t: &[u8],
+    score: T,
+    (end_i, end_j): Node,
+) -> AlignmentResult<T> {
+    let mut align1 = Vec::new();
+    let mut align2 = Vec::new();
+    let (mut i, mut j) = (end_i, end_j);
+    while i > 0 || j > 0 {
+        match backtrack.get((i, j)) {
+            Direction::Diagonal => {
+                align1.push(s[i - 1]);
+                align2.push(t[j - 1]);
+                i -= 1;
+                j -= 1;
+            }
+            Direction::Left => {
+                align1.push(b'-');
+                align2.push(t[j - 1]);
+                j -= 1;
+            }
+            Direction::Up => {
+                align1.push(s[i - 1]);
+                align2.push(b'-');
+                i -= 1;
+            }
+            _ => break,
+        }
+    }
+    align1.reverse();
+    align2.reverse();
+    AlignmentResult::with_span(
+        score,
+        &String::from_utf8_lossy(&align1),
+        &String::from_utf8_lossy(&align2),
+        AlignmentSpan {
+            s: i..end_i,
+            t: j..end_j,
+        },
+    )
+}
+
+/// Whether no path leaving the band widened by `width` can score `score` or
+/// more. Such a global path has at least `|n - m| + 2 (width + 1)` indels and
+/// such a fitting path at least `width + 1` insertions into `s`; the bound
+/// gives every other column the best substitution score.
+fn band_is_optimal<T>(
+    s: &[u8],
+    t: &[u8],
+    scoring: &ScoringScheme<T>,
+    indel_penalty: T,
+    mode: AlignmentMode,
+    width: usize,
+    score: T,
+) -> bool
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + FromPrimitive,
+{
+    let (n, m) = (s.len(), t.len());
+    let symbols = |x: &[u8]| {
+        let mut present = [false; 256];
+        x.iter().for_each(|&b| present[b as usize] = true);
+        (0..=255u8).filter(move |&b| present[b as usize])
+    };
+    let Some(best_substitution) = symbols(s)
+        .flat_map(|a| symbols(t).map(move |b| scoring.score(a, b)))
+        .max()
+    else {
+        return true;
+    };
+    let count = |k: usize| T::from_usize(k).ok_or("Band bound overflows the score type");
+    let two = T::one() + T::one();
+    // Bounds are linear in the number of indels, so the extremes suffice
+    let bounds = match mode {
+        AlignmentMode::Global => {
+            let fewest = n.abs_diff(m) + 2 * (width + 1);
+            if fewest > n + m {
+                return true;
+            }
+            // Twice the bound, as the diagonal steps are half the other steps
+            [fewest, n + m].map(|indels| {
+                Ok(best_substitution * count(n + m - indels)?
+                    - two * indel_penalty * count(indels)?)
+            })
+        }
+        _ => {
+            if width + 1 > m {
+                return true;
+            }
+            [width + 1, m].map(|insertions| {
+                Ok(two
+                    * (best_substitution * count(m - insertions)?
+                        - indel_penalty * count(insertions)?))
+            })
+        }
+    };
+    // A bound that does not fit in `T` proves nothing
+    bounds
+        .iter()
+        .all(|bound: &Result<T, &str>| bound.is_ok_and(|bound| two * score > bound))
+}
+
+fn banded_alignment<T>(
+    s: &str,
+    t: &str,
+    scoring: &ScoringScheme<T>,
+    mode: AlignmentMode,
+    band: Band,
+) -> Result<AlignmentResult<T>, Box<dyn Error>>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + FromPrimitive,
+{
+    scoring.check(s, t)?;
+    let indel_penalty = scoring.indel_penalty()?;
+    let (s, t) = (s.as_bytes(), t.as_bytes());
+    let shift = s.len() as isize - t.len() as isize;
+    let diagonals = |width: usize| (shift.min(0) - width as isize, shift.max(0) + width as isize);
+    let mut width = match band {
+        Band::Width(width) => width,
+        Band::Adaptive => ADAPTIVE_START_WIDTH,
+    };
+    loop {
+        let (lo, hi) = diagonals(width);
+        let (backtrack, score, end) =
+            banded_backtrack(s, t, scoring, indel_penalty, mode, (lo, hi));
+        let whole = lo <= -(t.len() as isize) && hi >= s.len() as isize;
+        if matches!(band, Band::Width(_))
+            || whole
+            || band_is_optimal(s, t, scoring, indel_penalty, mode, width, score)
+        {
+            return Ok(banded_path(&backtrack, s, t, score, end));
+        }
+        width *= 2;
+    }
+}
+
+/// Global alignment of `s` and `t` visiting only the nodes of `band`, in time
+/// and space proportional to its area. Returns the alignment of
+/// `global_alignment` whenever that alignment lies inside the band, which an
+/// adaptive band guarantees. Supports only linear gaps.
+pub fn banded_global_alignment<T>(
+    s: &str,
+    t: &str,
+    scoring: &ScoringScheme<T>,
+    band: Band,
+) -> Result<AlignmentResult<T>, Box<dyn Error>>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + FromPrimitive,
+{
+    banded_alignment(s, t, scoring, AlignmentMode::Global, band)
+}
+
+/// Fitting alignment of `t` into `s` visiting only the nodes of `band`, whose
+/// diagonals cover every place `t` fits into `s` without gaps. Meant for an
+/// `s` that is a window around the expected place of `t`, as around a seed.
+/// Returns the alignment of `fitting_alignment` whenever that alignment lies
+/// inside the band, which an adaptive band guarantees. Supports only linear
+/// gaps.
+pub fn banded_fitting_alignment<T>(
+    s: &str,
+    t: &str,
+    scoring: &ScoringScheme<T>,
+    band: Band,
+) -> Result<AlignmentResult<T>, Box<dyn Error>>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + FromPrimitive,
+{
+    banded_alignment(s, t, scoring, AlignmentMode::Fitting, band)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manhattan::alignment::alignment::AlignmentResult;
+    use crate::manhattan::alignment::banded::{
+        banded_fitting_alignment, banded_global_alignment, Band,
+    };
+    use crate::manhattan::alignment::fitting::fitting_alignment;
+    use crate::manhattan::alignment::global::global_alignment;
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme, SubstitutionMatrix};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::error::Error;
+
+    /// `s` with a few random substitutions, insertions and deletions.
+    fn mutate(rng: &mut StdRng, s: &str, edits: usize) -> String {
+        let mut bytes = s.as_bytes().to_vec();
+        for _ in 0..edits {
+            let k = rng.gen_range(0..=bytes.len());
+            let base = b"ACGT"[rng.gen_range(0..4)];
+            match rng.gen_range(0..3) {
+                0 if k < bytes.len() => bytes[k] = base,
+                1 if k < bytes.len() => {
+                    bytes.remove(k);
+                }
+                _ => bytes.insert(k, base),
+            }
+        }
+        String::from_utf8(bytes).unwrap()
+    }
+
+    fn random(rng: &mut StdRng, len: usize) -> String {
+        (0..len)
+            .map(|_| b"ACGT"[rng.gen_range(0..4)] as char)
+            .collect()
+    }
+
+    #[test]
+    fn test_banded_global_alignment1() -> Result<(), Box<dyn Error>> {
+        let scoring = ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1));
+        assert_eq!(
+            banded_global_alignment("AAAAT", "TAAAA", &scoring, Band::Width(0))?,
+            AlignmentResult::new(1, "AAAAT", "TAAAA")
+        );
+        assert_eq!(
+            banded_global_alignment("AAAAT", "TAAAA", &scoring, Band::Width(1))?,
+            global_alignment("AAAAT", "TAAAA", &scoring)?
+        );
+        assert_eq!(
+            banded_global_alignment("GAGA", "GAT", &scoring, Band::Adaptive)?,
+            global_alignment("GAGA", "GAT", &scoring)?
+        );
+        assert_eq!(
+            banded_global_alignment("", "ACG", &scoring, Band::Width(0))?,
+            AlignmentResult::new(-3, "---", "ACG")
+        );
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(5));
+        assert_eq!(
+            banded_global_alignment("PLEASANTLY", "MEANLY", &blosum62, Band::Adaptive)?,
+            global_alignment("PLEASANTLY", "MEANLY", &blosum62)?
+        );
+        let affine = ScoringScheme::match_mismatch(
+            1,
+            1,
+            GapModel::Affine {
+                opening: 2,
+                extension: 1,
+            },
+        );
+        assert!(banded_global_alignment("GAGA", "GAT", &affine, Band::Adaptive).is_err());
+
+        assert_eq!("Adaptive".parse::<Band>()?, Band::Adaptive);
+        assert_eq!("12".parse::<Band>()?, Band::Width(12));
+        assert!("-1".parse::<Band>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_banded_global_alignment2() -> Result<(), Box<dyn Error>> {
+        let mut rng = StdRng::seed_from_u64(5);
+        for round in 0..300 {
+            let len = rng.gen_range(0..300);
+            let s = random(&mut rng, len);
+            // Mostly close relatives, sometimes unrelated strings
+            let t = if round % 4 == 0 {
+                let len = rng.gen_range(0..300);
+                random(&mut rng, len)
+            } else {
+                let edits = rng.gen_range(0..=len / 10 + 1);
+                mutate(&mut rng, &s, edits)
+            };
+            for (m, x, g) in [(1, 1, 1), (1, 1, 2), (2, 3, 2), (1, 0, 1)] {
+                let scoring = ScoringScheme::match_mismatch(m, x, GapModel::Linear(g));
+                let expected = global_alignment(&s, &t, &scoring)?;
+                assert_eq!(
+                    banded_global_alignment(&s, &t, &scoring, Band::Adaptive)?,
+                    expected,
+                    "{} {}",
+                    s,
+                    t
+                );
+                assert_eq!(
+                    banded_global_alignment(&s, &t, &scoring, Band::Width(300))?,
+                    expected
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_banded_fitting_alignment1() -> Result<(), Box<dyn Error>> {
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(1));
+        for (s, t) in [
+            ("DISCREPANTLY", "PATENT"),
+            ("ARKANSAS", "SASS"),
+            ("CANT", "CA"),
+        ] {
+            assert_eq!(
+                banded_fitting_alignment(s, t, &blosum62, Band::Adaptive)?,
+                fitting_alignment(s, t, &blosum62)?
+            );
+        }
+        let scoring = ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1));
+        assert_eq!(
+            banded_fitting_alignment("CCAAAAT", "TAAAA", &scoring, Band::Width(0))?,
+            fitting_alignment("CCAAAAT", "TAAAA", &scoring)?
+        );
+        assert_eq!(
+            *banded_fitting_alignment("AAAAGG", "GGAAAA", &scoring, Band::Width(0))?.score(),
+            -2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_banded_fitting_alignment2() -> Result<(), Box<dyn Error>> {
+        // Reads fitted back into windows around where they came from
+        let mut rng = StdRng::seed_from_u64(9);
+        for round in 0..300 {
+            let len = rng.gen_range(0..150);
+            let read = random(&mut rng, len);
+            let t = if round % 4 == 0 {
+                random(&mut rng, read.len())
+            } else {
+                let edits = rng.gen_range(0..=read.len() / 10 + 1);
+                mutate(&mut rng, &read, edits)
+            };
+            let (left, right) = (rng.gen_range(0..40), rng.gen_range(0..40));
+            let s = random(&mut rng, left) + &read + &random(&mut rng, right);
+            for (m, x, g) in [(1, 1, 1), (1, 1, 2), (2, 3, 2), (1, 0, 1)] {
+                let scoring = ScoringScheme::match_mismatch(m, x, GapModel::Linear(g));
+                let expected = fitting_alignment(&s, &t, &scoring)?;
+                assert_eq!(
+                    banded_fitting_alignment(&s, &t, &scoring, Band::Adaptive)?,
+                    expected,
+                    "{} {}",
+                    s,
+                    t
+                );
+                assert_eq!(
+                    banded_fitting_alignment(&s, &t, &scoring, Band::Width(300))?,
+                    expected
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CigarOp {
+    Match,
+    Insertion,
+    Deletion,
+    SoftClip,
+}
+
+impl CigarOp {
+    fn code(&self) -> char {
+        match self {
+            CigarOp::Match => 'M',
+            CigarOp::Insertion => 'I',
+            CigarOp::Deletion => 'D',
+            CigarOp::SoftClip => 'S',
+        }
+    }
+
+    fn consumes_reference(&self) -> bool {
+        matches!(self, CigarOp::Match | CigarOp::Deletion)
+    }
+
+    fn consumes_query(&self) -> bool {
+        matches!(
+            self,
+            CigarOp::Match | CigarOp::Insertion | CigarOp::SoftClip
+        )
+    }
+}
+
+/// Run-length encoded alignment of a query against a reference, as in SAM.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cigar {
+    ops: Vec<(usize, CigarOp)>,
+}
+
+impl Cigar {
+    /// CIGAR of two alignment rows, `reference` first. A gap in the reference
+    /// row is an insertion and a gap in the query row a deletion.
+    pub fn from_alignment(reference: &str, query: &str) -> Result<Self, Box<dyn Error>> {
+        if reference.len() != query.len() {
+            return Err("Alignment rows differ in length".into());
+        }
+        let mut cigar = Cigar::default();
+        for (r, q) in reference.bytes().zip(query.bytes()) {
+            let op = match (r, q) {
+                (b'-', b'-') => return Err("Alignment column holds two gaps".into()),
+                (b'-', _) => CigarOp::Insertion,
+                (_, b'-') => CigarOp::Deletion,
+                _ => CigarOp::Match,
+            };
+            cigar.push(1, op);
+        }
+        Ok(cigar)
+    }
+
+    /// Appends `len` operations, merging with the last run when it matches.
+    pub fn push(&mut self, len: usize, op: CigarOp) {
+        if len == 0 {
+            return;
+        }
+        match self.ops.last_mut() {
+            Some((last_len, last_op)) if *last_op == op => *last_len += len,
+            _ => self.ops.push((len, op)),
+        }
+    }
+
+    /// Copy with `start` and `end` query characters soft-clipped on either side.
+    pub fn clipped(&self, start: usize, end: usize) -> Self {
+        let mut cigar = Cigar::default();
+        cigar.push(start, CigarOp::SoftClip);
+        for &(len, op) in &self.ops {
+            cigar.push(len, op);
+        }
+        cigar.push(end, CigarOp::SoftClip);
+        cigar
+    }
+
+    pub fn ops(&self) -> &[(usize, CigarOp)] {
+        &self.ops
+    }
+
+    pub fn reference_len(&self) -> usize {
+        self.ops
+            .iter()
+            .filter(|(_, op)| op.consumes_reference())
+            .map(|(len, _)| len)
+            .sum()
+    }
+
+    pub fn query_len(&self) -> usize {
+        self.ops
+            .iter()
+            .filter(|(_, op)| op.consumes_query())
+            .map(|(len, _)| len)
+            .sum()
+    }
+}
+
+impl Display for Cigar {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        if self.ops.is_empty() {
+            return write!(f, "*");
+        }
+        for (len, op) in &self.ops {
+            write!(f, "{}{}", len, op.code())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manhattan::alignment::cigar::Cigar;
+    use std::error::Error;
+
+    #[test]
+    fn test_cigar1() -> Result<(), Box<dyn Error>> {
+        let cigar = Cigar::from_alignment("PA--NT", "PATENT")?;
+        assert_eq!(cigar.to_string(), "2M2I2M");
+        assert_eq!(cigar.reference_len(), 4);
+        assert_eq!(cigar.query_len(), 6);
+        let cigar = Cigar::from_alignment("DISCREPANT-", "DISCRE---TE")?.clipped(2, 0);
+        assert_eq!(cigar.to_string(), "2S6M3D1M1I");
+        assert_eq!(cigar.reference_len(), 10);
+        assert_eq!(cigar.query_len(), 10);
+        assert_eq!(Cigar::default().to_string(), "*");
+        assert!(Cigar::from_alignment("A-", "-A").is_ok());
+        assert!(Cigar::from_alignment("A-", "A-").is_err());
+        Ok(())
+    }
+}
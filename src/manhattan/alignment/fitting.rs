@@ -1,8 +1,9 @@
-use crate::manhattan::alignment::alignment::AlignmentResult;
+use crate::manhattan::alignment::affine::affine_alignment;
+use crate::manhattan::alignment::alignment::{AlignmentMode, AlignmentResult};
 use crate::manhattan::alignment::backtrack::backtrack_alignment;
+use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
 use crate::manhattan::direction::Direction;
-use num::Num;
-use std::collections::HashMap;
+use num::{Bounded, Num};
 use std::error::Error;
 use std::fmt::Debug;
 use std::ops::{Mul, Neg};
@@ -10,14 +11,15 @@ use std::ops::{Mul, Neg};
 fn fitting_backtrack<T>(
     s: &str,
     t: &str,
-    blosum: &HashMap<(char, char), T>,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<(Vec<Vec<Direction>>, T), Box<dyn Error>>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
 {
-    let s_chars: Vec<char> = s.chars().collect();
-    let t_chars: Vec<char> = t.chars().collect();
+    scoring.check(s, t)?;
+    let indel_penalty = scoring.indel_penalty()?;
+    let s_chars = s.as_bytes();
+    let t_chars = t.as_bytes();
 
     // Still need backtrack matrix for path reconstruction
     let mut backtrack = vec![vec![Direction::None; t.len() + 1]; s.len() + 1];
@@ -31,18 +33,17 @@ where
         backtrack[0][j] = Direction::Left;
     }
 
-    let mut max_score = T::zero();
+    // Best score in the last column so far; row 0 fits t entirely with gaps
+    let mut max_score = current_row[t.len()];
     let mut max_i = 0;
 
     // Variables to store the previous diagonal score
     let mut prev_diagonal;
-    let mut prev_last;
     let mut temp;
 
     for i in 1..=s.len() {
         backtrack[i][0] = Direction::Start;
         prev_diagonal = current_row[0];
-        prev_last = current_row[t.len()];
         current_row[0] = T::zero(); // Local alignment can start anywhere
 
         for j in 1..=t.len() {
@@ -50,8 +51,7 @@ where
             temp = current_row[j];
 
             // Calculate scores using the single vector
-            let diagonal_score =
-                prev_diagonal + *blosum.get(&(s_chars[i - 1], t_chars[j - 1])).unwrap();
+            let diagonal_score = prev_diagonal + scoring.score(s_chars[i - 1], t_chars[j - 1]);
             let up_score = current_row[j] - indel_penalty;
             let left_score = current_row[j - 1] - indel_penalty;
 
@@ -70,14 +70,14 @@ where
             // Update previous diagonal for next iteration
             prev_diagonal = temp;
         }
-        if current_row[t.len()] > prev_last {
+        if current_row[t.len()] > max_score {
             max_score = current_row[t.len()];
             max_i = i;
         }
     }
 
     // Store the endpoint coordinates in the backtrack matrix
-    if max_score > current_row[t.len()] {
+    if max_i < s.len() {
         current_row[t.len()] = max_score;
         backtrack[s.len()][t.len()] = Direction::Coordinate(max_i, t.len());
     }
@@ -88,51 +88,56 @@ where
 pub fn fitting_alignment<T>(
     s: &str,
     t: &str,
-    blosum: &HashMap<(char, char), T>,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<AlignmentResult<T>, Box<dyn Error>>
 where
-    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
 {
+    if let GapModel::Affine { .. } = scoring.gaps {
+        return affine_alignment(s, t, scoring, AlignmentMode::Fitting);
+    }
+
     // Initialize the score and backtrack matrices
-    let (backtrack, score) = fitting_backtrack(s, t, blosum, indel_penalty)?;
+    let (backtrack, score) = fitting_backtrack(s, t, scoring)?;
 
     // Backtrack to find the alignment
     backtrack_alignment(&backtrack, s, t, score)
 }
 #[cfg(test)]
 mod tests {
-    use crate::manhattan::alignment::alignment::AlignmentResult;
+    use crate::manhattan::alignment::alignment::{AlignmentResult, AlignmentSpan};
     use crate::manhattan::alignment::fitting::fitting_alignment;
-    use crate::manhattan::alignment::local::local_alignment;
-    use crate::utils::blosum_matrix;
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme, SubstitutionMatrix};
     use std::error::Error;
 
     #[test]
     fn test_fitting_alignment1() -> Result<(), Box<dyn Error>> {
-        let blosum = blosum_matrix()?;
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(1));
         assert_eq!(
-            fitting_alignment("DISCREPANTLY", "PATENT", &blosum, 1)?,
-            AlignmentResult::new(20, "PA--NT", "PATENT")
+            fitting_alignment("DISCREPANTLY", "PATENT", &blosum62)?,
+            AlignmentResult::with_span(20, "PA--NT", "PATENT", AlignmentSpan { s: 6..10, t: 0..6 })
         );
         Ok(())
     }
 
     #[test]
     fn test_fitting_alignment2() -> Result<(), Box<dyn Error>> {
-        let blosum = blosum_matrix()?;
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(1));
         assert_eq!(
-            fitting_alignment("ARKANSAS", "SASS", &blosum, 1)?,
-            AlignmentResult::new(11, "SA-S", "SASS")
+            fitting_alignment("ARKANSAS", "SASS", &blosum62)?,
+            AlignmentResult::with_span(11, "SA-S", "SASS", AlignmentSpan { s: 5..8, t: 0..4 })
         );
         Ok(())
     }
 
     #[test]
     fn test_fitting_alignment3() -> Result<(), Box<dyn Error>> {
-        let blosum = blosum_matrix()?;
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(1));
         assert_eq!(
-            fitting_alignment("DISCREPANTLY", "DISCRETE", &blosum, 1)?,
+            fitting_alignment("DISCREPANTLY", "DISCRETE", &blosum62)?,
             AlignmentResult::new(34, "DISCREPANT-", "DISCRE---TE")
         );
         Ok(())
@@ -140,11 +145,23 @@ mod tests {
 
     #[test]
     fn test_fitting_alignment4() -> Result<(), Box<dyn Error>> {
-        let blosum = blosum_matrix()?;
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(1));
         assert_eq!(
-            fitting_alignment("CANT", "CA", &blosum, 1)?,
+            fitting_alignment("CANT", "CA", &blosum62)?,
             AlignmentResult::new(13, "CA", "CA")
         );
         Ok(())
     }
+
+    #[test]
+    fn test_fitting_alignment5() -> Result<(), Box<dyn Error>> {
+        let matrix = SubstitutionMatrix::from_fn(b"ACGT", |a, b| if a == b { 1 } else { -1 });
+        let scoring = ScoringScheme::with_matrix(matrix, GapModel::Linear(2));
+        assert_eq!(
+            fitting_alignment("GGTTACCAGTT", "TACAG", &scoring)?,
+            AlignmentResult::with_span(3, "TACCAG", "TA-CAG", AlignmentSpan { s: 3..9, t: 0..5 })
+        );
+        Ok(())
+    }
 }
@@ -1,7 +1,9 @@
+use crate::manhattan::alignment::affine::affine_gap_alignment;
 use crate::manhattan::alignment::alignment::AlignmentResult;
 use crate::manhattan::alignment::backtrack::backtrack_alignment;
+use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
 use crate::manhattan::direction::Direction;
-use num::Num;
+use num::{Bounded, Num};
 use std::error::Error;
 use std::fmt::Debug;
 use std::ops::{Mul, Neg};
@@ -9,13 +11,13 @@ use std::ops::{Mul, Neg};
 fn global_backtrack<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<(Vec<Vec<Direction>>, T), Box<dyn Error>>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
 {
+    scoring.check(s, t)?;
+    let indel_penalty = scoring.indel_penalty()?;
     let s_chars = s.as_bytes();
     let t_chars = t.as_bytes();
 
@@ -45,11 +47,7 @@ where
             // Store the current score before updating
             temp = current_row[j];
 
-            let match_score = if s_chars[i - 1] == t_chars[j - 1] {
-                match_reward
-            } else {
-                -mismatch_penalty
-            };
+            let match_score = scoring.score(s_chars[i - 1], t_chars[j - 1]);
 
             // Calculate scores using the single vector
             let diagonal_score = prev_diagonal + match_score;
@@ -76,18 +74,22 @@ where
     Ok((backtrack, current_row[t.len()]))
 }
 
+/// Global alignment of `s` and `t`; affine gap penalties go through
+/// `affine_gap_alignment`.
 pub fn global_alignment<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<AlignmentResult<T>, Box<dyn Error>>
 where
-    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
 {
+    if let GapModel::Affine { .. } = scoring.gaps {
+        return affine_gap_alignment(s, t, scoring);
+    }
+
     // Initialize the score and backtrack matrices
-    let (backtrack, score) = global_backtrack(s, t, match_reward, mismatch_penalty, indel_penalty)?;
+    let (backtrack, score) = global_backtrack(s, t, scoring)?;
 
     // Backtrack to find the alignment
     backtrack_alignment(&backtrack, s, t, score)
@@ -96,12 +98,17 @@ where
 mod tests {
     use crate::manhattan::alignment::alignment::AlignmentResult;
     use crate::manhattan::alignment::global::global_alignment;
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme, SubstitutionMatrix};
     use std::error::Error;
 
     #[test]
     fn test_global_alignment1() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("GAGA", "GAT", 1, 1, 2)?,
+            global_alignment(
+                "GAGA",
+                "GAT",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
             AlignmentResult::new(-1, "GAGA", "GA-T")
         );
         Ok(())
@@ -110,7 +117,11 @@ mod tests {
     #[test]
     fn test_global_alignment2() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("ACG", "ACT", 1, 3, 1)?,
+            global_alignment(
+                "ACG",
+                "ACT",
+                &ScoringScheme::match_mismatch(1, 3, GapModel::Linear(1))
+            )?,
             AlignmentResult::new(0, "ACG-", "AC-T")
         );
         Ok(())
@@ -119,7 +130,11 @@ mod tests {
     #[test]
     fn test_global_alignment3() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("AT", "AG", 1, 1, 1)?,
+            global_alignment(
+                "AT",
+                "AG",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1))
+            )?,
             AlignmentResult::new(0, "AT", "AG")
         );
         Ok(())
@@ -128,7 +143,11 @@ mod tests {
     #[test]
     fn test_global_alignment4() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("TCA", "CA", 2, 5, 1)?,
+            global_alignment(
+                "TCA",
+                "CA",
+                &ScoringScheme::match_mismatch(2, 5, GapModel::Linear(1))
+            )?,
             AlignmentResult::new(3, "TCA", "-CA")
         );
         Ok(())
@@ -137,7 +156,11 @@ mod tests {
     #[test]
     fn test_global_alignment5() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("TTTTCCTT", "CC", 1, 10, 1)?,
+            global_alignment(
+                "TTTTCCTT",
+                "CC",
+                &ScoringScheme::match_mismatch(1, 10, GapModel::Linear(1))
+            )?,
             AlignmentResult::new(-4, "TTTTCCTT", "----CC--")
         );
         Ok(())
@@ -146,7 +169,11 @@ mod tests {
     #[test]
     fn test_global_alignment6() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("ACAGATTAG", "T", 2, 3, 2)?,
+            global_alignment(
+                "ACAGATTAG",
+                "T",
+                &ScoringScheme::match_mismatch(2, 3, GapModel::Linear(2))
+            )?,
             AlignmentResult::new(-14, "ACAGATTAG", "------T--")
         );
         Ok(())
@@ -155,9 +182,37 @@ mod tests {
     #[test]
     fn test_global_alignment7() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            global_alignment("G", "ACATACGATG", 3, 1, 2)?,
+            global_alignment(
+                "G",
+                "ACATACGATG",
+                &ScoringScheme::match_mismatch(3, 1, GapModel::Linear(2))
+            )?,
             AlignmentResult::new(-15, "---------G", "ACATACGATG")
         );
         Ok(())
     }
+
+    #[test]
+    fn test_global_alignment8() -> Result<(), Box<dyn Error>> {
+        let blosum62 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::blosum62()?, GapModel::Linear(5));
+        assert_eq!(
+            global_alignment("PLEASANTLY", "MEANLY", &blosum62)?,
+            AlignmentResult::new(8, "PLEASANTLY", "-ME--AN-LY")
+        );
+        let affine = ScoringScheme::match_mismatch(
+            1,
+            3,
+            GapModel::Affine {
+                opening: 2,
+                extension: 1,
+            },
+        );
+        assert_eq!(
+            global_alignment("GA", "GTTA", &affine)?,
+            AlignmentResult::new(-1, "G--A", "GTTA")
+        );
+        assert!(global_alignment("PLEASANTLY", "MEANLY?", &blosum62).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,378 @@
+use crate::manhattan::alignment::alignment::{AlignmentResult, Node};
+use crate::manhattan::alignment::scoring::ScoringScheme;
+use crate::manhattan::direction::Direction;
+use num::Num;
+use std::error::Error;
+use std::fmt::Debug;
+use std::ops::{Mul, Neg, RangeInclusive};
+
+/// The alignment graph of `s` against `t`, scored and tie-broken exactly as
+/// `global_alignment` does: diagonal before left before up.
+struct Grid<'a, T> {
+    s: &'a [u8],
+    t: &'a [u8],
+    scoring: &'a ScoringScheme<T>,
+    indel_penalty: T,
+}
+
+impl<T> Grid<'_, T>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+{
+    fn cell(&self, i: usize, j: usize, diagonal: T, up: T, left: T) -> (T, Direction) {
+        let diagonal_score = diagonal + self.scoring.score(self.s[i - 1], self.t[j - 1]);
+        let up_score = up - self.indel_penalty;
+        let left_score = left - self.indel_penalty;
+        if diagonal_score >= up_score && diagonal_score >= left_score {
+            (diagonal_score, Direction::Diagonal)
+        } else if left_score >= up_score {
+            (left_score, Direction::Left)
+        } else {
+            (up_score, Direction::Up)
+        }
+    }
+
+    /// Scores of the block `rows` x `cols` one column at a time, from the
+    /// scores of its `top` row and `left` column. Each column after the first
+    /// is passed to `visit` with the backtrack directions of its nodes; those
+    /// on the top row point left, as in the full matrix.
+    fn columns(
+        &self,
+        rows: &RangeInclusive<usize>,
+        cols: &RangeInclusive<usize>,
+        top: &[T],
+        left: &[T],
+        mut visit: impl FnMut(usize, &[T], &[Direction]),
+    ) {
+        let (i0, j0) = (*rows.start(), *cols.start());
+        let mut previous = left.to_vec();
+        let mut current = previous.clone();
+        let mut directions = vec![Direction::Left; left.len()];
+        for j in j0 + 1..=*cols.end() {
+            current[0] = top[j - j0];
+            for i in 1..left.len() {
+                (current[i], directions[i]) =
+                    self.cell(i0 + i, j, previous[i - 1], current[i - 1], previous[i]);
+            }
+            visit(j, &current, &directions);
+            std::mem::swap(&mut previous, &mut current);
+        }
+    }
+
+    /// Middle edge of the block: the edge by which the backtrack path from its
+    /// bottom-right node leaves the middle column, as its start row and
+    /// direction, with the scores of the column after the middle one.
+    fn middle_edge(
+        &self,
+        rows: &RangeInclusive<usize>,
+        cols: &RangeInclusive<usize>,
+        top: &[T],
+        left: &[T],
+    ) -> (usize, Direction, Vec<T>) {
+        let i0 = *rows.start();
+        let middle = (cols.start() + cols.end()) / 2;
+        // For each node right of the middle column, the edge by which its
+        // backtrack path leaves that column
+        let mut previous = Vec::new();
+        let mut crossings = vec![(i0, Direction::Left); left.len()];
+        let mut next_column = Vec::new();
+        self.columns(rows, cols, top, left, |j, scores, directions| {
+            if j <= middle {
+                return;
+            }
+            for i in 1..crossings.len() {
+                crossings[i] = match (directions[i], j == middle + 1) {
+                    (Direction::Up, _) => crossings[i - 1],
+                    (Direction::Diagonal, true) => (i0 + i - 1, Direction::Diagonal),
+                    (_, true) => (i0 + i, Direction::Left),
+                    (Direction::Diagonal, false) => previous[i - 1],
+                    (_, false) => previous[i],
+                };
+            }
+            if j == middle + 1 {
+                crossings[0] = (i0, Direction::Left);
+                next_column = scores.to_vec();
+            }
+            previous.clone_from(&crossings);
+        });
+        let (row, direction) = crossings[crossings.len() - 1];
+        (row, direction, next_column)
+    }
+
+    /// Appends the path through the block, from its top-left to its
+    /// bottom-right node, to `path`.
+    fn align(
+        &self,
+        rows: RangeInclusive<usize>,
+        cols: RangeInclusive<usize>,
+        top: &[T],
+        left: &[T],
+        path: &mut Vec<Direction>,
+    ) {
+        let (i0, i1) = (*rows.start(), *rows.end());
+        let (j0, j1) = (*cols.start(), *cols.end());
+        if j0 == j1 {
+            path.extend(std::iter::repeat_n(Direction::Up, i1 - i0));
+            return;
+        }
+        if i0 == i1 {
+            path.extend(std::iter::repeat_n(Direction::Left, j1 - j0));
+            return;
+        }
+
+        let middle = (j0 + j1) / 2;
+        let (row, direction, next_column) = self.middle_edge(&rows, &cols, top, left);
+        self.align(
+            i0..=row,
+            j0..=middle,
+            &top[..=middle - j0],
+            &left[..=row - i0],
+            path,
+        );
+        path.push(direction);
+
+        // The path right of the middle edge never rises above its end, so the
+        // block after it starts there
+        let next_row = if direction == Direction::Diagonal {
+            row + 1
+        } else {
+            row
+        };
+        let mut next_top = vec![next_column[next_row - i0]];
+        self.columns(
+            &(i0..=next_row),
+            &cols,
+            top,
+            &left[..=next_row - i0],
+            |j, scores, _| {
+                if j > middle + 1 {
+                    next_top.push(scores[next_row - i0]);
+                }
+            },
+        );
+        self.align(
+            next_row..=i1,
+            middle + 1..=j1,
+            &next_top,
+            &next_column[next_row - i0..],
+            path,
+        );
+    }
+
+    fn borders(&self) -> (Vec<T>, Vec<T>) {
+        let border = |len: usize| {
+            let mut scores = vec![T::zero(); len + 1];
+            for k in 1..=len {
+                scores[k] = scores[k - 1] - self.indel_penalty;
+            }
+            scores
+        };
+        (border(self.t.len()), border(self.s.len()))
+    }
+}
+
+/// Global alignment in space linear in the input lengths, by Hirschberg's
+/// divide and conquer: the middle edge of the grid splits it into two blocks
+/// that are aligned recursively. Returns the same alignment as
+/// `global_alignment` for the same scores.
+pub fn linear_space_alignment<T>(
+    s: &str,
+    t: &str,
+    scoring: &ScoringScheme<T>,
+) -> Result<AlignmentResult<T>, Box<dyn Error>>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+{
+    scoring.check(s, t)?;
+    let grid = Grid {
+        s: s.as_bytes(),
+        t: t.as_bytes(),
+        scoring,
+        indel_penalty: scoring.indel_penalty()?,
+    };
+    let (top, left) = grid.borders();
+    let mut score = left[s.len()];
+    grid.columns(
+        &(0..=s.len()),
+        &(0..=t.len()),
+        &top,
+        &left,
+        |_, scores, _| {
+            score = scores[s.len()];
+        },
+    );
+
+    let mut path = Vec::with_capacity(s.len() + t.len());
+    grid.align(0..=s.len(), 0..=t.len(), &top, &left, &mut path);
+    let mut align1 = String::with_capacity(path.len());
+    let mut align2 = String::with_capacity(path.len());
+    let (mut i, mut j) = (0, 0);
+    for direction in path {
+        match direction {
+            Direction::Diagonal => {
+                align1.push(grid.s[i] as char);
+                align2.push(grid.t[j] as char);
+                i += 1;
+                j += 1;
+            }
+            Direction::Left => {
+                align1.push('-');
+                align2.push(grid.t[j] as char);
+                j += 1;
+            }
+            _ => {
+                align1.push(grid.s[i] as char);
+                align2.push('-');
+                i += 1;
+            }
+        }
+    }
+    Ok(AlignmentResult::new(score, &align1, &align2))
+}
+
+/// Middle edge of the alignment graph of `s` against `t`: the edge by which
+/// the path of `global_alignment` leaves the middle column `t.len() / 2`, as
+/// its two nodes.
+pub fn middle_edge<T>(
+    s: &str,
+    t: &str,
+    scoring: &ScoringScheme<T>,
+) -> Result<(Node, Node), Box<dyn Error>>
+where
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+{
+    if t.is_empty() {
+        return Err("The alignment graph has no edge leaving its middle column".into());
+    }
+    scoring.check(s, t)?;
+    let grid = Grid {
+        s: s.as_bytes(),
+        t: t.as_bytes(),
+        scoring,
+        indel_penalty: scoring.indel_penalty()?,
+    };
+    let (top, left) = grid.borders();
+    let middle = t.len() / 2;
+    let (row, direction, _) = grid.middle_edge(&(0..=s.len()), &(0..=t.len()), &top, &left);
+    let next_row = if direction == Direction::Diagonal {
+        row + 1
+    } else {
+        row
+    };
+    Ok(((row, middle), (next_row, middle + 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manhattan::alignment::alignment::AlignmentResult;
+    use crate::manhattan::alignment::global::global_alignment;
+    use crate::manhattan::alignment::hirschberg::{linear_space_alignment, middle_edge};
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::error::Error;
+
+    #[test]
+    fn test_linear_space_alignment1() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            linear_space_alignment(
+                "GAGA",
+                "GAT",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
+            AlignmentResult::new(-1, "GAGA", "GA-T")
+        );
+        assert_eq!(
+            linear_space_alignment(
+                "TTTTCCTT",
+                "CC",
+                &ScoringScheme::match_mismatch(1, 10, GapModel::Linear(1))
+            )?,
+            AlignmentResult::new(-4, "TTTTCCTT", "----CC--")
+        );
+        assert_eq!(
+            linear_space_alignment(
+                "",
+                "ACG",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
+            AlignmentResult::new(-6, "---", "ACG")
+        );
+        assert_eq!(
+            linear_space_alignment(
+                "ACG",
+                "",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
+            AlignmentResult::new(-6, "ACG", "---")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_linear_space_alignment2() -> Result<(), Box<dyn Error>> {
+        // Ties are broken as in the full backtrack matrix
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..500 {
+            let mut random = |len: usize| {
+                (0..rng.gen_range(0..=len))
+                    .map(|_| b"ACGT"[rng.gen_range(0..4)] as char)
+                    .collect::<String>()
+            };
+            let (s, t) = (random(30), random(30));
+            for (m, x, g) in [(1, 1, 1), (1, 1, 2), (2, 3, 2), (1, 0, 1), (3, 1, 2)] {
+                assert_eq!(
+                    linear_space_alignment(
+                        &s,
+                        &t,
+                        &ScoringScheme::match_mismatch(m, x, GapModel::Linear(g))
+                    )?,
+                    global_alignment(
+                        &s,
+                        &t,
+                        &ScoringScheme::match_mismatch(m, x, GapModel::Linear(g))
+                    )?,
+                    "{} {}",
+                    s,
+                    t
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_middle_edge1() -> Result<(), Box<dyn Error>> {
+        assert_eq!(
+            middle_edge(
+                "GAGA",
+                "GAT",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
+            ((1, 1), (2, 2))
+        );
+        assert_eq!(
+            middle_edge(
+                "TTTTCCTT",
+                "CC",
+                &ScoringScheme::match_mismatch(1, 10, GapModel::Linear(1))
+            )?,
+            ((5, 1), (6, 2))
+        );
+        assert_eq!(
+            middle_edge(
+                "AC",
+                "T",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1))
+            )?,
+            ((1, 0), (2, 1))
+        );
+        assert!(middle_edge(
+            "AC",
+            "",
+            &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1))
+        )
+        .is_err());
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
-use crate::manhattan::alignment::alignment::AlignmentResult;
+use crate::manhattan::alignment::affine::affine_alignment;
+use crate::manhattan::alignment::alignment::{AlignmentMode, AlignmentResult};
 use crate::manhattan::alignment::backtrack::backtrack_alignment;
+use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
 use crate::manhattan::direction::Direction;
-use num::Num;
+use num::{Bounded, Num};
 use std::error::Error;
 use std::fmt::Debug;
 use std::ops::{Mul, Neg};
@@ -9,13 +11,13 @@ use std::ops::{Mul, Neg};
 fn local_backtrack<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<(Vec<Vec<Direction>>, T), Box<dyn Error>>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
 {
+    scoring.check(s, t)?;
+    let indel_penalty = scoring.indel_penalty()?;
     let s_chars = s.as_bytes();
     let t_chars = t.as_bytes();
 
@@ -47,11 +49,7 @@ where
             // Store the current score before updating
             temp = current_row[j];
 
-            let match_score = if s_chars[i - 1] == t_chars[j - 1] {
-                match_reward
-            } else {
-                -mismatch_penalty
-            };
+            let match_score = scoring.score(s_chars[i - 1], t_chars[j - 1]);
 
             // Calculate scores using the single vector
             let diagonal_score = prev_diagonal + match_score;
@@ -104,29 +102,36 @@ where
 pub fn local_alignment<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<AlignmentResult<T>, Box<dyn Error>>
 where
-    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
 {
+    if let GapModel::Affine { .. } = scoring.gaps {
+        return affine_alignment(s, t, scoring, AlignmentMode::Local);
+    }
+
     // Initialize the score and backtrack matrices
-    let (backtrack, score) = local_backtrack(s, t, match_reward, mismatch_penalty, indel_penalty)?;
+    let (backtrack, score) = local_backtrack(s, t, scoring)?;
 
     // Backtrack to find the alignment
     backtrack_alignment(&backtrack, s, t, score)
 }
 #[cfg(test)]
 mod tests {
-    use crate::manhattan::alignment::alignment::AlignmentResult;
+    use crate::manhattan::alignment::alignment::{AlignmentResult, AlignmentSpan};
     use crate::manhattan::alignment::local::local_alignment;
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme, SubstitutionMatrix};
     use std::error::Error;
 
     #[test]
     fn test_local_alignment1() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            local_alignment("GAGA", "GAT", 1, 1, 2)?,
+            local_alignment(
+                "GAGA",
+                "GAT",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
             AlignmentResult::new(2, "GA", "GA")
         );
         Ok(())
@@ -135,7 +140,11 @@ mod tests {
     #[test]
     fn test_local_alignment2() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            local_alignment("AGC", "ATC", 3, 3, 1)?,
+            local_alignment(
+                "AGC",
+                "ATC",
+                &ScoringScheme::match_mismatch(3, 3, GapModel::Linear(1))
+            )?,
             AlignmentResult::new(4, "AG-C", "A-TC")
         );
         Ok(())
@@ -144,7 +153,11 @@ mod tests {
     #[test]
     fn test_local_alignment3() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            local_alignment("AT", "AG", 1, 1, 1)?,
+            local_alignment(
+                "AT",
+                "AG",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1))
+            )?,
             AlignmentResult::new(1, "A", "A")
         );
         Ok(())
@@ -153,8 +166,12 @@ mod tests {
     #[test]
     fn test_local_alignment4() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            local_alignment("TAACG", "ACGTG", 1, 1, 1)?,
-            AlignmentResult::new(3, "ACG", "ACG")
+            local_alignment(
+                "TAACG",
+                "ACGTG",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(3, "ACG", "ACG", AlignmentSpan { s: 2..5, t: 0..3 })
         );
         Ok(())
     }
@@ -162,8 +179,12 @@ mod tests {
     #[test]
     fn test_local_alignment5() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            local_alignment("CAGAGATGGCCG", "ACG", 3, 2, 1)?,
-            AlignmentResult::new(6, "CG", "CG")
+            local_alignment(
+                "CAGAGATGGCCG",
+                "ACG",
+                &ScoringScheme::match_mismatch(3, 2, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(6, "CG", "CG", AlignmentSpan { s: 10..12, t: 1..3 })
         );
         Ok(())
     }
@@ -171,8 +192,23 @@ mod tests {
     #[test]
     fn test_local_alignment6() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            local_alignment("CTT", "AGCATAAAGCATT", 2, 3, 1)?,
-            AlignmentResult::new(5, "C-TT", "CATT")
+            local_alignment(
+                "CTT",
+                "AGCATAAAGCATT",
+                &ScoringScheme::match_mismatch(2, 3, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(5, "C-TT", "CATT", AlignmentSpan { s: 0..3, t: 9..13 })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_local_alignment7() -> Result<(), Box<dyn Error>> {
+        let pam250 =
+            ScoringScheme::with_matrix(SubstitutionMatrix::builtin("PAM250")?, GapModel::Linear(5));
+        assert_eq!(
+            local_alignment("MEANLY", "PENALTY", &pam250)?,
+            AlignmentResult::with_span(15, "EANL-Y", "ENALTY", AlignmentSpan { s: 1..6, t: 1..7 })
         );
         Ok(())
     }
@@ -2,7 +2,21 @@ mod affine;
 mod affine_classes;
 mod alignment;
 mod backtrack;
+mod banded;
+mod cigar;
 mod fitting;
 mod global;
+mod hirschberg;
 mod local;
 mod overlap;
+mod scoring;
+
+pub use alignment::AlignmentMode;
+pub use banded::{banded_fitting_alignment, banded_global_alignment, Band};
+pub use cigar::{Cigar, CigarOp};
+pub use fitting::fitting_alignment;
+pub use global::global_alignment;
+pub use hirschberg::linear_space_alignment;
+pub use local::local_alignment;
+pub use overlap::overlap_alignment;
+pub use scoring::{GapModel, ScoringScheme, SubstitutionMatrix};
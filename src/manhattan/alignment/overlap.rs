@@ -1,7 +1,9 @@
-use crate::manhattan::alignment::alignment::AlignmentResult;
+use crate::manhattan::alignment::affine::affine_alignment;
+use crate::manhattan::alignment::alignment::{AlignmentMode, AlignmentResult};
 use crate::manhattan::alignment::backtrack::backtrack_alignment;
+use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
 use crate::manhattan::direction::Direction;
-use num::Num;
+use num::{Bounded, Num};
 use std::error::Error;
 use std::fmt::Debug;
 use std::ops::{Mul, Neg};
@@ -9,13 +11,13 @@ use std::ops::{Mul, Neg};
 fn overlap_backtrack<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<(Vec<Vec<Direction>>, T), Box<dyn Error>>
 where
     T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
 {
+    scoring.check(s, t)?;
+    let indel_penalty = scoring.indel_penalty()?;
     let s_chars = s.as_bytes();
     let t_chars = t.as_bytes();
 
@@ -48,11 +50,7 @@ where
             temp = current_row[j];
 
             // Calculate scores using the single vector
-            let match_score = if s_chars[i - 1] == t_chars[j - 1] {
-                match_reward
-            } else {
-                -mismatch_penalty
-            };
+            let match_score = scoring.score(s_chars[i - 1], t_chars[j - 1]);
 
             // Calculate scores using the single vector
             let diagonal_score = prev_diagonal + match_score;
@@ -95,31 +93,37 @@ where
 pub fn overlap_alignment<T>(
     s: &str,
     t: &str,
-    match_reward: T,
-    mismatch_penalty: T,
-    indel_penalty: T,
+    scoring: &ScoringScheme<T>,
 ) -> Result<AlignmentResult<T>, Box<dyn Error>>
 where
-    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T>,
+    T: Num + Debug + Copy + Ord + Mul + Neg<Output = T> + Bounded,
 {
+    if let GapModel::Affine { .. } = scoring.gaps {
+        return affine_alignment(s, t, scoring, AlignmentMode::Overlap);
+    }
+
     // Initialize the score and backtrack matrices
-    let (backtrack, score) =
-        overlap_backtrack(s, t, match_reward, mismatch_penalty, indel_penalty)?;
+    let (backtrack, score) = overlap_backtrack(s, t, scoring)?;
 
     // Backtrack to find the alignment
     backtrack_alignment(&backtrack, s, t, score)
 }
 #[cfg(test)]
 mod tests {
-    use crate::manhattan::alignment::alignment::AlignmentResult;
+    use crate::manhattan::alignment::alignment::{AlignmentResult, AlignmentSpan};
     use crate::manhattan::alignment::overlap::overlap_alignment;
+    use crate::manhattan::alignment::scoring::{GapModel, ScoringScheme};
     use std::error::Error;
 
     #[test]
     fn test_overlap_alignment1() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("GAGA", "GAT", 1, 1, 2)?,
-            AlignmentResult::new(2, "GA", "GA")
+            overlap_alignment(
+                "GAGA",
+                "GAT",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(2))
+            )?,
+            AlignmentResult::with_span(2, "GA", "GA", AlignmentSpan { s: 2..4, t: 0..2 })
         );
         Ok(())
     }
@@ -127,8 +131,12 @@ mod tests {
     #[test]
     fn test_overlap_alignment2() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("CCAT", "AT", 1, 1, 1)?,
-            AlignmentResult::new(2, "AT", "AT")
+            overlap_alignment(
+                "CCAT",
+                "AT",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(2, "AT", "AT", AlignmentSpan { s: 2..4, t: 0..2 })
         );
         Ok(())
     }
@@ -136,8 +144,12 @@ mod tests {
     #[test]
     fn test_overlap_alignment3() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("GAT", "CAT", 1, 5, 1)?,
-            AlignmentResult::new(1, "-AT", "CAT")
+            overlap_alignment(
+                "GAT",
+                "CAT",
+                &ScoringScheme::match_mismatch(1, 5, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(1, "-AT", "CAT", AlignmentSpan { s: 1..3, t: 0..3 })
         );
         Ok(())
     }
@@ -145,8 +157,12 @@ mod tests {
     #[test]
     fn test_overlap_alignment4() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("ATCACT", "AT", 1, 5, 1)?,
-            AlignmentResult::new(1, "ACT", "A-T")
+            overlap_alignment(
+                "ATCACT",
+                "AT",
+                &ScoringScheme::match_mismatch(1, 5, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(1, "ACT", "A-T", AlignmentSpan { s: 3..6, t: 0..2 })
         );
         Ok(())
     }
@@ -154,8 +170,12 @@ mod tests {
     #[test]
     fn test_overlap_alignment5() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("ATCACT", "ATG", 1, 1, 5)?,
-            AlignmentResult::new(0, "", "")
+            overlap_alignment(
+                "ATCACT",
+                "ATG",
+                &ScoringScheme::match_mismatch(1, 1, GapModel::Linear(5))
+            )?,
+            AlignmentResult::with_span(0, "", "", AlignmentSpan { s: 6..6, t: 0..0 })
         );
         Ok(())
     }
@@ -163,8 +183,12 @@ mod tests {
     #[test]
     fn test_overlap_alignment6() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("CAGAGATGGCCG", "ACG", 3, 2, 1)?,
-            AlignmentResult::new(5, "-CG", "ACG")
+            overlap_alignment(
+                "CAGAGATGGCCG",
+                "ACG",
+                &ScoringScheme::match_mismatch(3, 2, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(5, "-CG", "ACG", AlignmentSpan { s: 10..12, t: 0..3 })
         );
         Ok(())
     }
@@ -172,8 +196,12 @@ mod tests {
     #[test]
     fn test_overlap_alignment7() -> Result<(), Box<dyn Error>> {
         assert_eq!(
-            overlap_alignment("CTT", "AGCATAAAGCATT", 2, 3, 1)?,
-            AlignmentResult::new(0, "", "")
+            overlap_alignment(
+                "CTT",
+                "AGCATAAAGCATT",
+                &ScoringScheme::match_mismatch(2, 3, GapModel::Linear(1))
+            )?,
+            AlignmentResult::with_span(0, "", "", AlignmentSpan { s: 3..3, t: 0..0 })
         );
         Ok(())
     }
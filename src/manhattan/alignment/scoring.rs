@@ -0,0 +1,322 @@
+use num::Num;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
+
+const BLOSUM45: &str = include_str!("matrices/BLOSUM45");
+const BLOSUM62: &str = include_str!("matrices/BLOSUM62");
+const BLOSUM80: &str = include_str!("matrices/BLOSUM80");
+const PAM250: &str = include_str!("matrices/PAM250");
+
+/// Names of the substitution matrices compiled into the binary.
+pub const BUILTIN_MATRICES: [&str; 4] = ["BLOSUM45", "BLOSUM62", "BLOSUM80", "PAM250"];
+
+/// Scores of substituting one symbol for another. Symbols are looked up
+/// case-insensitively.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubstitutionMatrix<T> {
+    symbols: Vec<u8>,
+    /// Row of each byte in `scores`, or `usize::MAX` for symbols not scored.
+    index: Vec<usize>,
+    scores: Vec<T>,
+}
+
+impl<T: Copy> SubstitutionMatrix<T> {
+    /// Matrix over `symbols` scoring each pair with `score`.
+    pub fn from_fn(symbols: &[u8], score: impl Fn(u8, u8) -> T) -> Self {
+        let mut index = vec![usize::MAX; 256];
+        for (i, &symbol) in symbols.iter().enumerate() {
+            index[symbol.to_ascii_uppercase() as usize] = i;
+            index[symbol.to_ascii_lowercase() as usize] = i;
+        }
+        let scores = symbols
+            .iter()
+            .flat_map(|&a| symbols.iter().map(move |&b| (a, b)))
+            .map(|(a, b)| score(a, b))
+            .collect();
+        SubstitutionMatrix {
+            symbols: symbols.to_vec(),
+            index,
+            scores,
+        }
+    }
+
+    pub fn symbols(&self) -> &[u8] {
+        &self.symbols
+    }
+
+    pub fn contains(&self, symbol: u8) -> bool {
+        self.index[symbol as usize] != usize::MAX
+    }
+
+    pub fn score(&self, a: u8, b: u8) -> Option<T> {
+        let (i, j) = (self.index[a as usize], self.index[b as usize]);
+        if i == usize::MAX || j == usize::MAX {
+            return None;
+        }
+        Some(self.scores[i * self.symbols.len() + j])
+    }
+}
+
+impl<T> SubstitutionMatrix<T>
+where
+    T: Copy + FromStr,
+{
+    /// Parses a matrix in the NCBI format: `#` comments, a header line of
+    /// column symbols, then one row per symbol starting with that symbol.
+    pub fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let mut lines = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let header = lines.next().ok_or("Substitution matrix has no header")?;
+        let symbols = header
+            .split_whitespace()
+            .map(|s| match s.as_bytes() {
+                [symbol] => Ok(*symbol),
+                _ => Err(format!("Invalid matrix symbol {}", s)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut rows = vec![None; symbols.len()];
+        for line in lines {
+            let mut fields = line.split_whitespace();
+            let row = fields.next().unwrap_or_default();
+            let i = symbols
+                .iter()
+                .position(|s| row.as_bytes() == [*s])
+                .ok_or(format!("Matrix row {} is not in the header", row))?;
+            let scores = fields
+                .map(|f| {
+                    f.parse::<T>()
+                        .map_err(|_| format!("Invalid score {} in matrix row {}", f, row))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if scores.len() != symbols.len() {
+                return Err(format!(
+                    "Matrix row {} has {} scores, expected {}",
+                    row,
+                    scores.len(),
+                    symbols.len()
+                )
+                .into());
+            }
+            if rows[i].replace(scores).is_some() {
+                return Err(format!("Matrix row {} appears twice", row).into());
+            }
+        }
+        let rows = rows
+            .into_iter()
+            .zip(&symbols)
+            .map(|(row, s)| row.ok_or(format!("Matrix row {} is missing", *s as char)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let position = |s: u8| symbols.iter().position(|&x| x == s).unwrap();
+        Ok(Self::from_fn(&symbols, |a, b| {
+            rows[position(a)][position(b)]
+        }))
+    }
+
+    pub fn read_file(file: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&read_to_string(file)?)
+    }
+
+    /// One of the [`BUILTIN_MATRICES`], by case-insensitive name.
+    pub fn builtin(name: &str) -> Result<Self, Box<dyn Error>> {
+        let content = match name.to_ascii_uppercase().as_str() {
+            "BLOSUM45" => BLOSUM45,
+            "BLOSUM62" => BLOSUM62,
+            "BLOSUM80" => BLOSUM80,
+            "PAM250" => PAM250,
+            _ => {
+                return Err(format!(
+                    "Unknown substitution matrix {}, expected one of {}",
+                    name,
+                    BUILTIN_MATRICES.join(", ")
+                )
+                .into())
+            }
+        };
+        Self::parse(content)
+    }
+
+    pub fn blosum62() -> Result<Self, Box<dyn Error>> {
+        Self::builtin("BLOSUM62")
+    }
+}
+
+/// How aligned symbols score.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Substitution<T> {
+    MatchMismatch {
+        match_reward: T,
+        mismatch_penalty: T,
+    },
+    Matrix(SubstitutionMatrix<T>),
+}
+
+/// Penalty of a gap: `Linear` charges the same for every position, `Affine`
+/// charges `opening` for its first position and `extension` for each further
+/// one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GapModel<T> {
+    Linear(T),
+    Affine { opening: T, extension: T },
+}
+
+/// Substitution scores and gap penalties of an alignment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoringScheme<T> {
+    pub substitution: Substitution<T>,
+    pub gaps: GapModel<T>,
+}
+
+impl<T> ScoringScheme<T>
+where
+    T: Num + Debug + Copy,
+{
+    pub fn new(substitution: Substitution<T>, gaps: GapModel<T>) -> Self {
+        ScoringScheme { substitution, gaps }
+    }
+
+    pub fn match_mismatch(match_reward: T, mismatch_penalty: T, gaps: GapModel<T>) -> Self {
+        Self::new(
+            Substitution::MatchMismatch {
+                match_reward,
+                mismatch_penalty,
+            },
+            gaps,
+        )
+    }
+
+    pub fn with_matrix(matrix: SubstitutionMatrix<T>, gaps: GapModel<T>) -> Self {
+        Self::new(Substitution::Matrix(matrix), gaps)
+    }
+
+    /// Fails when a matrix does not score some symbol of `s` or `t`, so that
+    /// [`ScoringScheme::score`] can be used on them afterwards.
+    pub fn check(&self, s: &str, t: &str) -> Result<(), Box<dyn Error>> {
+        if let Substitution::Matrix(matrix) = &self.substitution {
+            if let Some(symbol) = s.bytes().chain(t.bytes()).find(|&b| !matrix.contains(b)) {
+                return Err(format!(
+                    "Symbol {} is not in the substitution matrix",
+                    symbol as char
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Score of aligning `a` with `b`; both must have passed
+    /// [`ScoringScheme::check`].
+    pub fn score(&self, a: u8, b: u8) -> T {
+        match &self.substitution {
+            Substitution::MatchMismatch {
+                match_reward,
+                mismatch_penalty,
+            } => {
+                if a == b {
+                    *match_reward
+                } else {
+                    T::zero() - *mismatch_penalty
+                }
+            }
+            Substitution::Matrix(matrix) => matrix
+                .score(a, b)
+                .expect("Symbols are checked before scoring"),
+        }
+    }
+
+    /// Penalty of each gap position, for the alignments that only support
+    /// linear gaps.
+    pub fn indel_penalty(&self) -> Result<T, Box<dyn Error>> {
+        match self.gaps {
+            GapModel::Linear(penalty) => Ok(penalty),
+            GapModel::Affine { .. } => {
+                Err("This alignment supports only linear gap penalties".into())
+            }
+        }
+    }
+
+    /// Opening and extension penalties, equal for linear gaps.
+    pub fn affine_penalties(&self) -> (T, T) {
+        match self.gaps {
+            GapModel::Linear(penalty) => (penalty, penalty),
+            GapModel::Affine { opening, extension } => (opening, extension),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manhattan::alignment::scoring::{
+        GapModel, ScoringScheme, SubstitutionMatrix, BUILTIN_MATRICES,
+    };
+    use std::error::Error;
+
+    #[test]
+    fn test_substitution_matrix1() -> Result<(), Box<dyn Error>> {
+        for name in BUILTIN_MATRICES {
+            let matrix = SubstitutionMatrix::<i32>::builtin(name)?;
+            assert_eq!(matrix.symbols(), b"ARNDCQEGHILKMFPSTWYVBZX*");
+            for &a in matrix.symbols() {
+                for &b in matrix.symbols() {
+                    assert_eq!(matrix.score(a, b), matrix.score(b, a));
+                }
+            }
+        }
+        let blosum62 = SubstitutionMatrix::<i32>::blosum62()?;
+        assert_eq!(blosum62.score(b'W', b'W'), Some(11));
+        assert_eq!(blosum62.score(b'w', b'F'), Some(1));
+        assert_eq!(blosum62.score(b'A', b'J'), None);
+        assert_eq!(
+            SubstitutionMatrix::<i32>::builtin("pam250")?.score(b'C', b'C'),
+            Some(12)
+        );
+        assert!(SubstitutionMatrix::<i32>::builtin("PAM30").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_substitution_matrix2() -> Result<(), Box<dyn Error>> {
+        let content = "# comment\n   A  C\nC -1  2\nA  3 -1\n";
+        let matrix = SubstitutionMatrix::<i64>::parse(content)?;
+        assert_eq!(matrix.score(b'A', b'A'), Some(3));
+        assert_eq!(matrix.score(b'C', b'A'), Some(-1));
+        assert_eq!(matrix.score(b'C', b'C'), Some(2));
+
+        assert!(SubstitutionMatrix::<i32>::parse("A C\nA 1 0\n").is_err());
+        assert!(SubstitutionMatrix::<i32>::parse("A C\nA 1 0\nC 0\n").is_err());
+        assert!(SubstitutionMatrix::<i32>::parse("A C\nA 1 0\nC 0 x\n").is_err());
+        assert!(SubstitutionMatrix::<i32>::parse("A C\nA 1 0\nG 0 1\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scoring_scheme1() -> Result<(), Box<dyn Error>> {
+        let simple = ScoringScheme::match_mismatch(2, 3, GapModel::Linear(1));
+        assert_eq!(
+            (simple.score(b'A', b'A'), simple.score(b'A', b'C')),
+            (2, -3)
+        );
+        assert_eq!(simple.indel_penalty()?, 1);
+        assert_eq!(simple.affine_penalties(), (1, 1));
+        assert!(simple.check("ACGT", "XYZ").is_ok());
+
+        let affine = ScoringScheme::with_matrix(
+            SubstitutionMatrix::blosum62()?,
+            GapModel::Affine {
+                opening: 11,
+                extension: 1,
+            },
+        );
+        assert_eq!(affine.score(b'P', b'E'), -1);
+        assert_eq!(affine.affine_penalties(), (11, 1));
+        assert!(affine.indel_penalty().is_err());
+        assert!(affine.check("PLEASANTLY", "MEANLY").is_ok());
+        assert!(affine.check("PLEASANTLY", "MEAN1").is_err());
+        Ok(())
+    }
+}
@@ -4,3 +4,9 @@ mod common;
 mod direction;
 mod distance;
 mod path;
+
+pub use alignment::{
+    banded_fitting_alignment, banded_global_alignment, fitting_alignment, global_alignment,
+    linear_space_alignment, local_alignment, overlap_alignment, AlignmentMode, Band, Cigar,
+    CigarOp, GapModel, ScoringScheme, SubstitutionMatrix,
+};
@@ -0,0 +1,77 @@
+use crate::bwt::Mappability;
+use crate::utils::{print_hms, Alphabet, Fasta};
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct MappabilityArgs {
+    #[arg(long, required = true, value_name = "genome_file")]
+    genome_file: String,
+
+    /// Read length: the k-mer starting at each position is counted.
+    #[arg(long, required = true, value_parser = value_parser!(usize))]
+    k: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "0")]
+    mismatches: usize,
+
+    /// Also count the hits of each k-mer's reverse complement.
+    #[arg(long, required = false)]
+    both_strands: bool,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "ALPHABET",
+        default_value = "iupac"
+    )]
+    alphabet: String,
+
+    /// Write the bedGraph track here instead of to standard output.
+    #[arg(long, required = false, value_name = "BEDGRAPH")]
+    output: Option<String>,
+}
+
+impl MappabilityArgs {
+    pub fn get_genome_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.genome_file.to_owned())
+    }
+
+    pub fn get_alphabet(&self) -> Result<Alphabet, Box<dyn Error>> {
+        Alphabet::named(&self.alphabet)
+    }
+
+    pub fn get_output(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.output.to_owned())
+    }
+}
+
+pub fn run_mappability(args: MappabilityArgs) -> Result<(), Box<dyn Error>> {
+    let records = Fasta::read_file(args.get_genome_file()?)?
+        .iter()
+        .map(|f| f.upper())
+        .collect::<Vec<_>>();
+    let alphabet = args.get_alphabet()?;
+
+    let start = Instant::now();
+    let track = Mappability::from_records(
+        &records,
+        &alphabet,
+        args.k,
+        args.mismatches,
+        args.both_strands,
+    )?;
+    let mut writer: Box<dyn Write> = match args.get_output()? {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    track.write_bedgraph(&mut writer)?;
+    if args.output.is_some() {
+        print_hms(&start);
+    }
+    Ok(())
+}
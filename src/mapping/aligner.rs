@@ -0,0 +1,375 @@
+use crate::bwt::{FMIndex, Strand};
+use crate::manhattan::{
+    banded_fitting_alignment, local_alignment, Band, Cigar, GapModel, ScoringScheme,
+    SubstitutionMatrix,
+};
+use crate::utils::{iupac_complement, Fasta};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::str::FromStr;
+
+/// How a candidate window is aligned: `Fitting` places the whole read inside
+/// the window, `Local` may soft-clip both ends of it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Extension {
+    Fitting,
+    Local,
+}
+
+impl FromStr for Extension {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fitting" => Ok(Extension::Fitting),
+            "local" => Ok(Extension::Local),
+            _ => Err(format!("Unknown extension mode {}", s).into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AlignerOptions {
+    pub seed_len: usize,
+    /// Distance between the starts of consecutive seeds.
+    pub seed_step: usize,
+    /// Seeds with more hits than this are too repetitive to vote.
+    pub max_seed_hits: usize,
+    /// Candidate windows extended per read, best voted first.
+    pub max_candidates: usize,
+    /// Reference characters added on both sides of a window to absorb indels.
+    pub padding: usize,
+    pub extension: Extension,
+    pub match_reward: i32,
+    pub mismatch_penalty: i32,
+    pub indel_penalty: i32,
+}
+
+impl Default for AlignerOptions {
+    fn default() -> Self {
+        AlignerOptions {
+            seed_len: 20,
+            seed_step: 10,
+            max_seed_hits: 100,
+            max_candidates: 5,
+            padding: 10,
+            extension: Extension::Fitting,
+            match_reward: 1,
+            mismatch_penalty: 1,
+            indel_penalty: 2,
+        }
+    }
+}
+
+/// Best placement of a read. `offset` is the 0-based start of the alignment
+/// on the forward strand of the record; for reverse-strand hits the CIGAR
+/// describes the reverse complement of the read, as in SAM.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReadAlignment {
+    pub record: usize,
+    pub offset: usize,
+    pub strand: Strand,
+    pub score: i32,
+    pub cigar: Cigar,
+    /// Phred-scaled confidence that this is the right placement, from the
+    /// score gap to the next best distinct placement.
+    pub mapq: u8,
+}
+
+/// Seed-and-extend aligner: exact seeds from the FM-index vote for diagonals,
+/// and the best voted windows of the reference are aligned with dynamic
+/// programming.
+pub struct ReadAligner<'a> {
+    index: &'a FMIndex,
+    reference: &'a [Fasta],
+    options: AlignerOptions,
+    scoring: ScoringScheme<i32>,
+}
+
+impl<'a> ReadAligner<'a> {
+    /// `reference` must hold the records the index was built from, in order.
+    pub fn new(
+        index: &'a FMIndex,
+        reference: &'a [Fasta],
+        options: AlignerOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let records = index.records();
+        if records.len() != reference.len()
+            || (0..records.len()).any(|r| records.record_len(r) != reference[r].len())
+        {
+            return Err("Reference records do not match the index".into());
+        }
+        if options.seed_len == 0 || options.seed_step == 0 {
+            return Err("Seed length and step must be positive".into());
+        }
+
+        // N and the other ambiguity codes never score as a match
+        let matrix = SubstitutionMatrix::from_fn(b"ABCDGHKMNRSTVWY", |a, b| {
+            if a == b && b"ACGT".contains(&a) {
+                options.match_reward
+            } else {
+                -options.mismatch_penalty
+            }
+        });
+        let scoring = ScoringScheme::with_matrix(matrix, GapModel::Linear(options.indel_penalty));
+        Ok(ReadAligner {
+            index,
+            reference,
+            options,
+            scoring,
+        })
+    }
+
+    /// Best alignment of `read` over both strands, or `None` when no seed hits
+    /// the reference.
+    pub fn align(&self, read: &str) -> Result<Option<ReadAlignment>, Box<dyn Error>> {
+        let reverse = iupac_complement(read)?;
+        let mut best: Option<ReadAlignment> = None;
+        let mut second: Option<i32> = None;
+        for (query, strand) in [(read, Strand::Forward), (reverse.as_str(), Strand::Reverse)] {
+            for (record, diagonal) in self.candidates(query)? {
+                let Some(alignment) = self.extend(query, strand, record, diagonal)? else {
+                    continue;
+                };
+                // Merged windows can still settle on the same placement
+                let same = |b: &ReadAlignment| {
+                    (b.record, b.offset, b.strand)
+                        == (alignment.record, alignment.offset, alignment.strand)
+                };
+                match &best {
+                    Some(b) if same(b) => {
+                        if alignment.score > b.score {
+                            best = Some(alignment);
+                        }
+                    }
+                    // Ties keep the forward strand and the better voted window
+                    Some(b) if alignment.score <= b.score => {
+                        second = second.max(Some(alignment.score));
+                    }
+                    _ => {
+                        second = second.max(best.as_ref().map(|b| b.score));
+                        best = Some(alignment);
+                    }
+                }
+            }
+        }
+        Ok(best.map(|b| ReadAlignment {
+            mapq: mapping_quality(b.score, second),
+            ..b
+        }))
+    }
+
+    /// Diagonals (record offset of the read start) voted for by the seeds,
+    /// with neighbouring diagonals merged, best voted first.
+    fn candidates(&self, query: &str) -> Result<Vec<(usize, isize)>, Box<dyn Error>> {
+        let seed_len = self.options.seed_len.min(query.len());
+        let mut votes = BTreeMap::new();
+        if seed_len == 0 {
+            return Ok(Vec::new());
+        }
+        let mut starts = (0..=query.len() - seed_len)
+            .step_by(self.options.seed_step)
+            .collect::<Vec<_>>();
+        // Always seed the end of the read as well
+        if starts.last() != Some(&(query.len() - seed_len)) {
+            starts.push(query.len() - seed_len);
+        }
+        for start in starts {
+            let positions = self.index.locate(&query[start..start + seed_len])?;
+            if positions.len() > self.options.max_seed_hits {
+                continue;
+            }
+            for position in positions {
+                if let Some((record, offset)) = self.index.records().resolve(position) {
+                    *votes
+                        .entry((record, offset as isize - start as isize))
+                        .or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut merged: Vec<((usize, isize), usize, usize)> = Vec::new();
+        for ((record, diagonal), count) in votes {
+            match merged.last_mut() {
+                Some(((r, d), best, total))
+                    if *r == record && diagonal - *d <= self.options.padding as isize =>
+                {
+                    *total += count;
+                    if count > *best {
+                        *best = count;
+                        *d = diagonal;
+                    }
+                }
+                _ => merged.push(((record, diagonal), count, count)),
+            }
+        }
+        merged.sort_by_key(|&(key, _, total)| (std::cmp::Reverse(total), key));
+        Ok(merged
+            .into_iter()
+            .take(self.options.max_candidates)
+            .map(|(key, _, _)| key)
+            .collect())
+    }
+
+    fn extend(
+        &self,
+        query: &str,
+        strand: Strand,
+        record: usize,
+        diagonal: isize,
+    ) -> Result<Option<ReadAlignment>, Box<dyn Error>> {
+        let text = &self.reference[record].text;
+        let padding = self.options.padding as isize;
+        let start = (diagonal - padding).clamp(0, text.len() as isize) as usize;
+        let end =
+            (diagonal + query.len() as isize + padding).clamp(0, text.len() as isize) as usize;
+        if start >= end {
+            return Ok(None);
+        }
+        let window = &text[start..end];
+
+        let result = match self.options.extension {
+            Extension::Fitting => {
+                banded_fitting_alignment(window, query, &self.scoring, Band::Adaptive)?
+            }
+            Extension::Local => local_alignment(window, query, &self.scoring)?,
+        };
+        let span = result.span();
+        if span.t.is_empty() {
+            return Ok(None);
+        }
+        let cigar = result
+            .cigar()?
+            .clipped(span.t.start, query.len() - span.t.end);
+        Ok(Some(ReadAlignment {
+            record,
+            offset: start + span.s.start,
+            strand,
+            score: *result.score(),
+            cigar,
+            mapq: 0,
+        }))
+    }
+}
+
+/// 60 for a unique placement, 0 when another placement scores as well, and
+/// in between in proportion to the score gap.
+fn mapping_quality(best: i32, second: Option<i32>) -> u8 {
+    match second {
+        None => 60,
+        Some(second) if second >= best => 0,
+        Some(second) => ((best - second) * 60 / best.max(1)).clamp(1, 60) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::{FMIndex, Strand};
+    use crate::mapping::aligner::{AlignerOptions, Extension, ReadAligner};
+    use crate::utils::{Fasta, DNA_BW_N};
+    use std::error::Error;
+
+    fn reference() -> Vec<Fasta> {
+        vec![
+            Fasta::new(
+                "chr1",
+                "GCTAAAGACAATTACATAACATACACGTCAGCACGAAACTTGTTGGCCCAGTGTGAATCG",
+            ),
+            Fasta::new(
+                "chr2",
+                "CTTAAGGGTTAAGTAAGTGTGATGCATACGCCTTTACTTGCTGTGTCCACCCCATCGGAC",
+            ),
+        ]
+    }
+
+    fn options(extension: Extension) -> AlignerOptions {
+        AlignerOptions {
+            seed_len: 8,
+            seed_step: 4,
+            padding: 4,
+            extension,
+            ..AlignerOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_read_aligner1() -> Result<(), Box<dyn Error>> {
+        let reference = reference();
+        let index = FMIndex::from_records(&reference, &DNA_BW_N, 4)?;
+        let aligner = ReadAligner::new(&index, &reference, options(Extension::Fitting))?;
+
+        // One mismatch
+        let hit = aligner.align("GATGCATACGCCATTACTTGCTGTG")?.unwrap();
+        assert_eq!(
+            (hit.record, hit.offset, hit.strand),
+            (1, 20, Strand::Forward)
+        );
+        assert_eq!((hit.score, hit.cigar.to_string()), (23, "25M".to_string()));
+        assert_eq!(hit.mapq, 60);
+
+        // One inserted base
+        let hit = aligner.align("ATTACATAACATACATCGTCAGCACGAAACT")?.unwrap();
+        assert_eq!(
+            (hit.record, hit.offset, hit.strand),
+            (0, 10, Strand::Forward)
+        );
+        assert_eq!(
+            (hit.score, hit.cigar.to_string()),
+            (28, "15M1I15M".to_string())
+        );
+
+        // Reverse complement of chr1[30..55]
+        let hit = aligner.align("CACACTGGGCCAACAAGTTTCGTGC")?.unwrap();
+        assert_eq!(
+            (hit.record, hit.offset, hit.strand),
+            (0, 30, Strand::Reverse)
+        );
+        assert_eq!((hit.score, hit.cigar.to_string()), (25, "25M".to_string()));
+
+        assert_eq!(aligner.align("AAAAAAAAAAAAAAAAAAAA")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_aligner2() -> Result<(), Box<dyn Error>> {
+        let reference = reference();
+        let index = FMIndex::from_records(&reference, &DNA_BW_N, 4)?;
+        let aligner = ReadAligner::new(&index, &reference, options(Extension::Local))?;
+        let hit = aligner
+            .align("TTTTTTTTCTTAAGGGTTAAGTAAGTGTGATGCATACG")?
+            .unwrap();
+        assert_eq!(
+            (hit.record, hit.offset, hit.strand),
+            (1, 0, Strand::Forward)
+        );
+        assert_eq!(
+            (hit.score, hit.cigar.to_string()),
+            (30, "8S30M".to_string())
+        );
+
+        assert!(ReadAligner::new(&index, &reference[..1], options(Extension::Local)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_aligner3() -> Result<(), Box<dyn Error>> {
+        // Ns in the read are mismatches on either strand
+        let reference = reference();
+        let index = FMIndex::from_records(&reference, &DNA_BW_N, 4)?;
+        let aligner = ReadAligner::new(&index, &reference, options(Extension::Fitting))?;
+        let hit = aligner.align("GATGCATACGCCNTTACTTGCTGTG")?.unwrap();
+        assert_eq!(
+            (hit.record, hit.offset, hit.strand),
+            (1, 20, Strand::Forward)
+        );
+        assert_eq!((hit.score, hit.cigar.to_string()), (23, "25M".to_string()));
+
+        let hit = aligner.align("CACACNGGGCCAACAAGTTTCGTGC")?.unwrap();
+        assert_eq!(
+            (hit.record, hit.offset, hit.strand),
+            (0, 30, Strand::Reverse)
+        );
+        assert_eq!((hit.score, hit.cigar.to_string()), (23, "25M".to_string()));
+        Ok(())
+    }
+}
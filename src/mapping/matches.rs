@@ -0,0 +1,214 @@
+use crate::bwt::Strand;
+use crate::mapping::sam::query_name;
+use std::error::Error;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Hits of a pattern on one strand. Positions are left out when only counts
+/// were asked for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StrandMatches {
+    pub count: usize,
+    pub positions: Option<Vec<usize>>,
+}
+
+impl StrandMatches {
+    pub fn from_count(count: usize) -> Self {
+        StrandMatches {
+            count,
+            positions: None,
+        }
+    }
+
+    pub fn from_positions(positions: Vec<usize>) -> Self {
+        StrandMatches {
+            count: positions.len(),
+            positions: Some(positions),
+        }
+    }
+}
+
+/// Exact hits of one input pattern: those of the pattern itself on the
+/// forward strand and those of its reverse complement on the reverse strand.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternMatches {
+    pub id: String,
+    pub pattern: String,
+    pub forward: StrandMatches,
+    pub reverse: StrandMatches,
+}
+
+impl PatternMatches {
+    pub fn new(title: &str, pattern: &str, forward: StrandMatches, reverse: StrandMatches) -> Self {
+        PatternMatches {
+            id: query_name(title),
+            pattern: pattern.to_string(),
+            forward,
+            reverse,
+        }
+    }
+
+    fn strands(&self) -> [(Strand, &StrandMatches); 2] {
+        [
+            (Strand::Forward, &self.forward),
+            (Strand::Reverse, &self.reverse),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MatchFormat {
+    Tsv,
+    Json,
+}
+
+impl FromStr for MatchFormat {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tsv" => Ok(MatchFormat::Tsv),
+            "json" => Ok(MatchFormat::Json),
+            _ => Err(format!("Unknown output format {}, expected tsv or json", s).into()),
+        }
+    }
+}
+
+pub fn write_matches(
+    writer: &mut impl Write,
+    matches: &[PatternMatches],
+    format: MatchFormat,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        MatchFormat::Tsv => write_tsv(writer, matches)?,
+        MatchFormat::Json => write_json(writer, matches)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// One line per pattern and strand; positions are comma separated, or `.`
+/// when there are none or they were not located.
+fn write_tsv(writer: &mut impl Write, matches: &[PatternMatches]) -> Result<(), Box<dyn Error>> {
+    writeln!(writer, "#id\tpattern\tstrand\tcount\tpositions")?;
+    for m in matches {
+        for (strand, hits) in m.strands() {
+            let positions = match &hits.positions {
+                Some(positions) if !positions.is_empty() => positions
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                _ => ".".to_string(),
+            };
+            writeln!(
+                writer,
+                "{}\t{}\t{}\t{}\t{}",
+                m.id, m.pattern, strand, hits.count, positions
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(writer: &mut impl Write, matches: &[PatternMatches]) -> Result<(), Box<dyn Error>> {
+    writeln!(writer, "[")?;
+    for (i, m) in matches.iter().enumerate() {
+        let strands = m
+            .strands()
+            .iter()
+            .map(|(strand, hits)| {
+                let name = match strand {
+                    Strand::Forward => "forward",
+                    Strand::Reverse => "reverse",
+                };
+                let positions = hits.positions.as_ref().map_or(String::new(), |positions| {
+                    let positions = positions.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                    format!(", \"positions\": [{}]", positions.join(", "))
+                });
+                format!("\"{}\": {{\"count\": {}{}}}", name, hits.count, positions)
+            })
+            .collect::<Vec<_>>();
+        let separator = if i + 1 < matches.len() { "," } else { "" };
+        writeln!(
+            writer,
+            "  {{\"id\": {}, \"pattern\": {}, {}}}{}",
+            json_string(&m.id),
+            json_string(&m.pattern),
+            strands.join(", "),
+            separator
+        )?;
+    }
+    writeln!(writer, "]")?;
+    Ok(())
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mapping::matches::{
+        json_string, write_matches, MatchFormat, PatternMatches, StrandMatches,
+    };
+    use std::error::Error;
+
+    #[test]
+    fn test_write_matches1() -> Result<(), Box<dyn Error>> {
+        let matches = vec![
+            PatternMatches::new(
+                "p1 first primer",
+                "ACG",
+                StrandMatches::from_positions(vec![3, 17]),
+                StrandMatches::from_positions(vec![]),
+            ),
+            PatternMatches::new(
+                "p2",
+                "TTA",
+                StrandMatches::from_count(1),
+                StrandMatches::from_count(2),
+            ),
+        ];
+        let mut tsv = Vec::new();
+        write_matches(&mut tsv, &matches, MatchFormat::Tsv)?;
+        assert_eq!(
+            String::from_utf8(tsv)?,
+            "#id\tpattern\tstrand\tcount\tpositions\n\
+             p1\tACG\t+\t2\t3,17\n\
+             p1\tACG\t-\t0\t.\n\
+             p2\tTTA\t+\t1\t.\n\
+             p2\tTTA\t-\t2\t.\n"
+        );
+
+        let mut json = Vec::new();
+        write_matches(&mut json, &matches, "JSON".parse()?)?;
+        assert_eq!(
+            String::from_utf8(json)?,
+            "[\n  \
+             {\"id\": \"p1\", \"pattern\": \"ACG\", \
+             \"forward\": {\"count\": 2, \"positions\": [3, 17]}, \
+             \"reverse\": {\"count\": 0, \"positions\": []}},\n  \
+             {\"id\": \"p2\", \"pattern\": \"TTA\", \
+             \"forward\": {\"count\": 1}, \"reverse\": {\"count\": 2}}\n\
+             ]\n"
+        );
+        assert_eq!(json_string("a\"b\\\t"), "\"a\\\"b\\\\\\t\"");
+        assert!("xml".parse::<MatchFormat>().is_err());
+        Ok(())
+    }
+}
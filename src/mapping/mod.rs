@@ -0,0 +1,7 @@
+mod aligner;
+mod matches;
+mod sam;
+
+pub use aligner::{AlignerOptions, Extension, ReadAligner};
+pub use matches::{write_matches, MatchFormat, PatternMatches, StrandMatches};
+pub use sam::{SamRecord, SamWriter};
@@ -0,0 +1,254 @@
+use crate::bwt::{RecordHit, RecordTable, Strand};
+use crate::manhattan::{Cigar, CigarOp};
+use crate::mapping::aligner::ReadAlignment;
+use crate::utils::iupac_complement;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub const FLAG_UNMAPPED: u16 = 0x4;
+pub const FLAG_REVERSE: u16 = 0x10;
+pub const FLAG_SECONDARY: u16 = 0x100;
+
+const SAM_VERSION: &str = "1.6";
+
+/// One alignment line of a SAM file. `pos` is 1-based and 0 when unmapped;
+/// `seq` and `qual` are given on the forward strand of the reference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamRecord {
+    pub qname: String,
+    pub flag: u16,
+    pub rname: String,
+    pub pos: usize,
+    pub mapq: u8,
+    pub cigar: Cigar,
+    pub seq: String,
+    pub qual: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl SamRecord {
+    pub fn unmapped(title: &str, seq: &str, qual: Option<&str>) -> Self {
+        SamRecord {
+            qname: query_name(title),
+            flag: FLAG_UNMAPPED,
+            rname: "*".to_string(),
+            pos: 0,
+            mapq: 0,
+            cigar: Cigar::default(),
+            seq: seq.to_string(),
+            qual: qual.map(str::to_string),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Record for the placement of `read` found by the read aligner.
+    pub fn from_alignment(
+        title: &str,
+        read: &str,
+        qual: Option<&str>,
+        alignment: &ReadAlignment,
+        records: &RecordTable,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (seq, qual) = oriented(read, qual, alignment.strand)?;
+        Ok(SamRecord {
+            qname: query_name(title),
+            flag: strand_flag(alignment.strand),
+            rname: records.name(alignment.record).to_string(),
+            pos: alignment.offset + 1,
+            mapq: alignment.mapq,
+            cigar: alignment.cigar.clone(),
+            seq,
+            qual,
+            tags: vec![format!("AS:i:{}", alignment.score)],
+        })
+    }
+
+    /// Record for an FM-index hit of `pattern` with `mismatches` substitutions.
+    /// Every hit after the primary one is flagged as secondary.
+    pub fn from_hit(
+        title: &str,
+        pattern: &str,
+        qual: Option<&str>,
+        hit: &RecordHit,
+        mismatches: usize,
+        mapq: u8,
+        secondary: bool,
+    ) -> Result<Self, Box<dyn Error>> {
+        let (seq, qual) = oriented(pattern, qual, hit.strand)?;
+        let mut cigar = Cigar::default();
+        cigar.push(pattern.len(), CigarOp::Match);
+        Ok(SamRecord {
+            qname: query_name(title),
+            flag: strand_flag(hit.strand) | if secondary { FLAG_SECONDARY } else { 0 },
+            rname: hit.title.clone(),
+            pos: hit.offset + 1,
+            mapq,
+            cigar,
+            seq,
+            qual,
+            tags: vec![format!("NM:i:{}", mismatches)],
+        })
+    }
+}
+
+impl Display for SamRecord {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}\t*\t0\t0\t{}\t{}",
+            self.qname,
+            self.flag,
+            self.rname,
+            self.pos,
+            self.mapq,
+            self.cigar,
+            if self.seq.is_empty() { "*" } else { &self.seq },
+            self.qual.as_deref().unwrap_or("*")
+        )?;
+        for tag in &self.tags {
+            write!(f, "\t{}", tag)?;
+        }
+        Ok(())
+    }
+}
+
+/// SAM QNAME: the first word of a FASTA title, as names cannot hold spaces.
+pub(crate) fn query_name(title: &str) -> String {
+    title.split_whitespace().next().unwrap_or("*").to_string()
+}
+
+fn strand_flag(strand: Strand) -> u16 {
+    match strand {
+        Strand::Forward => 0,
+        Strand::Reverse => FLAG_REVERSE,
+    }
+}
+
+fn oriented(
+    seq: &str,
+    qual: Option<&str>,
+    strand: Strand,
+) -> Result<(String, Option<String>), Box<dyn Error>> {
+    Ok(match strand {
+        Strand::Forward => (seq.to_string(), qual.map(str::to_string)),
+        Strand::Reverse => (
+            iupac_complement(seq)?,
+            qual.map(|q| q.chars().rev().collect()),
+        ),
+    })
+}
+
+/// Writes a SAM header with one @SQ line per indexed record, then records.
+pub struct SamWriter<W: Write> {
+    writer: W,
+}
+
+impl SamWriter<BufWriter<File>> {
+    pub fn create(file: impl AsRef<Path>, records: &RecordTable) -> Result<Self, Box<dyn Error>> {
+        Self::new(BufWriter::new(File::create(file)?), records)
+    }
+}
+
+impl<W: Write> SamWriter<W> {
+    pub fn new(mut writer: W, records: &RecordTable) -> Result<Self, Box<dyn Error>> {
+        writeln!(writer, "@HD\tVN:{}\tSO:unsorted", SAM_VERSION)?;
+        for record in 0..records.len() {
+            writeln!(
+                writer,
+                "@SQ\tSN:{}\tLN:{}",
+                records.name(record),
+                records.record_len(record)
+            )?;
+        }
+        writeln!(
+            writer,
+            "@PG\tID:{0}\tPN:{0}\tVN:{1}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        )?;
+        Ok(SamWriter { writer })
+    }
+
+    pub fn write(&mut self, record: &SamRecord) -> Result<(), Box<dyn Error>> {
+        writeln!(self.writer, "{}", record)?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, Box<dyn Error>> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bwt::{FMIndex, NPolicy, RecordHit, Strand};
+    use crate::manhattan::Cigar;
+    use crate::mapping::aligner::ReadAlignment;
+    use crate::mapping::sam::{SamRecord, SamWriter};
+    use crate::utils::{Fasta, DNA_BW};
+    use std::error::Error;
+
+    #[test]
+    fn test_sam_writer1() -> Result<(), Box<dyn Error>> {
+        let records = vec![
+            Fasta::new("chr1 first", "ACGTTGCA"),
+            Fasta::new("chr2", "TTGCAACG"),
+        ];
+        let index = FMIndex::from_records(&records, &DNA_BW, 2)?;
+        let mut writer = SamWriter::new(Vec::new(), index.records())?;
+
+        let alignment = ReadAlignment {
+            record: 1,
+            offset: 2,
+            strand: Strand::Reverse,
+            score: 3,
+            cigar: Cigar::from_alignment("GCA-A", "GCATA")?,
+            mapq: 60,
+        };
+        writer.write(&SamRecord::from_alignment(
+            "read1 sample",
+            "TATGC",
+            Some("ABCDE"),
+            &alignment,
+            index.records(),
+        )?)?;
+        let hit = RecordHit::new("chr1", 0, Strand::Forward);
+        writer.write(&SamRecord::from_hit("p1", "ACG", None, &hit, 1, 0, true)?)?;
+        writer.write(&SamRecord::unmapped("read2", "GGGG", None))?;
+
+        let sam = String::from_utf8(writer.finish()?)?;
+        let lines = sam.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "@HD\tVN:1.6\tSO:unsorted");
+        assert_eq!(lines[1], "@SQ\tSN:chr1\tLN:8");
+        assert_eq!(lines[2], "@SQ\tSN:chr2\tLN:8");
+        assert!(lines[3].starts_with("@PG\tID:"));
+        assert_eq!(
+            &lines[4..],
+            [
+                "read1\t16\tchr2\t3\t60\t3M1I1M\t*\t0\t0\tGCATA\tEDCBA\tAS:i:3",
+                "p1\t256\tchr1\t1\t0\t3M\t*\t0\t0\tACG\t*\tNM:i:1",
+                "read2\t4\t*\t0\t0\t*\t*\t0\t0\tGGGG\t*",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sam_writer2() -> Result<(), Box<dyn Error>> {
+        // A degenerate pattern that only hits the reverse strand
+        let records = vec![Fasta::new("chr1", "TTTTGTAGGCTTTT")];
+        let index = FMIndex::from_records(&records, &DNA_BW, 2)?;
+        let hits = index.locate_records_degenerate("GCNTAC", NPolicy::NeverMatch)?;
+        assert_eq!(hits, vec![RecordHit::new("chr1", 4, Strand::Reverse)]);
+        let record = SamRecord::from_hit("p2", "GCNTAC", Some("ABCDEF"), &hits[0], 0, 60, false)?;
+        assert_eq!(
+            record.to_string(),
+            "p2\t16\tchr1\t5\t60\t6M\t*\t0\t0\tGTANGC\tFEDCBA\tNM:i:0"
+        );
+        Ok(())
+    }
+}
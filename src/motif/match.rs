@@ -1,7 +1,7 @@
 use crate::utils::hamming::hamming_distance;
 use std::error::Error;
 
-fn approximate_pattern_matching(
+pub(crate) fn approximate_pattern_matching(
     text: &str,
     pattern: &str,
     max_diff: usize,
@@ -1,7 +1,7 @@
 mod clumps;
 mod enumeration;
 mod frequency;
-mod r#match;
+pub(crate) mod r#match;
 mod mismatch;
 mod neighbors;
 mod pattern;
@@ -0,0 +1,82 @@
+use crate::bwt::{RecordTable, RepeatIndex};
+use crate::utils::{print_hms, Alphabet, Fasta};
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct RepeatsArgs {
+    #[arg(long, required = true, value_name = "genome_file")]
+    genome_file: String,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "20")]
+    min_length: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "6")]
+    max_period: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "3")]
+    min_copies: usize,
+
+    #[arg(
+        long,
+        required = false,
+        value_name = "ALPHABET",
+        default_value = "iupac"
+    )]
+    alphabet: String,
+}
+
+impl RepeatsArgs {
+    pub fn get_genome_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.genome_file.to_owned())
+    }
+
+    pub fn get_alphabet(&self) -> Result<Alphabet, Box<dyn Error>> {
+        Alphabet::named(&self.alphabet)
+    }
+}
+
+fn coordinate(records: &RecordTable, position: usize) -> String {
+    match records.resolve(position) {
+        Some((record, offset)) => format!("{}:{}", records.name(record), offset),
+        None => position.to_string(),
+    }
+}
+
+/// Prints one line per repeat: kind, length, period (tandem repeats only) and
+/// the `record:offset` coordinates of its occurrences.
+pub fn run_repeats(args: RepeatsArgs) -> Result<(), Box<dyn Error>> {
+    let records = Fasta::read_file(args.get_genome_file()?)?
+        .iter()
+        .map(|f| f.upper())
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let index = RepeatIndex::from_records(&records, &args.get_alphabet()?)?;
+    let table = index.records();
+
+    let maximal = index.maximal_repeats(args.min_length);
+    let longest = index.longest_repeats();
+    for (kind, repeats) in [("longest", longest), ("maximal", maximal)] {
+        for repeat in repeats {
+            let positions = repeat
+                .positions
+                .iter()
+                .map(|&p| coordinate(table, p))
+                .collect::<Vec<_>>();
+            println!("{}\t{}\t-\t{}", kind, repeat.length, positions.join(","));
+        }
+    }
+    for tandem in index.tandem_repeats(args.max_period, args.min_copies) {
+        println!(
+            "tandem\t{}\t{}\t{}",
+            tandem.length,
+            tandem.period,
+            coordinate(table, tandem.start)
+        );
+    }
+    print_hms(&start);
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::bwt::{BidirectionalIndex, Strand};
+use crate::utils::{iupac_complement, print_hms, Fasta, IUPAC_ALPHABET};
+use clap::{value_parser, Parser};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::Instant;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct SmemsArgs {
+    #[arg(long, required = true, value_name = "genome_file")]
+    genome_file: String,
+
+    #[arg(long, required = true, value_name = "reads_file")]
+    reads_file: String,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "19")]
+    min_length: usize,
+
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "32")]
+    sa_sample: usize,
+
+    /// SMEMs occurring more often than this are printed without positions.
+    #[arg(long, required = false, value_parser = value_parser!(usize), default_value = "20")]
+    max_positions: usize,
+
+    /// Write the SMEMs here instead of to standard output.
+    #[arg(long, required = false, value_name = "OUTPUT")]
+    output: Option<String>,
+}
+
+impl SmemsArgs {
+    pub fn get_genome_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.genome_file.to_owned())
+    }
+
+    pub fn get_reads_file(&self) -> Result<String, Box<dyn Error>> {
+        Ok(self.reads_file.to_owned())
+    }
+
+    pub fn get_output(&self) -> Result<Option<String>, Box<dyn Error>> {
+        Ok(self.output.to_owned())
+    }
+}
+
+/// Prints one line per SMEM of each read on either strand: read title, strand,
+/// start and end on the read, occurrence count and `record:offset:strand`
+/// hits. With `--output` the lines go to that file instead.
+pub fn run_smems(args: SmemsArgs) -> Result<(), Box<dyn Error>> {
+    let records = Fasta::read_file(args.get_genome_file()?)?
+        .iter()
+        .map(|f| f.upper())
+        .collect::<Vec<_>>();
+    let reads = Fasta::read_file(args.get_reads_file()?)?;
+
+    let start = Instant::now();
+    let index = BidirectionalIndex::from_records(&records, &IUPAC_ALPHABET, args.sa_sample)?;
+    let mut out: Box<dyn Write> = match args.get_output()? {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    for read in reads.iter().map(|r| r.upper()) {
+        let reverse = iupac_complement(&read.text)?;
+        for (query, strand) in [(&read.text, Strand::Forward), (&reverse, Strand::Reverse)] {
+            for smem in index.smems(query, args.min_length, args.max_positions)? {
+                // Read coordinates always refer to the read as given
+                let (from, to) = match strand {
+                    Strand::Forward => (smem.query.start, smem.query.end),
+                    Strand::Reverse => {
+                        (query.len() - smem.query.end, query.len() - smem.query.start)
+                    }
+                };
+                let hits = smem
+                    .positions
+                    .iter()
+                    .filter_map(|&p| index.records().hit(p, strand))
+                    .map(|h| h.to_string())
+                    .collect::<Vec<_>>();
+                writeln!(
+                    out,
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    read.title,
+                    strand,
+                    from,
+                    to,
+                    smem.count,
+                    hits.join(",")
+                )?;
+            }
+        }
+    }
+    out.flush()?;
+    if args.output.is_some() {
+        print_hms(&start);
+    }
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+
+const NO_RANK: u8 = u8::MAX;
+
+/// Maps the symbols of a text to dense ranks `0..size()` in symbol order, as
+/// needed for bucketing in SA-IS and for counting in the FM-index.
+pub trait SymbolMap<T>: Sync {
+    fn rank(&self, symbol: &T) -> Option<usize>;
+
+    fn size(&self) -> usize;
+
+    /// Position of the first symbol of `text` that has no rank.
+    fn first_invalid(&self, text: &[T]) -> Option<usize> {
+        text.iter().position(|s| self.rank(s).is_none())
+    }
+}
+
+impl<T: Eq + Hash + Sync> SymbolMap<T> for HashMap<T, usize> {
+    fn rank(&self, symbol: &T) -> Option<usize> {
+        self.get(symbol).copied()
+    }
+
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidSymbolError {
+    symbol: u8,
+    position: usize,
+}
+
+impl Error for InvalidSymbolError {}
+
+impl InvalidSymbolError {
+    pub fn new(symbol: u8, position: usize) -> Self {
+        InvalidSymbolError { symbol, position }
+    }
+}
+
+impl Display for InvalidSymbolError {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Invalid symbol '{}' at position {}",
+            self.symbol.escape_ascii(),
+            self.position
+        )
+    }
+}
+
+/// Byte alphabet backed by a 256-entry rank table. The sentinel always has
+/// rank 0 and every other symbol sorts after it, so ranks follow byte order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Alphabet {
+    sentinel: u8,
+    size: usize,
+    ranks: [u8; 256],
+    symbols: [u8; 256],
+}
+
+pub const DNA_ALPHABET: Alphabet = Alphabet::new(b'$', b"ACGT");
+pub const IUPAC_ALPHABET: Alphabet = Alphabet::new(b'$', b"ABCDGHKMNRSTVWY");
+pub const RNA_ALPHABET: Alphabet = Alphabet::new(b'$', b"ACGU");
+pub const PROTEIN_ALPHABET: Alphabet = Alphabet::new(b'$', b"ACDEFGHIKLMNPQRSTVWXY");
+
+impl Alphabet {
+    /// Builds the alphabet at compile time. `symbols` must be strictly
+    /// ascending and sort after `sentinel`.
+    pub const fn new(sentinel: u8, symbols: &[u8]) -> Self {
+        assert!(symbols.len() < NO_RANK as usize, "Too many symbols");
+        let mut ranks = [NO_RANK; 256];
+        let mut table = [0; 256];
+        ranks[sentinel as usize] = 0;
+        table[0] = sentinel;
+        let mut i = 0;
+        while i < symbols.len() {
+            let previous = if i == 0 { sentinel } else { symbols[i - 1] };
+            assert!(
+                symbols[i] > previous,
+                "Symbols must ascend after the sentinel"
+            );
+            ranks[symbols[i] as usize] = (i + 1) as u8;
+            table[i + 1] = symbols[i];
+            i += 1;
+        }
+        Alphabet {
+            sentinel,
+            size: symbols.len() + 1,
+            ranks,
+            symbols: table,
+        }
+    }
+
+    /// Alphabet from its symbols in rank order, sentinel first, as stored by
+    /// [`Alphabet::symbols`].
+    pub fn from_symbols(symbols: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (&sentinel, rest) = symbols.split_first().ok_or("Alphabet has no sentinel")?;
+        if rest.len() >= NO_RANK as usize {
+            return Err(format!("Alphabet has {} symbols", symbols.len()).into());
+        }
+        if symbols.windows(2).any(|w| w[0] >= w[1]) {
+            return Err("Alphabet symbols are not in ascending order".into());
+        }
+        Ok(Self::new(sentinel, rest))
+    }
+
+    /// Predefined alphabet by name: `dna`, `iupac`, `rna` or `protein`.
+    pub fn named(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name.to_ascii_lowercase().as_str() {
+            "dna" => Ok(DNA_ALPHABET),
+            "iupac" => Ok(IUPAC_ALPHABET),
+            "rna" => Ok(RNA_ALPHABET),
+            "protein" => Ok(PROTEIN_ALPHABET),
+            _ => Err(format!("Unknown alphabet {}", name).into()),
+        }
+    }
+
+    /// Copy of the alphabet with `symbol` appended as its largest symbol.
+    pub fn with_symbol(&self, symbol: u8) -> Result<Self, Box<dyn Error>> {
+        let mut symbols = self.symbols().to_vec();
+        symbols.push(symbol);
+        Self::from_symbols(&symbols).map_err(|_| {
+            format!(
+                "Symbol '{}' does not sort after every symbol of the alphabet",
+                symbol.escape_ascii()
+            )
+            .into()
+        })
+    }
+
+    pub fn sentinel(&self) -> u8 {
+        self.sentinel
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Symbols in rank order, starting with the sentinel.
+    pub fn symbols(&self) -> &[u8] {
+        &self.symbols[..self.size]
+    }
+
+    pub fn symbol(&self, rank: usize) -> u8 {
+        self.symbols[rank]
+    }
+
+    pub fn rank(&self, symbol: u8) -> Option<usize> {
+        match self.ranks[symbol as usize] {
+            NO_RANK => None,
+            rank => Some(rank as usize),
+        }
+    }
+
+    pub fn contains(&self, symbol: u8) -> bool {
+        self.ranks[symbol as usize] != NO_RANK
+    }
+
+    /// Checks that every byte of `text` belongs to the alphabet.
+    pub fn validate(&self, text: &[u8]) -> Result<(), InvalidSymbolError> {
+        match self.first_invalid(text) {
+            Some(position) => Err(InvalidSymbolError::new(text[position], position)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl SymbolMap<u8> for Alphabet {
+    fn rank(&self, symbol: &u8) -> Option<usize> {
+        Alphabet::rank(self, *symbol)
+    }
+
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn first_invalid(&self, text: &[u8]) -> Option<usize> {
+        text.iter().position(|&s| !self.contains(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::alphabet::{Alphabet, InvalidSymbolError, IUPAC_ALPHABET, RNA_ALPHABET};
+    use std::error::Error;
+
+    #[test]
+    fn test_alphabet1() -> Result<(), Box<dyn Error>> {
+        let alphabet = Alphabet::new(b'$', b"ACGT");
+        assert_eq!(alphabet.size(), 5);
+        assert_eq!(alphabet.symbols(), b"$ACGT");
+        assert_eq!(alphabet.rank(b'$'), Some(0));
+        assert_eq!(alphabet.rank(b'G'), Some(3));
+        assert_eq!(alphabet.rank(b'N'), None);
+        assert_eq!(alphabet.symbol(4), b'T');
+        assert_eq!(Alphabet::from_symbols(b"$ACGT")?, alphabet);
+        assert_eq!(Alphabet::named("DNA")?, alphabet);
+        assert!(Alphabet::from_symbols(b"$AGC").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_alphabet_validate1() {
+        assert_eq!(IUPAC_ALPHABET.validate(b"ACGTNNRYK"), Ok(()));
+        assert_eq!(
+            RNA_ALPHABET.validate(b"ACGUUT"),
+            Err(InvalidSymbolError::new(b'T', 5))
+        );
+    }
+
+    #[test]
+    fn test_alphabet_with_symbol1() -> Result<(), Box<dyn Error>> {
+        let alphabet = RNA_ALPHABET.with_symbol(b'|')?;
+        assert_eq!(alphabet.symbols(), b"$ACGU|");
+        assert!(RNA_ALPHABET.with_symbol(b'C').is_err());
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::utils::alphabet::{Alphabet, DNA_ALPHABET};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 
@@ -12,6 +13,28 @@ pub static DNA_COMPLEMENT_MAP: Lazy<HashMap<char, char>> = Lazy::new(|| {
     m
 });
 
+/// Complements of the IUPAC nucleotide codes: each ambiguity code maps to the
+/// code of the complementary base set.
+pub static IUPAC_COMPLEMENT_MAP: Lazy<HashMap<char, char>> = Lazy::new(|| {
+    let pairs = [
+        ('A', 'T'),
+        ('C', 'G'),
+        ('R', 'Y'),
+        ('K', 'M'),
+        ('B', 'V'),
+        ('D', 'H'),
+        ('S', 'S'),
+        ('W', 'W'),
+        ('N', 'N'),
+    ];
+    let mut m = HashMap::new();
+    for (a, b) in pairs {
+        m.insert(a, b);
+        m.insert(b, a);
+    }
+    m
+});
+
 pub static DNA_INDEX: Lazy<HashMap<char, usize>> = Lazy::new(|| {
     let mut m = HashMap::new();
     m.insert('A', 0);
@@ -30,33 +53,9 @@ pub static DNA_BYTES: Lazy<HashMap<u8, usize>> = Lazy::new(|| {
     m
 });
 
-pub static DNA_BW: Lazy<HashMap<u8, usize>> = Lazy::new(|| {
-    let mut m = HashMap::new();
-    m.insert(b'$', 0);
-    m.insert(b'A', 1);
-    m.insert(b'C', 2);
-    m.insert(b'G', 3);
-    m.insert(b'T', 4);
-    m
-});
+pub const DNA_BW: Alphabet = DNA_ALPHABET;
 
-pub static DNA_BW_N: Lazy<HashMap<u8, usize>> = Lazy::new(|| {
-    let mut m = HashMap::new();
-    m.insert(b'$', 0);
-    m.insert(b'A', 1);
-    m.insert(b'B', 2);
-    m.insert(b'C', 3);
-    m.insert(b'G', 4);
-    m.insert(b'K', 5);
-    m.insert(b'M', 6);
-    m.insert(b'N', 7);
-    m.insert(b'R', 8);
-    m.insert(b'S', 9);
-    m.insert(b'T', 10);
-    m.insert(b'W', 11);
-    m.insert(b'Y', 12);
-    m
-});
+pub const DNA_BW_N: Alphabet = Alphabet::new(b'$', b"ABCGKMNRSTWY");
 
 pub static DNA_BYTES_N: Lazy<HashMap<u8, usize>> = Lazy::new(|| {
     let mut m = HashMap::new();
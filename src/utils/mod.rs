@@ -1,4 +1,4 @@
-mod blosum;
+pub mod alphabet;
 mod count;
 mod dna;
 mod fasta;
@@ -6,6 +6,7 @@ mod graph;
 pub mod hamming;
 mod kmp;
 mod nucleotide;
+mod reader;
 mod reverse;
 mod rna;
 mod time;
@@ -13,12 +14,13 @@ mod transpose;
 mod union;
 mod vec;
 
-pub use blosum::blosum_matrix;
+pub use alphabet::{Alphabet, InvalidSymbolError, SymbolMap, IUPAC_ALPHABET};
 pub use dna::{DNA, DNA_BW, DNA_BW_N, DNA_BYTES, DNA_BYTES_N, DNA_INDEX};
 pub use fasta::Fasta;
 pub use graph::{add_weighted_edge_pair, Graph, WeightedGraph};
 pub use nucleotide::{InvalidNucleotideError, InvalidNucleotidePositionError};
-pub use reverse::dna_complement;
+pub use reader::{SequenceReader, SequenceRecord};
+pub use reverse::{dna_complement, iupac_complement};
 pub use rna::dna_to_rna;
 pub use time::print_hms;
 pub use transpose::transpose;
@@ -0,0 +1,169 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Streams the records of a FASTA or FASTQ file one at a time, so that files
+/// of millions of reads never have to be held in memory. Each record's format
+/// follows its header: `>` for FASTA and `@` for FASTQ.
+pub struct SequenceReader<R: BufRead> {
+    reader: R,
+    /// A header line read ahead while collecting the previous FASTA record.
+    pending: Option<String>,
+    line_number: usize,
+}
+
+/// A FASTA or FASTQ record; only FASTQ records carry a quality string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SequenceRecord {
+    pub(crate) title: String,
+    pub(crate) text: String,
+    pub(crate) quality: Option<String>,
+}
+
+impl SequenceRecord {
+    pub(crate) fn new(
+        title: impl Into<String>,
+        text: impl Into<String>,
+        quality: Option<&str>,
+    ) -> Self {
+        SequenceRecord {
+            title: title.into(),
+            text: text.into(),
+            quality: quality.map(str::to_string),
+        }
+    }
+
+    pub(crate) fn upper(&self) -> Self {
+        SequenceRecord {
+            text: self.text.to_uppercase(),
+            ..self.clone()
+        }
+    }
+}
+
+impl SequenceReader<BufReader<File>> {
+    pub fn open(file: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::new(BufReader::new(File::open(file)?)))
+    }
+}
+
+impl<R: BufRead> SequenceReader<R> {
+    pub fn new(reader: R) -> Self {
+        SequenceReader {
+            reader,
+            pending: None,
+            line_number: 0,
+        }
+    }
+
+    /// Up to `size` further records; empty once the input is exhausted.
+    pub fn next_chunk(&mut self, size: usize) -> Result<Vec<SequenceRecord>, Box<dyn Error>> {
+        self.by_ref().take(size).collect()
+    }
+
+    fn next_line(&mut self) -> Result<Option<String>, Box<dyn Error>> {
+        if let Some(line) = self.pending.take() {
+            return Ok(Some(line));
+        }
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        self.line_number += 1;
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        Ok(Some(line))
+    }
+
+    fn next_record(&mut self) -> Result<Option<SequenceRecord>, Box<dyn Error>> {
+        let header = loop {
+            match self.next_line()? {
+                None => return Ok(None),
+                Some(line) if line.trim().is_empty() => continue,
+                Some(line) => break line,
+            }
+        };
+        if let Some(title) = header.strip_prefix('>') {
+            let mut text = String::new();
+            while let Some(line) = self.next_line()? {
+                if line.starts_with(['>', '@']) {
+                    self.pending = Some(line);
+                    break;
+                }
+                text.push_str(line.trim());
+            }
+            return Ok(Some(SequenceRecord::new(title, text, None)));
+        }
+        if let Some(title) = header.strip_prefix('@') {
+            let text = self
+                .next_line()?
+                .ok_or("FASTQ record ends before its sequence")?;
+            let separator = self.next_line()?.unwrap_or_default();
+            if !separator.starts_with('+') {
+                return Err(format!("Expected '+' on line {}", self.line_number).into());
+            }
+            let quality = self.next_line()?.unwrap_or_default();
+            if quality.len() != text.len() {
+                return Err(format!(
+                    "Quality on line {} does not match the sequence length",
+                    self.line_number
+                )
+                .into());
+            }
+            return Ok(Some(SequenceRecord::new(title, text, Some(&quality))));
+        }
+        Err(format!(
+            "Line {} starts neither a FASTA nor a FASTQ record",
+            self.line_number
+        )
+        .into())
+    }
+}
+
+impl<R: BufRead> Iterator for SequenceReader<R> {
+    type Item = Result<SequenceRecord, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::reader::{SequenceReader, SequenceRecord};
+    use std::error::Error;
+
+    #[test]
+    fn test_sequence_reader1() -> Result<(), Box<dyn Error>> {
+        let content = ">r1 first\nACGT\nTTGA\n\n>r2\r\nGGCC\r\n@r3 fastq\nACGTN\n+\nIIIII\n@r4\nA\n+r4\n#\n>r5\n";
+        let mut reader = SequenceReader::new(content.as_bytes());
+        assert_eq!(
+            reader.next_chunk(2)?,
+            vec![
+                SequenceRecord::new("r1 first", "ACGTTTGA", None),
+                SequenceRecord::new("r2", "GGCC", None)
+            ]
+        );
+        assert_eq!(
+            reader.next_chunk(5)?,
+            vec![
+                SequenceRecord::new("r3 fastq", "ACGTN", Some("IIIII")),
+                SequenceRecord::new("r4", "A", Some("#")),
+                SequenceRecord::new("r5", "", None),
+            ]
+        );
+        assert_eq!(reader.next_chunk(5)?, vec![]);
+
+        let truncated = "@r1\nACGT\n+\nIII\n";
+        assert!(SequenceReader::new(truncated.as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+        assert!(SequenceReader::new("ACGT\n".as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use crate::utils::dna::DNA_COMPLEMENT_MAP;
+use crate::utils::dna::{DNA_COMPLEMENT_MAP, IUPAC_COMPLEMENT_MAP};
 use crate::utils::rna::RNA_COMPLEMENT_MAP;
 use crate::utils::InvalidNucleotidePositionError;
 use std::collections::HashMap;
@@ -28,12 +28,17 @@ pub fn dna_complement(pattern: &str) -> Result<String, InvalidNucleotidePosition
     reverse_complement(pattern, &DNA_COMPLEMENT_MAP)
 }
 
+/// Reverse complement of a pattern that may hold IUPAC ambiguity codes.
+pub fn iupac_complement(pattern: &str) -> Result<String, InvalidNucleotidePositionError> {
+    reverse_complement(pattern, &IUPAC_COMPLEMENT_MAP)
+}
+
 pub fn rna_complement(pattern: &str) -> Result<String, InvalidNucleotidePositionError> {
     reverse_complement(pattern, &RNA_COMPLEMENT_MAP)
 }
 #[cfg(test)]
 mod tests {
-    use crate::utils::reverse::{dna_complement, InvalidNucleotidePositionError};
+    use crate::utils::reverse::{dna_complement, iupac_complement, InvalidNucleotidePositionError};
     use std::error::Error;
 
     #[test]
@@ -59,4 +64,14 @@ mod tests {
         assert_eq!(dna_reverse, InvalidNucleotidePositionError::new('D', 1));
         Ok(())
     }
+
+    #[test]
+    fn test_iupac_complement1() -> Result<(), Box<dyn Error>> {
+        assert_eq!(iupac_complement("ACGTRYKMSWBDHVN")?, "NBDHVWSKMRYACGT");
+        assert_eq!(
+            iupac_complement("ACXT").unwrap_err(),
+            InvalidNucleotidePositionError::new('X', 2)
+        );
+        Ok(())
+    }
 }